@@ -51,7 +51,21 @@ fn generate_fn(prefix: &str, field_type: &Ident, field_metadata: &FieldMetadata)
     Ident::new(&fn_name, Span::call_site())
 }
 
+/// The number of bytes read for a field of the primitive type.
+fn field_size(field_type: &Ident) -> Option<usize> {
+    match field_type.to_string().as_str() {
+        "bool" | "u8" | "i8" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" => Some(4),
+        "u64" | "i64" => Some(8),
+        _ => None,
+    }
+}
+
+/// Generates the code reading a field, which returns a `DecodeError` rather than panicking when
+/// the payload ends early.
 fn generate_quote_read(
+    field: &syn::Field,
     field_name: &Ident,
     field_type: &Ident,
     field_metadata: &FieldMetadata,
@@ -59,33 +73,41 @@ fn generate_quote_read(
     if field_type == "bool" {
         if let Some(ref transform) = field_metadata.transform {
             quote! {
-                self.#field_name = src.get_u8t(#transform) == 1;
+                self.#field_name = src.get_checked(1, |src| src.get_u8t(#transform))? == 1;
             }
         } else {
             quote! {
-                self.#field_name = src.get_u8() == 1;
+                self.#field_name = src.get_checked(1, |src| src.get_u8())? == 1;
             }
         }
     } else if field_type == "String" {
         if field_metadata.base37 {
             quote! {
-                let base37_encoded = src.get_u64();
-                self.#field_name = mithril_text::decode_base37(base37_encoded)?;
+                let base37_encoded = src.get_checked(8, |src| src.get_u64())?;
+                self.#field_name = mithril_text::decode_base37(base37_encoded)
+                    .map_err(|_| crate::DecodeError::InvalidName(base37_encoded))?;
             }
         } else {
             quote! {
-                self.#field_name = src.get_rs_string();
+                self.#field_name = src.try_get_rs_string()?;
             }
         }
     } else {
+        let size = match field_size(field_type) {
+            Some(size) => size,
+            None => {
+                return syn::Error::new_spanned(field, "Packet cannot read a field of this type")
+                    .to_compile_error()
+            }
+        };
         let fn_ident = generate_fn("get", field_type, &field_metadata);
         if let Some(ref transform) = field_metadata.transform {
             quote! {
-                self.#field_name = src.#fn_ident(#transform);
+                self.#field_name = src.get_checked(#size, |src| src.#fn_ident(#transform))?;
             }
         } else {
             quote! {
-                self.#field_name = src.#fn_ident();
+                self.#field_name = src.get_checked(#size, |src| src.#fn_ident())?;
             }
         }
     }
@@ -234,7 +256,7 @@ pub fn derive_packet(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
         }
 
         let write = generate_quote_write(&field_name, field_type_name, &field_metadata);
-        let read = generate_quote_read(&field_name, field_type_name, &field_metadata);
+        let read = generate_quote_read(field, &field_name, field_type_name, &field_metadata);
 
         write_code.push(write);
         read_code.push(read);
@@ -242,7 +264,7 @@ pub fn derive_packet(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     let completed = quote! {
         impl crate::packet::Packet for #ident {
-            fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+            fn try_read(&mut self, src: &mut BytesMut) -> Result<(), crate::DecodeError> {
                 #(#read_code)*
                 Ok(())
             }
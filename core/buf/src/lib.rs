@@ -1,4 +1,4 @@
-pub use read::GameBuf;
+pub use read::{EndOfBuffer, GameBuf};
 pub use write::BitWriter;
 pub use write::GameBufMut;
 
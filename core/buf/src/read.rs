@@ -4,10 +4,89 @@ use bytes::Buf;
 
 use super::Transform;
 
+/// Returned by the checked readers of [`GameBuf`] when the buffer ends before the value being
+/// read does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndOfBuffer;
+
+impl std::fmt::Display for EndOfBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("buffer ended unexpectedly")
+    }
+}
+
+impl std::error::Error for EndOfBuffer {}
+
 /// A set of helper methods that extend the Buf object with functionality required to fully
 /// decode packets sent by the client.
+///
+/// Like the methods of `Buf`, the unchecked readers panic when the buffer ends early, so bytes
+/// sent by the client should be read with the checked readers instead.
 pub trait GameBuf: Buf {
+    /// Returns an error unless at least `len` bytes remain to be read.
+    fn ensure_remaining(&self, len: usize) -> Result<(), EndOfBuffer> {
+        if self.remaining() < len {
+            Err(EndOfBuffer)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads a value that is `len` bytes long using `read`, or returns an error without reading
+    /// anything if fewer than `len` bytes remain.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bytes::{Buf, Bytes};
+    /// use mithril_buf::{EndOfBuffer, GameBuf};
+    ///
+    /// let mut buf = Bytes::from_static(&[1, 2, 3]);
+    /// assert_eq!(buf.get_checked(2, |buf| buf.get_u16()), Ok(0x0102));
+    /// assert_eq!(buf.get_checked(2, |buf| buf.get_u16()), Err(EndOfBuffer));
+    /// ```
+    fn get_checked<T, F>(&mut self, len: usize, read: F) -> Result<T, EndOfBuffer>
+    where
+        Self: Sized,
+        F: FnOnce(&mut Self) -> T,
+    {
+        self.ensure_remaining(len)?;
+        Ok(read(self))
+    }
+
+    /// Reads a String terminated by a line feed (`\n`) character, or returns an error if the
+    /// buffer ends before the line feed.
+    fn try_get_rs_string(&mut self) -> Result<String, EndOfBuffer>
+    where
+        Self: Sized,
+    {
+        let mut result = String::default();
+        loop {
+            match self.get_checked(1, |buf| buf.get_u8())? {
+                10 => break,
+                c => result.push(char::from(c)),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Reads a smart as [`get_smart`](GameBuf::get_smart) does, or returns an error without
+    /// reading anything if the buffer ends before the smart does.
+    fn try_get_smart(&mut self) -> Result<u16, EndOfBuffer>
+    where
+        Self: Sized,
+    {
+        let first = *self.bytes().first().ok_or(EndOfBuffer)?;
+        let len = if first > 127 { 2 } else { 1 };
+        self.get_checked(len, |buf| buf.get_smart())
+    }
+
     /// Attempts to read a String from self, this method will read until a line feed (`\n`) character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer ends before the line feed, see
+    /// [`try_get_rs_string`](GameBuf::try_get_rs_string).
     fn get_rs_string(&mut self) -> String {
         let mut result = String::default();
         loop {
@@ -35,7 +114,7 @@ pub trait GameBuf: Buf {
         match transform {
             Transform::Add => self.get_u8().wrapping_sub(128),
             Transform::Subtract => 128u8.wrapping_sub(self.get_u8()),
-            Transform::Negate => (-self.get_i8()) as u8,
+            Transform::Negate => self.get_u8().wrapping_neg(),
         }
     }
 
@@ -70,12 +149,28 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    pub fn test_checked_reads() {
+        let mut buf = Bytes::from_static(&[b'a', b'b', 10, b'c', 200]);
+        assert_eq!(buf.try_get_rs_string(), Ok(String::from("ab")));
+        assert_eq!(buf.try_get_rs_string(), Err(EndOfBuffer));
+
+        let mut buf = Bytes::from_static(&[200]);
+        assert_eq!(buf.try_get_smart(), Err(EndOfBuffer));
+        assert_eq!(buf.remaining(), 1);
+        let mut buf = Bytes::from_static(&[200, 1, 5]);
+        assert_eq!(buf.try_get_smart(), Ok(0x4801));
+        assert_eq!(buf.try_get_smart(), Ok(5));
+        assert_eq!(buf.get_checked(4, |buf| buf.get_u32()), Err(EndOfBuffer));
+    }
+
     #[test]
     pub fn test_get_u8t() {
-        let mut buf = Bytes::from_static(&[17u8, 17u8, 17u8]);
+        let mut buf = Bytes::from_static(&[17u8, 17u8, 17u8, 128u8]);
         assert_eq!(buf.get_u8t(Transform::Subtract), 111);
         assert_eq!(buf.get_u8t(Transform::Add), 145);
         assert_eq!(buf.get_u8t(Transform::Negate), 239);
+        assert_eq!(buf.get_u8t(Transform::Negate), 128);
     }
 
     #[test]
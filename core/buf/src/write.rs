@@ -99,12 +99,27 @@ pub trait GameBufMut: BufMut {
         self.put_u8(10);
     }
 
+    /// Writes a `u16` as a single byte if it is below 128, or as two bytes with the highest bit
+    /// set otherwise. Only values up to 32767 can be written as a smart.
+    fn put_smart(&mut self, value: u16) {
+        debug_assert!(
+            value < 0x8000,
+            "{} is too large to be written as a smart",
+            value
+        );
+        if value < 128 {
+            self.put_u8(value as u8);
+        } else {
+            self.put_u16(value | 0x8000);
+        }
+    }
+
     /// Writes a big endian `u8` to the `Buf` whilst applying a transformation.
     fn put_u8t(&mut self, value: u8, transform: Transform) {
         match transform {
             Transform::Add => self.put_u8(value.wrapping_add(128)),
             Transform::Subtract => self.put_u8(128u8.wrapping_sub(value)),
-            Transform::Negate => self.put_u8((-(value as i8)) as u8),
+            Transform::Negate => self.put_u8(value.wrapping_neg()),
         }
     }
 
@@ -159,6 +174,14 @@ mod tests {
         assert_eq!(&buf, &[0xD2, 0xFF, 0xFE][..]);
     }
 
+    #[test]
+    pub fn test_put_smart() {
+        let mut buf = BytesMut::with_capacity(3);
+        buf.put_smart(127);
+        buf.put_smart(0x4801);
+        assert_eq!(&buf[..], &[127, 0xC8, 0x01][..]);
+    }
+
     #[test]
     pub fn test_put_u8t() {
         let mut buf = BytesMut::with_capacity(3);
@@ -0,0 +1,55 @@
+/*
+ * This example will decode the model of every item in the cache and write it as a Wavefront OBJ
+ * to './cache/models', so that models can be previewed without running the client.
+ *
+ * Textured faces refer to './cache/models/textures/<id>.png', which is not written here.
+ */
+
+use mithril_fs::*;
+
+fn main() {
+    let cache_dir = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../cache"));
+
+    let mut cache = match CacheFileSystem::open(cache_dir) {
+        Ok(cache) => cache,
+        Err(CacheError::FileMapping { path, source, .. }) => {
+            eprintln!("Failed to map required file {:?}", path);
+            eprintln!("{}", source);
+            return;
+        }
+        _ => unreachable!(),
+    };
+
+    let models_dir = cache_dir.join("models");
+    if let Err(error) = std::fs::create_dir(&models_dir) {
+        if error.kind() != std::io::ErrorKind::AlreadyExists {
+            panic!("Failed to create './cache/models' dir; {}", error);
+        }
+    }
+
+    let items = defs::ItemDefinition::load(&mut cache).expect("items");
+    for item in items.iter() {
+        let model_id = match item.model_id() {
+            Some(model_id) => model_id,
+            None => continue,
+        };
+
+        let model = match Model::load(&cache, model_id as usize) {
+            Ok(model) => model,
+            Err(error) => {
+                eprintln!(
+                    "Skipping model {} of item {}; {}",
+                    model_id,
+                    item.id(),
+                    error
+                );
+                continue;
+            }
+        };
+
+        let mtl_name = format!("{}.mtl", model_id);
+        let (obj, mtl) = model.to_obj(&mtl_name);
+        std::fs::write(models_dir.join(format!("{}.obj", model_id)), obj).expect("write obj");
+        std::fs::write(models_dir.join(mtl_name), mtl).expect("write mtl");
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * This example will render every sound effect in the cache to a WAV file and extract every song
+ * as a MIDI file, writing both to './cache/sounds'.
+ */
+
+use mithril_fs::*;
+
+fn main() {
+    let cache_dir = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../cache"));
+
+    let cache = match CacheFileSystem::open(cache_dir) {
+        Ok(cache) => cache,
+        Err(CacheError::FileMapping { path, source, .. }) => {
+            eprintln!("Failed to map required file {:?}", path);
+            eprintln!("{}", source);
+            return;
+        }
+        _ => unreachable!(),
+    };
+
+    let sounds_dir = cache_dir.join("sounds");
+    if let Err(error) = std::fs::create_dir(&sounds_dir) {
+        if error.kind() != std::io::ErrorKind::AlreadyExists {
+            panic!("Failed to create './cache/sounds' dir; {}", error);
+        }
+    }
+
+    let sounds = SoundEffect::load(&cache).expect("sounds");
+    for (id, sound) in sounds.iter() {
+        let file_path = sounds_dir.join(format!("{}.wav", id));
+        std::fs::write(file_path, sound.to_wav(1)).expect("write wav");
+    }
+
+    for id in Song::ids(&cache).expect("song ids") {
+        match Song::load(&cache, id) {
+            Ok(song) => {
+                let file_path = sounds_dir.join(format!("song-{}.mid", id));
+                std::fs::write(file_path, song.data()).expect("write midi");
+            }
+            Err(error) => eprintln!("Skipping song {}; {}", id, error),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+/*
+ * This example will decode every sprite group in the media archive and then use the "PNG" crate
+ * to write each sprite to './cache/sprites'.
+ *
+ * Archives only store a hash of each entry's name, so groups that are not listed below are
+ * written using their name hash instead.
+ */
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use mithril_fs::*;
+
+const GROUP_NAMES: &[&str] = &[
+    "backbase1",
+    "backbase2",
+    "backhmid1",
+    "backhmid2",
+    "backleft1",
+    "backleft2",
+    "backright1",
+    "backright2",
+    "backtop1",
+    "backtop2",
+    "backvmid1",
+    "backvmid2",
+    "backvmid3",
+    "chatback",
+    "combatboxes",
+    "combaticons",
+    "combaticons2",
+    "combaticons3",
+    "compass",
+    "cross",
+    "headicons",
+    "hitmarks",
+    "invback",
+    "leftarrow",
+    "magicoff",
+    "magicoff2",
+    "magicon",
+    "magicon2",
+    "mapback",
+    "mapdots",
+    "mapedge",
+    "mapfunction",
+    "mapmarker",
+    "mapscene",
+    "miscgraphics",
+    "miscgraphics2",
+    "miscgraphics3",
+    "mod_icons",
+    "mouseicons",
+    "prayerglow",
+    "prayeroff",
+    "prayeron",
+    "redstone1",
+    "redstone2",
+    "redstone3",
+    "rightarrow",
+    "scrollbar",
+    "sideicons",
+    "staticons",
+    "staticons2",
+    "steelborder",
+    "steelborder2",
+    "sworddecor",
+    "tradebacking",
+    "wornicons",
+];
+
+fn main() {
+    let cache_dir = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../cache"));
+
+    let cache = match CacheFileSystem::open(cache_dir) {
+        Ok(cache) => cache,
+        Err(CacheError::FileMapping { path, source, .. }) => {
+            eprintln!("Failed to map required file {:?}", path);
+            eprintln!("{}", source);
+            return;
+        }
+        _ => unreachable!(),
+    };
+
+    if let Err(error) = std::fs::create_dir(cache_dir.join("sprites")) {
+        if error.kind() != std::io::ErrorKind::AlreadyExists {
+            panic!("Failed to create './cache/sprites' dir; {}", error);
+        }
+    }
+
+    let archive = cache.get_archive(0, 4).expect("media archive");
+    let index = archive.get_entry("index.dat").expect("index.dat");
+
+    for entry in archive.entries() {
+        if entry.name_hash() == index.name_hash() {
+            continue;
+        }
+
+        let name = GROUP_NAMES
+            .iter()
+            .find(|name| {
+                archive
+                    .get_entry(&format!("{}.dat", name))
+                    .map(|group| group.name_hash())
+                    == Some(entry.name_hash())
+            })
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("{:08x}", entry.name_hash()));
+
+        let sprites = match Sprite::decode(index.contents(), entry.contents()) {
+            Ok(sprites) => sprites,
+            Err(error) => {
+                eprintln!("Skipping sprite group {}; {}", name, error);
+                continue;
+            }
+        };
+
+        for (id, sprite) in sprites.iter().enumerate() {
+            if sprite.max_width() == 0 || sprite.max_height() == 0 {
+                continue;
+            }
+
+            let file_path = cache_dir
+                .join("sprites")
+                .join(format!("{}-{}.png", name, id));
+            let w = File::create(file_path)
+                .map(BufWriter::new)
+                .expect("sprite file");
+
+            let mut encoder = png::Encoder::new(
+                w,
+                u32::from(sprite.max_width()),
+                u32::from(sprite.max_height()),
+            );
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&sprite.to_rgba_untrimmed())
+                .expect("write_image_data");
+        }
+    }
+}
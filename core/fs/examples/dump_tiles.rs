@@ -29,7 +29,7 @@ fn plane_to_rgba(plane: &defs::MapPlane) -> bytes::Bytes {
 fn main() {
     let cache_dir = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../cache"));
 
-    let mut cache = match CacheFileSystem::open(cache_dir) {
+    let cache = match CacheFileSystem::open(cache_dir) {
         Ok(cache) => cache,
         Err(CacheError::FileMapping { path, source, .. }) => {
             eprintln!("Failed to map required file {:?}", path);
@@ -45,11 +45,11 @@ fn main() {
         }
     }
 
-    let map_indices = defs::MapIndex::load(&mut cache).expect("map_indices");
+    let map_indices = defs::MapIndex::load(&cache).expect("map_indices");
     let indexed_planes = map_indices
         .values()
         .map(|index| {
-            let map_file = defs::MapFile::load(&mut cache, index).expect("map_file");
+            let map_file = defs::MapFile::load(&cache, index).expect("map_file");
             (index, map_file)
         })
         .collect::<Vec<_>>();
@@ -68,8 +68,9 @@ fn main() {
         encoder.set_color(png::ColorType::RGBA);
         encoder.set_depth(png::BitDepth::Eight);
         let mut writer = encoder.write_header().unwrap();
+        let plane = map_file.get_plane(0).expect("plane");
         writer
-            .write_image_data(&plane_to_rgba(map_file.get_plane(0))[..])
+            .write_image_data(&plane_to_rgba(plane)[..])
             .expect("write_image_data");
     });
 }
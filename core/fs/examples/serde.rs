@@ -36,15 +36,15 @@ fn main() {
     }
 
     let items = defs::ItemDefinition::load(&mut cache).expect("items");
-    let objects = defs::ObjectDefinition::load(&mut cache).expect("objects");
+    let objects = defs::ObjectDefinition::load(&cache).expect("objects");
     let entities = defs::EntityDefinition::load(&mut cache).expect("entities");
-    let map_data = defs::MapIndex::load(&mut cache).expect("map_indices");
+    let map_data = defs::MapIndex::load(&cache).expect("map_indices");
     let map_data = map_data
         .iter()
         .map(|(_, index)| {
             (
                 index,
-                defs::MapObject::load(&mut cache, index).expect("map_objects"),
+                defs::MapObject::load(&cache, index).expect("map_objects"),
             )
         })
         .collect::<Vec<_>>();
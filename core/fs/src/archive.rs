@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 use crate::ArchiveError;
 
+const MAX_ARCHIVE_SIZE: usize = 0xFF_FFFF;
+
 #[derive(Debug)]
 pub struct Archive(HashMap<i32, ArchiveEntry>);
 
@@ -28,11 +30,16 @@ impl ArchiveEntry {
     pub fn size(&self) -> usize {
         self.header.extracted_size
     }
+
+    /// The hash of the entry's name, which is all the archive stores of the name.
+    pub fn name_hash(&self) -> i32 {
+        self.header.name_hash
+    }
 }
 
 #[allow(clippy::len_without_is_empty)]
 impl Archive {
-    pub(crate) fn decode(mut buf: Bytes) -> crate::Result<Self> {
+    pub fn decode(mut buf: Bytes) -> crate::Result<Self> {
         let decompressed_size = buf.get_uint(3) as usize;
         let size = buf.get_uint(3) as usize;
         let is_extracted = if size != decompressed_size {
@@ -90,6 +97,134 @@ impl Archive {
     pub fn len(&self) -> usize {
         self.0.capacity()
     }
+
+    /// Iterates over every entry in the archive in no particular order.
+    pub fn entries(&self) -> impl Iterator<Item = &ArchiveEntry> {
+        self.0.values()
+    }
+}
+
+/// Determines which of the two archive layouts an [`ArchiveBuilder`](struct.ArchiveBuilder.html)
+/// produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveCompression {
+    /// The headers and entries are compressed together as a single bzip2 stream.
+    Archive,
+    /// Every entry is compressed individually, the headers are left uncompressed.
+    Entries,
+}
+
+/// Encodes named entries into an archive that can be read by [`Archive::decode`].
+///
+/// # Example
+///
+/// ```rust
+/// use mithril_fs::{Archive, ArchiveBuilder, ArchiveCompression};
+///
+/// let mut builder = ArchiveBuilder::new(ArchiveCompression::Entries);
+/// builder.add_entry("obj.dat", &b"item data"[..]);
+/// let archive = Archive::decode(builder.build().unwrap()).unwrap();
+/// assert_eq!(&archive.get_entry("obj.dat").unwrap().contents()[..], b"item data");
+/// ```
+#[derive(Debug)]
+pub struct ArchiveBuilder {
+    compression: ArchiveCompression,
+    entries: Vec<(i32, Bytes)>,
+}
+
+impl ArchiveBuilder {
+    pub fn new(compression: ArchiveCompression) -> Self {
+        Self {
+            compression,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds an entry to the archive, replacing any existing entry with the same name.
+    pub fn add_entry<B: Into<Bytes>>(&mut self, name: &str, contents: B) -> &mut Self {
+        self.add_hashed_entry(hash_name(name), contents.into());
+        self
+    }
+
+    fn add_hashed_entry(&mut self, name_hash: i32, contents: Bytes) {
+        match self.entries.iter_mut().find(|(hash, _)| *hash == name_hash) {
+            Some(entry) => entry.1 = contents,
+            None => self.entries.push((name_hash, contents)),
+        }
+    }
+
+    pub fn build(&self) -> crate::Result<Bytes> {
+        let payload = self.encode_entries(self.compression)?;
+        match self.compression {
+            ArchiveCompression::Entries => Ok(finish_archive(payload.len(), payload)?),
+            ArchiveCompression::Archive => {
+                let compressed = crate::compression::compress_bzip2(&payload[..])?;
+                if compressed.len() == payload.len() {
+                    // The decoder would mistake this for individually compressed entries.
+                    let payload = self.encode_entries(ArchiveCompression::Entries)?;
+                    return Ok(finish_archive(payload.len(), payload)?);
+                }
+                Ok(finish_archive(payload.len(), compressed)?)
+            }
+        }
+    }
+
+    fn encode_entries(&self, compression: ArchiveCompression) -> crate::Result<Bytes> {
+        let mut headers = BytesMut::new();
+        let mut contents = BytesMut::new();
+        headers.put_u16(self.entries.len() as u16);
+        for (name_hash, entry) in self.entries.iter() {
+            let stored = match compression {
+                ArchiveCompression::Archive => entry.clone(),
+                ArchiveCompression::Entries => crate::compression::compress_bzip2(&entry[..])?,
+            };
+            if entry.len() > MAX_ARCHIVE_SIZE || stored.len() > MAX_ARCHIVE_SIZE {
+                return Err(ArchiveError::TooLarge(entry.len().max(stored.len())).into());
+            }
+            headers.put_i32(*name_hash);
+            headers.put_uint(entry.len() as u64, 3);
+            headers.put_uint(stored.len() as u64, 3);
+            contents.extend_from_slice(&stored[..]);
+        }
+        headers.extend_from_slice(&contents[..]);
+        Ok(headers.freeze())
+    }
+}
+
+impl From<&Archive> for ArchiveBuilder {
+    /// Creates a builder containing every entry of `archive`, compressed the same way.
+    fn from(archive: &Archive) -> Self {
+        let compression = if archive
+            .0
+            .values()
+            .any(|entry| entry.header.size != entry.header.extracted_size)
+        {
+            ArchiveCompression::Entries
+        } else {
+            ArchiveCompression::Archive
+        };
+        let mut entries = archive
+            .0
+            .iter()
+            .map(|(name_hash, entry)| (*name_hash, entry.contents()))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(name_hash, _)| *name_hash);
+        Self {
+            compression,
+            entries,
+        }
+    }
+}
+
+fn finish_archive(decompressed_size: usize, payload: Bytes) -> Result<Bytes, ArchiveError> {
+    if decompressed_size > MAX_ARCHIVE_SIZE || payload.len() > MAX_ARCHIVE_SIZE {
+        return Err(ArchiveError::TooLarge(decompressed_size.max(payload.len())));
+    }
+    let mut buf = BytesMut::with_capacity(payload.len() + 6);
+    buf.put_uint(decompressed_size as u64, 3);
+    buf.put_uint(payload.len() as u64, 3);
+    buf.extend_from_slice(&payload[..]);
+    Ok(buf.freeze())
 }
 
 fn decode_headers<B: Buf>(buf: &mut B) -> crate::Result<Vec<ArchiveHeader>> {
@@ -115,3 +250,65 @@ fn hash_name(name: &str) -> i32 {
                 .wrapping_sub(32)
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_archive(compression: ArchiveCompression) -> Archive {
+        let mut builder = ArchiveBuilder::new(compression);
+        builder
+            .add_entry("obj.dat", vec![1u8; 4096])
+            .add_entry("obj.idx", vec![0u8, 2, 0, 12])
+            .add_entry("empty", Vec::new());
+        Archive::decode(builder.build().expect("build")).expect("decode")
+    }
+
+    #[test]
+    pub fn round_trip_archive_compression() {
+        let archive = build_archive(ArchiveCompression::Archive);
+        assert_eq!(
+            &archive.get_entry("obj.dat").unwrap().contents()[..],
+            &[1u8; 4096][..]
+        );
+        assert_eq!(
+            &archive.get_entry("OBJ.IDX").unwrap().contents()[..],
+            &[0, 2, 0, 12]
+        );
+        assert!(archive.get_entry("empty").unwrap().contents().is_empty());
+        assert!(archive.get_entry("loc.dat").is_none());
+    }
+
+    #[test]
+    pub fn round_trip_entry_compression() {
+        let archive = build_archive(ArchiveCompression::Entries);
+        assert_eq!(
+            &archive.get_entry("obj.dat").unwrap().contents()[..],
+            &[1u8; 4096][..]
+        );
+        assert_eq!(
+            &archive.get_entry("obj.idx").unwrap().contents()[..],
+            &[0, 2, 0, 12]
+        );
+        assert!(archive.get_entry("empty").unwrap().contents().is_empty());
+    }
+
+    #[test]
+    pub fn rebuild_from_archive() {
+        let archive = build_archive(ArchiveCompression::Entries);
+        let mut builder = ArchiveBuilder::from(&archive);
+        assert_eq!(builder.compression, ArchiveCompression::Entries);
+
+        builder.add_entry("obj.idx", vec![0u8, 1, 0, 4]);
+        let rebuilt = Archive::decode(builder.build().expect("build")).expect("decode");
+        assert_eq!(
+            &rebuilt.get_entry("obj.dat").unwrap().contents()[..],
+            &[1u8; 4096][..]
+        );
+        assert_eq!(
+            &rebuilt.get_entry("obj.idx").unwrap().contents()[..],
+            &[0, 1, 0, 4]
+        );
+        assert_eq!(builder.entries.len(), 3);
+    }
+}
@@ -1,7 +1,8 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
 use bytes::{Bytes, BytesMut};
 use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
 use flate2::read::GzDecoder;
 
 pub(crate) fn decompress_bzip2(compressed: Bytes) -> crate::Result<Bytes> {
@@ -16,6 +17,14 @@ pub(crate) fn decompress_bzip2(compressed: Bytes) -> crate::Result<Bytes> {
     decompress(decoder)
 }
 
+pub(crate) fn compress_bzip2(data: &[u8]) -> crate::Result<Bytes> {
+    let mut encoder = BzEncoder::new(Vec::with_capacity(data.len()), bzip2::Compression::Fastest);
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    // The cache omits the "BZh1" header, it is added back by decompress_bzip2.
+    Ok(Bytes::copy_from_slice(&compressed[4..]))
+}
+
 pub(crate) fn decompress_gzip(compressed: Bytes) -> crate::Result<Bytes> {
     debug_assert!(!compressed.is_empty(), "compressed buffer is empty");
     let decoder = GzDecoder::new(&compressed[..]);
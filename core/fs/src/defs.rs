@@ -1,9 +1,144 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{ArchiveError, CacheError, CacheFileSystem};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub use animation::{SequenceDefinition, SpotAnimationDefinition};
+pub use config::{VarbitDefinition, VarpDefinition};
 pub use entity::{EntityAnimation, EntityDefinition};
-pub use item::ItemDefinition;
-pub use map::{MapFile, MapIndex, MapObject, MapPlane};
+pub use floor::{FloorDefinition, Hsl};
+pub use frame::{
+    BaseTransform, DanglingFrame, Frame, FrameBase, FrameGroup, FrameTransform, TransformType,
+};
+pub use item::{ItemDefinition, StackVariant};
+pub use map::{MapFile, MapIndex, MapObject, MapPlane, Tile};
 pub use object::ObjectDefinition;
+pub use widget::{
+    InventoryLayout, SlotSprite, WidgetAction, WidgetChild, WidgetDefinition, WidgetType,
+};
 
+mod animation;
+mod config;
 mod entity;
+mod floor;
+mod frame;
 mod item;
 mod map;
 mod object;
+mod widget;
+
+/// Replaces one colour of a model with another.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recolour {
+    pub original: u16,
+    pub replacement: u16,
+}
+
+/// Allows an object or entity to change into one of its children depending on player config.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Morphism {
+    varbit_id: u16,
+    varp_id: u16,
+    child_ids: Vec<u16>,
+}
+
+impl Recolour {
+    pub(crate) fn decode_all<B: Buf>(buf: &mut B) -> Vec<Self> {
+        let len = buf.get_u8();
+        (0..len)
+            .map(|_| Recolour {
+                original: buf.get_u16(),
+                replacement: buf.get_u16(),
+            })
+            .collect()
+    }
+
+    pub(crate) fn encode_all<B: BufMut>(recolours: &[Self], buf: &mut B) {
+        buf.put_u8(recolours.len() as u8);
+        for recolour in recolours {
+            buf.put_u16(recolour.original);
+            buf.put_u16(recolour.replacement);
+        }
+    }
+}
+
+impl Morphism {
+    /// The varbit that selects the child, if any.
+    pub fn varbit_id(&self) -> Option<u16> {
+        Some(self.varbit_id).filter(|id| *id != u16::MAX)
+    }
+
+    /// The varp that selects the child when there is no varbit.
+    pub fn varp_id(&self) -> Option<u16> {
+        Some(self.varp_id).filter(|id| *id != u16::MAX)
+    }
+
+    /// The children this definition can change into; `None` entries hide the definition.
+    pub fn child_ids(&self) -> impl Iterator<Item = Option<u16>> + '_ {
+        self.child_ids
+            .iter()
+            .map(|id| Some(*id).filter(|id| *id != u16::MAX))
+    }
+
+    /// Picks the child for the current value of the varbit or varp.
+    pub fn child_id(&self, state: usize) -> Option<u16> {
+        self.child_ids
+            .get(state)
+            .copied()
+            .filter(|id| *id != u16::MAX)
+    }
+
+    pub(crate) fn decode<B: Buf>(buf: &mut B) -> Self {
+        let varbit_id = buf.get_u16();
+        let varp_id = buf.get_u16();
+        let len = buf.get_u8();
+        Self {
+            varbit_id,
+            varp_id,
+            child_ids: (0..=len).map(|_| buf.get_u16()).collect(),
+        }
+    }
+
+    pub(crate) fn encode<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u16(self.varbit_id);
+        buf.put_u16(self.varp_id);
+        // The child count is written as the index of the last child.
+        buf.put_u8(self.child_ids.len().saturating_sub(1) as u8);
+        for child_id in self.child_ids.iter() {
+            buf.put_u16(*child_id);
+        }
+    }
+}
+
+/// Reads an entry of the config archive that holds definitions without an index.
+pub(crate) fn config_entry(cache: &CacheFileSystem, name: &'static str) -> crate::Result<Bytes> {
+    let archive = cache.get_archive(0, 2)?;
+    let entry = archive
+        .get_entry(name)
+        .map(|entry| entry.contents())
+        .ok_or(ArchiveError::EntryNotFound(name))?;
+    Ok(entry)
+}
+
+/// Packs encoded definitions into the `.dat` and `.idx` layout used by the config archive.
+pub(crate) fn pack_definitions(
+    ty: &'static str,
+    encoded: Vec<Bytes>,
+) -> crate::Result<(Bytes, Bytes)> {
+    let mut data = BytesMut::new();
+    let mut index = BytesMut::with_capacity(2 + encoded.len() * 2);
+    data.put_u16(encoded.len() as u16);
+    index.put_u16(encoded.len() as u16);
+    for (id, definition) in encoded.iter().enumerate() {
+        if definition.len() > u16::MAX as usize {
+            return Err(CacheError::EncodeDefinition { ty, id });
+        }
+        index.put_u16(definition.len() as u16);
+        data.extend_from_slice(&definition[..]);
+    }
+    Ok((data.freeze(), index.freeze()))
+}
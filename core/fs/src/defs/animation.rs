@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use super::{config_entry, Recolour};
+use crate::{CacheError, CacheFileSystem};
+use bytes::Buf;
+use mithril_buf::GameBuf;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The number of client cycles, each lasting 20ms, that make up a 600ms game tick.
+const CYCLES_PER_TICK: u32 = 30;
+
+/// A sequence of model frames that makes up an animation, such as an attack or emote.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceDefinition {
+    id: u16,
+    frame_ids: Vec<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    secondary_frame_ids: Vec<Option<u16>>,
+    frame_durations: Vec<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    loop_offset: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    interleave_order: Vec<u8>,
+    stretches: bool,
+    priority: u8,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    shield_id: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    weapon_id: Option<u16>,
+    maximum_loops: u8,
+    animating_precedence: u8,
+    walking_precedence: u8,
+    replay_mode: u8,
+}
+
+impl Default for SequenceDefinition {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            frame_ids: Vec::new(),
+            secondary_frame_ids: Vec::new(),
+            frame_durations: Vec::new(),
+            loop_offset: None,
+            interleave_order: Vec::new(),
+            stretches: false,
+            priority: 5,
+            shield_id: None,
+            weapon_id: None,
+            maximum_loops: 99,
+            animating_precedence: 0,
+            walking_precedence: 0,
+            replay_mode: 2,
+        }
+    }
+}
+
+impl SequenceDefinition {
+    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
+        let mut data = config_entry(cache, "seq.dat")?;
+        let count = data.get_u16();
+        (0..count)
+            .map(|id| decode_sequence(id, &mut data))
+            .collect()
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn frame_ids(&self) -> &[u16] {
+        &self.frame_ids
+    }
+
+    /// The frames applied on top of the primary frames, used for interleaved sequences.
+    pub fn secondary_frame_ids(&self) -> &[Option<u16>] {
+        &self.secondary_frame_ids
+    }
+
+    /// How long each frame is shown for, in client cycles of 20ms. A duration of zero means the
+    /// duration stored with the frame itself is used instead.
+    pub fn frame_durations(&self) -> &[u16] {
+        &self.frame_durations
+    }
+
+    /// The number of frames to step back by when the sequence loops.
+    pub fn loop_offset(&self) -> Option<u16> {
+        self.loop_offset
+    }
+
+    /// The skeleton labels animated by this sequence when interleaved with another.
+    pub fn interleave_order(&self) -> &[u8] {
+        &self.interleave_order
+    }
+
+    pub fn stretches(&self) -> bool {
+        self.stretches
+    }
+
+    /// Sequences with a higher priority replace one another; lower priorities are ignored while a
+    /// sequence is playing.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// The item model shown in place of the player's shield while the sequence plays.
+    pub fn shield_id(&self) -> Option<u16> {
+        self.shield_id
+    }
+
+    /// The item model shown in place of the player's weapon while the sequence plays.
+    pub fn weapon_id(&self) -> Option<u16> {
+        self.weapon_id
+    }
+
+    pub fn maximum_loops(&self) -> u8 {
+        self.maximum_loops
+    }
+
+    /// How the sequence reacts to the mob moving: `0` pauses it while moving, `1` cancels it
+    /// as soon as the mob moves and `2` lets it play over the movement.
+    pub fn animating_precedence(&self) -> u8 {
+        self.animating_precedence
+    }
+
+    /// Whether the walk and run animations may play instead of the sequence: `0` allows them,
+    /// `1` and `2` keep the sequence playing.
+    pub fn walking_precedence(&self) -> u8 {
+        self.walking_precedence
+    }
+
+    /// How the sequence restarts when it is played again before finishing.
+    pub fn replay_mode(&self) -> u8 {
+        self.replay_mode
+    }
+
+    /// The number of client cycles taken to play each frame once.
+    pub fn duration_cycles(&self) -> u32 {
+        self.frame_durations.iter().map(|d| u32::from(*d)).sum()
+    }
+
+    /// The number of client cycles taken to play each frame once, using the duration stored with
+    /// a frame when the sequence does not specify one. Missing frames count as a single cycle.
+    pub fn resolved_duration_cycles(&self, frame_durations: &HashMap<u16, u8>) -> u32 {
+        self.frame_ids
+            .iter()
+            .zip(self.frame_durations.iter())
+            .map(|(frame_id, duration)| match duration {
+                0 => frame_durations
+                    .get(frame_id)
+                    .map_or(1, |duration| u32::from(*duration)),
+                duration => u32::from(*duration),
+            })
+            .sum()
+    }
+
+    /// The number of game ticks taken to play each frame once, rounded up.
+    pub fn duration_ticks(&self) -> u32 {
+        self.duration_cycles().div_ceil(CYCLES_PER_TICK)
+    }
+}
+
+/// A graphic drawn at a mob or position, such as a spell's projectile or impact.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotAnimationDefinition {
+    id: u16,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    model_id: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    sequence_id: Option<u16>,
+    scale_xy: u16,
+    scale_z: u16,
+    rotation: u16,
+    ambient: u8,
+    contrast: u8,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    original_colours: [Option<u16>; 10],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    replacement_colours: [Option<u16>; 10],
+}
+
+impl Default for SpotAnimationDefinition {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            model_id: None,
+            sequence_id: None,
+            scale_xy: 128,
+            scale_z: 128,
+            rotation: 0,
+            ambient: 0,
+            contrast: 0,
+            original_colours: [None; 10],
+            replacement_colours: [None; 10],
+        }
+    }
+}
+
+impl SpotAnimationDefinition {
+    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
+        let mut data = config_entry(cache, "spotanim.dat")?;
+        let count = data.get_u16();
+        (0..count)
+            .map(|id| decode_spot_animation(id, &mut data))
+            .collect()
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn model_id(&self) -> Option<u16> {
+        self.model_id
+    }
+
+    /// The [`SequenceDefinition`](struct.SequenceDefinition.html) that animates the model.
+    pub fn sequence_id(&self) -> Option<u16> {
+        self.sequence_id
+    }
+
+    /// The horizontal and vertical scale of the model, where 128 is the original size.
+    pub fn scale(&self) -> (u16, u16) {
+        (self.scale_xy, self.scale_z)
+    }
+
+    pub fn rotation(&self) -> u16 {
+        self.rotation
+    }
+
+    pub fn ambient(&self) -> u8 {
+        self.ambient
+    }
+
+    pub fn contrast(&self) -> u8 {
+        self.contrast
+    }
+
+    pub fn recolours(&self) -> Vec<Recolour> {
+        self.original_colours
+            .iter()
+            .zip(self.replacement_colours.iter())
+            .filter_map(|(original, replacement)| match (original, replacement) {
+                (Some(original), Some(replacement)) => Some(Recolour {
+                    original: *original,
+                    replacement: *replacement,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn decode_sequence<B: GameBuf>(sequence_id: u16, buf: &mut B) -> crate::Result<SequenceDefinition> {
+    let mut definition = SequenceDefinition {
+        id: sequence_id,
+        ..SequenceDefinition::default()
+    };
+    let mut animating_precedence = None;
+    let mut walking_precedence = None;
+    loop {
+        match buf.get_u8() {
+            0 => break,
+            1 => {
+                let len = buf.get_u8() as usize;
+                definition.frame_ids = Vec::with_capacity(len);
+                definition.secondary_frame_ids = Vec::with_capacity(len);
+                definition.frame_durations = Vec::with_capacity(len);
+                for _ in 0..len {
+                    definition.frame_ids.push(buf.get_u16());
+                    let secondary = buf.get_u16();
+                    definition
+                        .secondary_frame_ids
+                        .push(Some(secondary).filter(|id| *id != u16::MAX));
+                    definition.frame_durations.push(buf.get_u16());
+                }
+            }
+            2 => definition.loop_offset = Some(buf.get_u16()),
+            3 => {
+                let len = buf.get_u8();
+                definition.interleave_order = (0..len).map(|_| buf.get_u8()).collect();
+            }
+            4 => definition.stretches = true,
+            5 => definition.priority = buf.get_u8(),
+            6 => definition.shield_id = Some(buf.get_u16()),
+            7 => definition.weapon_id = Some(buf.get_u16()),
+            8 => definition.maximum_loops = buf.get_u8(),
+            9 => animating_precedence = Some(buf.get_u8()),
+            10 => walking_precedence = Some(buf.get_u8()),
+            11 => definition.replay_mode = buf.get_u8(),
+            12 => {
+                buf.get_u32();
+            }
+            opcode => {
+                return Err(CacheError::DecodeDefinition {
+                    ty: "Sequence",
+                    opcode,
+                })
+            }
+        }
+    }
+
+    // Interleaved sequences keep playing over movement unless told otherwise.
+    let default_precedence = if definition.interleave_order.is_empty() {
+        0
+    } else {
+        2
+    };
+    definition.animating_precedence = animating_precedence.unwrap_or(default_precedence);
+    definition.walking_precedence = walking_precedence.unwrap_or(default_precedence);
+    Ok(definition)
+}
+
+fn decode_spot_animation<B: GameBuf>(
+    spot_animation_id: u16,
+    buf: &mut B,
+) -> crate::Result<SpotAnimationDefinition> {
+    let mut definition = SpotAnimationDefinition {
+        id: spot_animation_id,
+        ..SpotAnimationDefinition::default()
+    };
+    loop {
+        match buf.get_u8() {
+            0 => return Ok(definition),
+            1 => definition.model_id = Some(buf.get_u16()),
+            2 => definition.sequence_id = Some(buf.get_u16()),
+            4 => definition.scale_xy = buf.get_u16(),
+            5 => definition.scale_z = buf.get_u16(),
+            6 => definition.rotation = buf.get_u16(),
+            7 => definition.ambient = buf.get_u8(),
+            8 => definition.contrast = buf.get_u8(),
+            opcode @ 40..=49 => {
+                definition.original_colours[opcode as usize - 40] = Some(buf.get_u16());
+            }
+            opcode @ 50..=59 => {
+                definition.replacement_colours[opcode as usize - 50] = Some(buf.get_u16());
+            }
+            opcode => {
+                return Err(CacheError::DecodeDefinition {
+                    ty: "SpotAnimation",
+                    opcode,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    pub fn decode_sequence_definition() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            1, 3,
+            0x00, 0x10, 0xFF, 0xFF, 0x00, 0x0A,
+            0x00, 0x11, 0x00, 0x20, 0x00, 0x0A,
+            0x00, 0x12, 0xFF, 0xFF, 0x00, 0x14,
+            2, 0x00, 0x01,
+            3, 2, 1, 5,
+            5, 8,
+            10, 0,
+            0,
+        ];
+
+        let definition = decode_sequence(808, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.frame_ids(), &[0x10, 0x11, 0x12]);
+        assert_eq!(definition.secondary_frame_ids(), &[None, Some(0x20), None]);
+        assert_eq!(definition.loop_offset(), Some(1));
+        assert_eq!(definition.priority(), 8);
+        assert_eq!(definition.animating_precedence(), 2);
+        assert_eq!(definition.walking_precedence(), 0);
+        assert_eq!(definition.duration_cycles(), 40);
+        assert_eq!(definition.duration_ticks(), 2);
+    }
+
+    #[test]
+    pub fn decode_spot_animation_definition() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            1, 0x01, 0x00,
+            2, 0x02, 0x00,
+            4, 0x00, 0x40,
+            40, 0x12, 0x34,
+            50, 0x56, 0x78,
+            0,
+        ];
+
+        let definition = decode_spot_animation(1, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.model_id(), Some(0x100));
+        assert_eq!(definition.sequence_id(), Some(0x200));
+        assert_eq!(definition.scale(), (64, 128));
+        assert_eq!(
+            definition.recolours(),
+            vec![Recolour {
+                original: 0x1234,
+                replacement: 0x5678
+            }]
+        );
+    }
+}
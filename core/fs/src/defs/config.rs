@@ -0,0 +1,192 @@
+use super::config_entry;
+use crate::{CacheError, CacheFileSystem};
+use bytes::Buf;
+use mithril_buf::GameBuf;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A range of bits within a player config (varp), allowing several small values to share one
+/// integer.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VarbitDefinition {
+    id: u16,
+    config_id: u16,
+    low_bit: u8,
+    high_bit: u8,
+}
+
+impl VarbitDefinition {
+    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
+        let mut data = config_entry(cache, "varbit.dat")?;
+        let count = data.get_u16();
+        (0..count).map(|id| decode_varbit(id, &mut data)).collect()
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// The config that holds the value of this varbit.
+    pub fn config_id(&self) -> u16 {
+        self.config_id
+    }
+
+    pub fn low_bit(&self) -> u8 {
+        self.low_bit
+    }
+
+    pub fn high_bit(&self) -> u8 {
+        self.high_bit
+    }
+
+    /// The mask covering every value the varbit can hold, before shifting into place.
+    pub fn mask(&self) -> u32 {
+        let bits = u32::from(self.high_bit.saturating_sub(self.low_bit)) + 1;
+        ((1u64 << bits.min(32)) - 1) as u32
+    }
+
+    /// Extracts the value of this varbit from the value of its config.
+    pub fn unpack(&self, config: u32) -> u32 {
+        config.checked_shr(u32::from(self.low_bit)).unwrap_or(0) & self.mask()
+    }
+
+    /// Replaces the value of this varbit within the value of its config, truncating `value` to
+    /// the number of bits available.
+    pub fn pack(&self, config: u32, value: u32) -> u32 {
+        let shift = u32::from(self.low_bit);
+        let mask = self.mask().checked_shl(shift).unwrap_or(0);
+        let value = value.checked_shl(shift).unwrap_or(0);
+        (config & !mask) | (value & mask)
+    }
+}
+
+/// A player config, whose value is sent to the client with the config packets.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VarpDefinition {
+    id: u16,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    client_code: Option<u16>,
+}
+
+impl VarpDefinition {
+    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
+        let mut data = config_entry(cache, "varp.dat")?;
+        let count = data.get_u16();
+        (0..count).map(|id| decode_varp(id, &mut data)).collect()
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Selects the client behaviour triggered when the config changes, such as updating the
+    /// brightness or music volume.
+    pub fn client_code(&self) -> Option<u16> {
+        self.client_code
+    }
+}
+
+fn decode_varbit<B: GameBuf>(varbit_id: u16, buf: &mut B) -> crate::Result<VarbitDefinition> {
+    let mut definition = VarbitDefinition {
+        id: varbit_id,
+        ..VarbitDefinition::default()
+    };
+    loop {
+        match buf.get_u8() {
+            0 => return Ok(definition),
+            1 => {
+                definition.config_id = buf.get_u16();
+                definition.low_bit = buf.get_u8();
+                definition.high_bit = buf.get_u8();
+            }
+            // The remaining opcodes are not used by the client.
+            2 => {}
+            3 | 4 => {
+                buf.get_u32();
+            }
+            10 => {
+                buf.get_rs_string();
+            }
+            opcode => {
+                return Err(CacheError::DecodeDefinition {
+                    ty: "Varbit",
+                    opcode,
+                })
+            }
+        }
+    }
+}
+
+fn decode_varp<B: GameBuf>(varp_id: u16, buf: &mut B) -> crate::Result<VarpDefinition> {
+    let mut definition = VarpDefinition {
+        id: varp_id,
+        ..VarpDefinition::default()
+    };
+    loop {
+        match buf.get_u8() {
+            0 => return Ok(definition),
+            5 => definition.client_code = Some(buf.get_u16()),
+            // The remaining opcodes are not used by the client.
+            1 | 2 => {
+                buf.get_u8();
+            }
+            3 | 4 | 6 | 8 | 11 | 13 => {}
+            7 | 12 => {
+                buf.get_u32();
+            }
+            10 => {
+                buf.get_rs_string();
+            }
+            opcode => return Err(CacheError::DecodeDefinition { ty: "Varp", opcode }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    pub fn decode_varbit_definition() {
+        let data = [1, 0x01, 0x2C, 4, 7, 2, 0];
+        let definition = decode_varbit(9, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.config_id(), 300);
+        assert_eq!(definition.low_bit(), 4);
+        assert_eq!(definition.high_bit(), 7);
+    }
+
+    #[test]
+    pub fn decode_varp_definition() {
+        let data = [1, 9, 3, 5, 0x00, 0x06, 10, b'a', 10, 0];
+        let definition = decode_varp(166, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.client_code(), Some(6));
+    }
+
+    #[test]
+    pub fn pack_and_unpack() {
+        let varbit = VarbitDefinition {
+            id: 0,
+            config_id: 0,
+            low_bit: 4,
+            high_bit: 7,
+        };
+        assert_eq!(varbit.mask(), 0xF);
+        assert_eq!(varbit.unpack(0xABCD), 0xC);
+
+        let config = varbit.pack(0xABCD, 0x3);
+        assert_eq!(config, 0xAB3D);
+        assert_eq!(varbit.pack(config, 0x1F), 0xABFD, "value is truncated");
+
+        let whole = VarbitDefinition {
+            high_bit: 31,
+            low_bit: 0,
+            ..varbit
+        };
+        assert_eq!(whole.mask(), u32::MAX);
+        assert_eq!(whole.pack(0, 0xDEADBEEF), 0xDEADBEEF);
+    }
+}
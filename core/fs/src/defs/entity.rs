@@ -1,11 +1,13 @@
+use super::{pack_definitions, Morphism, Recolour};
 use crate::{ArchiveError, CacheError, CacheFileSystem};
-use bytes::Buf;
-use mithril_buf::GameBuf;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use mithril_buf::{GameBuf, GameBufMut};
 use std::io::{prelude::*, Cursor, SeekFrom};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EntityAnimation {
     Idle,
     Walk,
@@ -14,20 +16,33 @@ pub enum EntityAnimation {
     TurnRight,
 }
 
+impl EntityAnimation {
+    pub const ALL: [EntityAnimation; 5] = [
+        EntityAnimation::Idle,
+        EntityAnimation::Walk,
+        EntityAnimation::TurnAway,
+        EntityAnimation::TurnLeft,
+        EntityAnimation::TurnRight,
+    ];
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EntityDefinition {
     id: u16,
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     combat_level: Option<u16>,
     name: String,
     examine_text: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    models: Vec<u16>,
     #[cfg_attr(
         feature = "serde",
         serde(skip_serializing_if = "crate::skip_empty_options")
     )]
     interact_actions: [Option<String>; 5],
-    size: u8,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    size: Option<u8>,
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     anim_stand: Option<u16>,
     #[cfg_attr(
@@ -35,6 +50,29 @@ pub struct EntityDefinition {
         serde(skip_serializing_if = "crate::skip_empty_options")
     )]
     anim_walk: [Option<u16>; 4],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    recolours: Vec<Recolour>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    head_models: Vec<u16>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    unused: [Option<u16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    scale_xz: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    scale_y: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    light: Option<i8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    shadow: Option<i8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    head_icon: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    turn_degrees: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    morphism: Option<Morphism>,
     visible_on_minimap: bool,
     clickable: bool,
     visible: bool,
@@ -47,12 +85,23 @@ impl Default for EntityDefinition {
             combat_level: None,
             name: String::default(),
             examine_text: String::default(),
+            models: Vec::new(),
             interact_actions: [None, None, None, None, None],
-            size: 1,
+            size: None,
             anim_stand: None,
             anim_walk: [None, None, None, None],
+            recolours: Vec::new(),
+            head_models: Vec::new(),
+            unused: [None; 3],
+            scale_xz: None,
+            scale_y: None,
+            light: None,
+            shadow: None,
+            head_icon: None,
+            turn_degrees: None,
+            morphism: None,
             visible_on_minimap: true,
-            clickable: false,
+            clickable: true,
             visible: false,
         }
     }
@@ -89,12 +138,19 @@ impl EntityDefinition {
         Ok(definitions)
     }
 
+    /// Encodes the definitions into the contents of the `npc.dat` and `npc.idx` archive entries,
+    /// returned in that order.
+    pub fn encode(definitions: &[Self]) -> crate::Result<(Bytes, Bytes)> {
+        let encoded = definitions.iter().map(encode_definition).collect();
+        pack_definitions("Entity", encoded)
+    }
+
     pub fn id(&self) -> u16 {
         self.id
     }
 
     pub fn combat_level(&self) -> Option<u16> {
-        self.combat_level
+        self.combat_level.filter(|level| *level != 0)
     }
 
     pub fn examine(&self) -> &String {
@@ -102,17 +158,73 @@ impl EntityDefinition {
     }
 
     pub fn size(&self) -> u8 {
-        self.size
+        self.size.unwrap_or(1)
     }
 
     pub fn animation(&self, animation_type: EntityAnimation) -> Option<u16> {
-        match animation_type {
+        let animation = match animation_type {
             EntityAnimation::Idle => self.anim_stand,
             EntityAnimation::Walk => self.anim_walk[0],
             EntityAnimation::TurnAway => self.anim_walk[1],
             EntityAnimation::TurnLeft => self.anim_walk[2],
             EntityAnimation::TurnRight => self.anim_walk[3],
-        }
+        };
+        animation.filter(|id| *id != u16::MAX)
+    }
+
+    /// Every animation slot the entity has an animation for.
+    pub fn animations(&self) -> impl Iterator<Item = (EntityAnimation, u16)> + '_ {
+        EntityAnimation::ALL
+            .iter()
+            .filter_map(move |slot| self.animation(*slot).map(|id| (*slot, id)))
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn models(&self) -> &[u16] {
+        &self.models
+    }
+
+    /// The models drawn for the entity's head in dialogues.
+    pub fn head_models(&self) -> &[u16] {
+        &self.head_models
+    }
+
+    /// The prayer or skull icon drawn above the entity.
+    pub fn head_icon(&self) -> Option<u16> {
+        self.head_icon.filter(|id| *id != u16::MAX)
+    }
+
+    pub fn interact_action(&self, index: usize) -> Option<&String> {
+        self.interact_actions[index].as_ref()
+    }
+
+    pub fn recolours(&self) -> &[Recolour] {
+        &self.recolours
+    }
+
+    /// The horizontal and vertical scale of the model, where 128 is the original size.
+    pub fn scale(&self) -> (u16, u16) {
+        (self.scale_xz.unwrap_or(128), self.scale_y.unwrap_or(128))
+    }
+
+    pub fn light(&self) -> i8 {
+        self.light.unwrap_or(0)
+    }
+
+    pub fn shadow(&self) -> i8 {
+        self.shadow.unwrap_or(0)
+    }
+
+    /// How far the entity turns each client tick when changing direction.
+    pub fn turn_degrees(&self) -> u16 {
+        self.turn_degrees.unwrap_or(32)
+    }
+
+    pub fn morphism(&self) -> Option<&Morphism> {
+        self.morphism.as_ref()
     }
 
     pub fn is_clickable(&self) -> bool {
@@ -136,11 +248,11 @@ fn decode_definition<B: GameBuf>(npc_id: u16, buf: &mut B) -> crate::Result<Enti
             0 => return Ok(definition),
             1 => {
                 let len = buf.get_u8();
-                let _models = (0..len).map(|_| buf.get_u16()).collect::<Vec<_>>();
+                definition.models = (0..len).map(|_| buf.get_u16()).collect();
             }
             2 => definition.name = buf.get_rs_string(),
             3 => definition.examine_text = buf.get_rs_string(),
-            12 => definition.size = buf.get_u8(),
+            12 => definition.size = Some(buf.get_u8()),
             13 => {
                 definition.anim_stand = Some(buf.get_u16());
             }
@@ -153,51 +265,32 @@ fn decode_definition<B: GameBuf>(npc_id: u16, buf: &mut B) -> crate::Result<Enti
                 definition.anim_walk[2] = Some(buf.get_u16());
                 definition.anim_walk[3] = Some(buf.get_u16());
             }
-            opcode if (30..40).contains(&opcode) => {
+            opcode if (30..35).contains(&opcode) => {
                 let action = buf.get_rs_string();
                 definition.interact_actions[opcode as usize - 30] = Some(action);
             }
-            40 => {
-                // replacement colours
-                let len = buf.get_u8();
-                let _ = (0..len)
-                    .map(|_| (buf.get_u16(), buf.get_u16()))
-                    .collect::<Vec<_>>();
-            }
+            40 => definition.recolours = Recolour::decode_all(buf),
             60 => {
                 let len = buf.get_u8();
-                let _additional_models = (0..len).map(|_| buf.get_u16()).collect::<Vec<u16>>();
+                definition.head_models = (0..len).map(|_| buf.get_u16()).collect();
             }
-            90..=92 => {
-                buf.get_u16();
+            opcode @ 90..=92 => {
+                definition.unused[opcode as usize - 90] = Some(buf.get_u16());
             }
             93 => {
                 definition.visible_on_minimap = false;
             }
-            95 => {
-                definition.combat_level = match buf.get_u16() {
-                    0 => None,
-                    level => Some(level),
-                }
-            }
-            97 | 98 => {
-                buf.get_u16();
-            }
+            95 => definition.combat_level = Some(buf.get_u16()),
+            97 => definition.scale_xz = Some(buf.get_u16()),
+            98 => definition.scale_y = Some(buf.get_u16()),
             99 => {
                 definition.visible = true;
             }
-            100 | 101 => {
-                buf.get_u8();
-            }
-            102 | 103 => {
-                buf.get_u16();
-            }
-            106 => {
-                buf.get_u16();
-                buf.get_u16();
-                let len = buf.get_u8();
-                let _ = (0..len + 1).map(|_| buf.get_u16()).collect::<Vec<u16>>();
-            }
+            100 => definition.light = Some(buf.get_i8()),
+            101 => definition.shadow = Some(buf.get_i8()),
+            102 => definition.head_icon = Some(buf.get_u16()),
+            103 => definition.turn_degrees = Some(buf.get_u16()),
+            106 => definition.morphism = Some(Morphism::decode(buf)),
             107 => definition.clickable = false,
             opcode => {
                 return Err(CacheError::DecodeDefinition {
@@ -208,3 +301,152 @@ fn decode_definition<B: GameBuf>(npc_id: u16, buf: &mut B) -> crate::Result<Enti
         }
     }
 }
+
+fn encode_definition(definition: &EntityDefinition) -> Bytes {
+    let mut buf = BytesMut::new();
+
+    if !definition.models.is_empty() {
+        buf.put_u8(1);
+        put_models(&mut buf, &definition.models);
+    }
+    if !definition.name.is_empty() {
+        buf.put_u8(2);
+        buf.put_rs_string(definition.name.clone());
+    }
+    if !definition.examine_text.is_empty() {
+        buf.put_u8(3);
+        buf.put_rs_string(definition.examine_text.clone());
+    }
+    if let Some(size) = definition.size {
+        buf.put_u8(12);
+        buf.put_u8(size);
+    }
+    if let Some(stand) = definition.anim_stand {
+        buf.put_u8(13);
+        buf.put_u16(stand);
+    }
+    match definition.anim_walk {
+        [walk, None, None, None] => {
+            if let Some(walk) = walk {
+                buf.put_u8(14);
+                buf.put_u16(walk);
+            }
+        }
+        animations => {
+            buf.put_u8(17);
+            for animation in animations.iter() {
+                buf.put_u16(animation.unwrap_or(u16::MAX));
+            }
+        }
+    }
+    for (opcode, action) in (30..).zip(definition.interact_actions.iter()) {
+        if let Some(action) = action {
+            buf.put_u8(opcode);
+            buf.put_rs_string(action.clone());
+        }
+    }
+    if !definition.recolours.is_empty() {
+        buf.put_u8(40);
+        Recolour::encode_all(&definition.recolours, &mut buf);
+    }
+    if !definition.head_models.is_empty() {
+        buf.put_u8(60);
+        put_models(&mut buf, &definition.head_models);
+    }
+    for (opcode, value) in (90..).zip(definition.unused.iter()) {
+        if let Some(value) = value {
+            buf.put_u8(opcode);
+            buf.put_u16(*value);
+        }
+    }
+    if !definition.visible_on_minimap {
+        buf.put_u8(93);
+    }
+    let values = [
+        (95, definition.combat_level),
+        (97, definition.scale_xz),
+        (98, definition.scale_y),
+    ];
+    for (opcode, value) in values.iter() {
+        if let Some(value) = value {
+            buf.put_u8(*opcode);
+            buf.put_u16(*value);
+        }
+    }
+    if definition.visible {
+        buf.put_u8(99);
+    }
+    if let Some(light) = definition.light {
+        buf.put_u8(100);
+        buf.put_i8(light);
+    }
+    if let Some(shadow) = definition.shadow {
+        buf.put_u8(101);
+        buf.put_i8(shadow);
+    }
+    if let Some(head_icon) = definition.head_icon {
+        buf.put_u8(102);
+        buf.put_u16(head_icon);
+    }
+    if let Some(turn_degrees) = definition.turn_degrees {
+        buf.put_u8(103);
+        buf.put_u16(turn_degrees);
+    }
+    if let Some(morphism) = &definition.morphism {
+        buf.put_u8(106);
+        morphism.encode(&mut buf);
+    }
+    if !definition.clickable {
+        buf.put_u8(107);
+    }
+    buf.put_u8(0);
+    buf.freeze()
+}
+
+fn put_models(buf: &mut BytesMut, models: &[u16]) {
+    buf.put_u8(models.len() as u8);
+    for model in models {
+        buf.put_u16(*model);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn encode_round_trip() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            1, 2, 0x00, 0x01, 0x00, 0x02,
+            2, b'M', b'a', b'n', 10,
+            12, 2,
+            13, 0x03, 0x28,
+            17, 0x03, 0x33, 0x03, 0x34, 0x03, 0x35, 0x03, 0x36,
+            32, b'A', b't', b't', b'a', b'c', b'k', 10,
+            93,
+            95, 0x00, 0x02,
+            100, 0xE2,
+            106, 0xFF, 0xFF, 0x00, 0x10, 1, 0x00, 0x04, 0xFF, 0xFF,
+            107,
+            0,
+        ];
+
+        let definition = decode_definition(1, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(
+            definition.animation(EntityAnimation::TurnRight),
+            Some(0x336)
+        );
+        assert_eq!(definition.light, Some(-30));
+        assert!(!definition.is_clickable());
+        assert_eq!(definition.animations().count(), 5);
+        assert_eq!(definition.scale(), (128, 128));
+
+        let morphism = definition.morphism().expect("morphism");
+        assert_eq!(morphism.varbit_id(), None);
+        assert_eq!(morphism.varp_id(), Some(16));
+        let children = morphism.child_ids().collect::<Vec<_>>();
+        assert_eq!(children, vec![Some(4), None]);
+        assert_eq!(&encode_definition(&definition)[..], &data[..]);
+    }
+}
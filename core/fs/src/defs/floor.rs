@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use super::config_entry;
+use crate::{CacheError, CacheFileSystem, Texture};
+use bytes::Buf;
+use mithril_buf::GameBuf;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A colour in the hue, saturation and lightness model used by the client, with each component
+/// scaled to the range `0..=255`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Hsl {
+    pub hue: u8,
+    pub saturation: u8,
+    pub lightness: u8,
+}
+
+impl Hsl {
+    /// Converts a 24-bit RGB colour the same way the client converts floor colours.
+    pub fn from_rgb(rgb: u32) -> Self {
+        let r = f64::from((rgb >> 16) & 0xFF) / 256.0;
+        let g = f64::from((rgb >> 8) & 0xFF) / 256.0;
+        let b = f64::from(rgb & 0xFF) / 256.0;
+
+        let min = r.min(g).min(b);
+        let max = r.max(g).max(b);
+        let lightness = (min + max) / 2.0;
+
+        let mut hue = 0.0;
+        let mut saturation = 0.0;
+        if min != max {
+            saturation = if lightness < 0.5 {
+                (max - min) / (max + min)
+            } else {
+                (max - min) / (2.0 - max - min)
+            };
+
+            hue = if r == max {
+                (g - b) / (max - min)
+            } else if g == max {
+                2.0 + (b - r) / (max - min)
+            } else {
+                4.0 + (r - g) / (max - min)
+            };
+        }
+        hue /= 6.0;
+        if hue < 0.0 {
+            hue += 1.0;
+        }
+
+        let component = |value: f64| (value * 256.0).clamp(0.0, 255.0) as u8;
+        Self {
+            hue: component(hue),
+            saturation: component(saturation),
+            lightness: component(lightness),
+        }
+    }
+
+    /// Packs the colour into the 16-bit format used by models and the scene renderer.
+    pub fn to_packed(self) -> u16 {
+        let mut saturation = u16::from(self.saturation);
+        let lightness = u16::from(self.lightness);
+        for threshold in [179, 192, 217, 243].iter() {
+            if lightness > *threshold {
+                saturation /= 2;
+            }
+        }
+        ((u16::from(self.hue) / 4) << 10) | ((saturation / 32) << 7) | (lightness / 2)
+    }
+
+    /// Converts a packed 16-bit colour to 24-bit RGB, matching the client's palette before any
+    /// brightness adjustment is applied.
+    pub fn packed_to_rgb(packed: u16) -> u32 {
+        let hue = f64::from(packed >> 10) / 64.0 + 0.0078125;
+        let saturation = f64::from((packed >> 7) & 7) / 8.0 + 0.0625;
+        let lightness = f64::from(packed & 0x7F) / 128.0;
+
+        let q = if lightness < 0.5 {
+            lightness * (1.0 + saturation)
+        } else {
+            lightness + saturation - lightness * saturation
+        };
+        let p = 2.0 * lightness - q;
+        let component = |mut t: f64| {
+            if t > 1.0 {
+                t -= 1.0;
+            } else if t < 0.0 {
+                t += 1.0;
+            }
+            let value = if 6.0 * t < 1.0 {
+                p + (q - p) * 6.0 * t
+            } else if 2.0 * t < 1.0 {
+                q
+            } else if 3.0 * t < 2.0 {
+                p + (q - p) * (2.0 / 3.0 - t) * 6.0
+            } else {
+                p
+            };
+            ((value * 256.0) as u32).min(255)
+        };
+
+        let r = component(hue + 1.0 / 3.0);
+        let g = component(hue);
+        let b = component(hue - 1.0 / 3.0);
+        (r << 16) | (g << 8) | b
+    }
+}
+
+/// An underlay or overlay that can be painted onto a map tile.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloorDefinition {
+    id: u16,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    name: Option<String>,
+    rgb: u32,
+    hsl: Hsl,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    minimap_rgb: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    texture_id: Option<u8>,
+    occlude: bool,
+}
+
+impl Default for FloorDefinition {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: None,
+            rgb: 0,
+            hsl: Hsl::default(),
+            minimap_rgb: None,
+            texture_id: None,
+            occlude: true,
+        }
+    }
+}
+
+impl FloorDefinition {
+    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
+        let mut data = config_entry(cache, "flo.dat")?;
+        let count = data.get_u16();
+        (0..count)
+            .map(|id| decode_definition(id, &mut data))
+            .collect()
+    }
+
+    /// The ID of the definition, which tiles reference as one less than their stored
+    /// underlay or overlay.
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+
+    pub fn rgb(&self) -> u32 {
+        self.rgb
+    }
+
+    pub fn hsl(&self) -> Hsl {
+        self.hsl
+    }
+
+    /// The colour drawn on the minimap, when it differs from the tile colour.
+    pub fn minimap_rgb(&self) -> Option<u32> {
+        self.minimap_rgb
+    }
+
+    pub fn texture_id(&self) -> Option<u8> {
+        self.texture_id
+    }
+
+    /// The average colour of the floor's texture, or `None` if it is untextured or the texture
+    /// does not exist.
+    pub fn texture_rgb(&self, textures: &HashMap<u16, Texture>) -> Option<u32> {
+        let texture_id = u16::from(self.texture_id?);
+        textures.get(&texture_id).map(Texture::average_rgb)
+    }
+
+    /// Whether the floor hides the faces of the tiles beneath it.
+    pub fn occludes(&self) -> bool {
+        self.occlude
+    }
+}
+
+fn decode_definition<B: GameBuf>(floor_id: u16, buf: &mut B) -> crate::Result<FloorDefinition> {
+    let mut definition = FloorDefinition {
+        id: floor_id,
+        ..FloorDefinition::default()
+    };
+    loop {
+        match buf.get_u8() {
+            0 => return Ok(definition),
+            1 => {
+                definition.rgb = buf.get_uint(3) as u32;
+                definition.hsl = Hsl::from_rgb(definition.rgb);
+            }
+            2 => definition.texture_id = Some(buf.get_u8()),
+            3 => {}
+            5 => definition.occlude = false,
+            6 => definition.name = Some(buf.get_rs_string()),
+            7 => definition.minimap_rgb = Some(buf.get_uint(3) as u32),
+            opcode => {
+                return Err(CacheError::DecodeDefinition {
+                    ty: "Floor",
+                    opcode,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    pub fn decode_floor() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            1, 0x35, 0x5A, 0x1E,
+            2, 1,
+            5,
+            6, b'w', b'a', b't', b'e', b'r', 10,
+            0,
+        ];
+
+        let definition = decode_definition(3, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.rgb(), 0x355A1E);
+        assert_eq!(definition.texture_id(), Some(1));
+        assert!(!definition.occludes());
+        assert_eq!(definition.name().map(String::as_str), Some("water"));
+    }
+
+    #[test]
+    pub fn rgb_to_hsl() {
+        assert_eq!(
+            Hsl::from_rgb(0xFF0000),
+            Hsl {
+                hue: 0,
+                saturation: 255,
+                lightness: 127
+            }
+        );
+        assert_eq!(Hsl::from_rgb(0x00FF00).hue, 85);
+        assert_eq!(Hsl::from_rgb(0x808080).saturation, 0);
+        assert_eq!(Hsl::from_rgb(0xFFFFFF).to_packed(), 127);
+    }
+}
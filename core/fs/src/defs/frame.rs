@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+
+use bytes::{Buf, Bytes};
+
+use super::SequenceDefinition;
+use crate::{CacheError, CacheFileSystem, FrameError};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const FRAME_INDEX: usize = 2;
+const FOOTER_LEN: usize = 8;
+
+/// How a frame transform is applied to the vertices or faces of its label group.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformType {
+    /// Sets the origin used by the rotations and scales that follow.
+    Origin,
+    Translate,
+    Rotate,
+    Scale,
+    /// Changes the transparency of the labelled faces.
+    Alpha,
+    Unknown(u8),
+}
+
+impl From<u8> for TransformType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => TransformType::Origin,
+            1 => TransformType::Translate,
+            2 => TransformType::Rotate,
+            3 => TransformType::Scale,
+            5 => TransformType::Alpha,
+            value => TransformType::Unknown(value),
+        }
+    }
+}
+
+/// A transform of a [`FrameBase`] and the model labels it applies to.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseTransform {
+    pub ty: TransformType,
+    pub labels: Vec<u8>,
+}
+
+/// The skeleton shared by every frame in a file, listing the transforms a frame can apply.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameBase {
+    transforms: Vec<BaseTransform>,
+}
+
+impl FrameBase {
+    pub fn transforms(&self) -> &[BaseTransform] {
+        &self.transforms
+    }
+
+    fn transform_type(&self, index: usize) -> TransformType {
+        self.transforms[index].ty
+    }
+}
+
+/// A transform applied by a single frame, referring to a transform of the [`FrameBase`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTransform {
+    pub base_index: u8,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// A single pose of an animation.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    id: u16,
+    duration: u8,
+    transforms: Vec<FrameTransform>,
+}
+
+impl Frame {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// How long the frame is shown for in client cycles, used when a sequence does not specify a
+    /// duration of its own.
+    pub fn duration(&self) -> u8 {
+        self.duration
+    }
+
+    pub fn transforms(&self) -> &[FrameTransform] {
+        &self.transforms
+    }
+}
+
+/// The frames stored in a single file of index 2, along with the frame base they share.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameGroup {
+    file_id: u16,
+    base: FrameBase,
+    frames: Vec<Frame>,
+}
+
+/// A sequence frame that does not exist in any frame group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingFrame {
+    pub sequence_id: u16,
+    pub frame_id: u16,
+}
+
+impl FrameGroup {
+    pub fn load(cache: &CacheFileSystem, file_id: u16) -> crate::Result<Self> {
+        let file = cache.get_file(FRAME_INDEX, file_id as usize)?;
+        Self::decode(file_id, crate::compression::decompress_gzip(file)?)
+    }
+
+    /// Loads every frame group in the cache, skipping files that are empty.
+    pub fn load_all(cache: &CacheFileSystem) -> crate::Result<Vec<Self>> {
+        let mut groups = Vec::new();
+        for file_id in 0..cache.len(FRAME_INDEX)? {
+            let file = cache.get_file(FRAME_INDEX, file_id)?;
+            if file.is_empty() {
+                continue;
+            }
+            let data = crate::compression::decompress_gzip(file)?;
+            groups.push(Self::decode(file_id as u16, data)?);
+        }
+        Ok(groups)
+    }
+
+    /// Decodes a frame group from its decompressed contents.
+    ///
+    /// The file is split into sections for the frame headers, transform flags, transform values
+    /// and durations, the lengths of which are stored in the final 8 bytes. The frame base takes
+    /// up the rest of the file before the lengths.
+    pub fn decode(file_id: u16, data: Bytes) -> crate::Result<Self> {
+        ensure(&data, FOOTER_LEN)?;
+        let mut footer = data.slice(data.len() - FOOTER_LEN..);
+        let header_len = footer.get_u16() as usize + 2;
+        let flags_len = footer.get_u16() as usize;
+        let values_len = footer.get_u16() as usize;
+        let durations_len = footer.get_u16() as usize;
+
+        let values_start = header_len + flags_len;
+        let durations_start = values_start + values_len;
+        let end = durations_start + durations_len;
+        if end > data.len() - FOOTER_LEN {
+            return Err(FrameError::Truncated.into());
+        }
+        let mut headers = data.slice(..header_len);
+        let mut transform_flags = data.slice(header_len..values_start);
+        let mut values = data.slice(values_start..durations_start);
+        let mut durations = data.slice(durations_start..end);
+        let base = decode_base(data.slice(end..data.len() - FOOTER_LEN))?;
+
+        ensure(&headers, 2)?;
+        let frame_count = headers.get_u16();
+        let mut frames = Vec::with_capacity(frame_count as usize);
+        for _ in 0..frame_count {
+            ensure(&headers, 3)?;
+            let id = headers.get_u16();
+            let transform_count = headers.get_u8() as usize;
+            if transform_count > base.transforms.len() {
+                return Err(FrameError::TransformCount {
+                    frame_id: id,
+                    count: transform_count,
+                }
+                .into());
+            }
+            ensure(&durations, 1)?;
+            let duration = durations.get_u8();
+
+            let mut transforms = Vec::with_capacity(transform_count);
+            let mut previous = None;
+            ensure(&transform_flags, transform_count)?;
+            for index in 0..transform_count {
+                let flags = transform_flags.get_u8();
+                if flags == 0 {
+                    continue;
+                }
+
+                // The client resets the origin before any transform that is not preceded by one,
+                // so the implicit origin is made explicit here.
+                let ty = base.transform_type(index);
+                if ty != TransformType::Origin {
+                    let origin = (0..index)
+                        .rev()
+                        .take_while(|origin| Some(*origin) != previous)
+                        .find(|origin| base.transform_type(*origin) == TransformType::Origin);
+                    if let Some(origin) = origin {
+                        transforms.push(FrameTransform {
+                            base_index: origin as u8,
+                            x: 0,
+                            y: 0,
+                            z: 0,
+                        });
+                    }
+                }
+
+                let default = if ty == TransformType::Scale { 128 } else { 0 };
+                let mut component = |bit: u8| {
+                    if flags & bit != 0 {
+                        crate::get_signed_smart(&mut values).ok_or(FrameError::Truncated)
+                    } else {
+                        Ok(default)
+                    }
+                };
+                transforms.push(FrameTransform {
+                    base_index: index as u8,
+                    x: component(1)?,
+                    y: component(2)?,
+                    z: component(4)?,
+                });
+                previous = Some(index);
+            }
+
+            frames.push(Frame {
+                id,
+                duration,
+                transforms,
+            });
+        }
+
+        Ok(FrameGroup {
+            file_id,
+            base,
+            frames,
+        })
+    }
+
+    pub fn file_id(&self) -> u16 {
+        self.file_id
+    }
+
+    pub fn base(&self) -> &FrameBase {
+        &self.base
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The duration of every frame across the groups, keyed by frame ID.
+    pub fn durations(groups: &[Self]) -> HashMap<u16, u8> {
+        groups
+            .iter()
+            .flat_map(|group| group.frames.iter())
+            .map(|frame| (frame.id, frame.duration))
+            .collect()
+    }
+
+    /// Finds every primary or secondary frame of a sequence that is not in any of the groups.
+    pub fn validate(groups: &[Self], sequences: &[SequenceDefinition]) -> Vec<DanglingFrame> {
+        let frame_ids: HashSet<u16> = groups
+            .iter()
+            .flat_map(|group| group.frames.iter())
+            .map(Frame::id)
+            .collect();
+
+        let mut dangling = Vec::new();
+        for sequence in sequences.iter() {
+            let secondary = sequence.secondary_frame_ids().iter().filter_map(|id| *id);
+            for frame_id in sequence.frame_ids().iter().copied().chain(secondary) {
+                if !frame_ids.contains(&frame_id) {
+                    dangling.push(DanglingFrame {
+                        sequence_id: sequence.id(),
+                        frame_id,
+                    });
+                }
+            }
+        }
+        dangling
+    }
+}
+
+fn decode_base(mut buf: Bytes) -> crate::Result<FrameBase> {
+    ensure(&buf, 1)?;
+    let count = buf.get_u8() as usize;
+    ensure(&buf, count)?;
+    let types: Vec<TransformType> = (0..count).map(|_| buf.get_u8().into()).collect();
+
+    // Each transform's label count is followed by its labels.
+    let mut transforms = Vec::with_capacity(count);
+    for ty in types {
+        ensure(&buf, 1)?;
+        let label_count = buf.get_u8() as usize;
+        ensure(&buf, label_count)?;
+        let labels = (0..label_count).map(|_| buf.get_u8()).collect();
+        transforms.push(BaseTransform { ty, labels });
+    }
+    Ok(FrameBase { transforms })
+}
+
+fn ensure(buf: &Bytes, len: usize) -> Result<(), CacheError> {
+    if buf.remaining() < len {
+        return Err(FrameError::Truncated.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group() -> Bytes {
+        #[rustfmt::skip]
+        let data: &[u8] = &[
+            // frame headers: frame count, then the ID and transform count of each frame
+            0x00, 0x01,
+            0x00, 0x07, 2,
+            // transform flags
+            0, 3,
+            // transform values
+            69, 61,
+            // durations
+            4,
+            // frame base: transform count, types, then the labels of each transform
+            2, 0, 2,
+            1, 0,
+            2, 1, 2,
+            // footer: the length of each section, the first excluding the frame count
+            0x00, 0x03, 0x00, 0x02, 0x00, 0x02, 0x00, 0x01,
+        ];
+        Bytes::from(data)
+    }
+
+    #[test]
+    pub fn decode_group() {
+        let group = FrameGroup::decode(3, group()).expect("decode");
+        assert_eq!(group.file_id(), 3);
+        assert_eq!(
+            group.base().transforms(),
+            &[
+                BaseTransform {
+                    ty: TransformType::Origin,
+                    labels: vec![0],
+                },
+                BaseTransform {
+                    ty: TransformType::Rotate,
+                    labels: vec![1, 2],
+                },
+            ]
+        );
+
+        let frame = &group.frames()[0];
+        assert_eq!(frame.id(), 7);
+        assert_eq!(frame.duration(), 4);
+        assert_eq!(
+            frame.transforms(),
+            &[
+                FrameTransform {
+                    base_index: 0,
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                },
+                FrameTransform {
+                    base_index: 1,
+                    x: 5,
+                    y: -3,
+                    z: 0,
+                },
+            ],
+            "rotation should be preceded by an implicit origin"
+        );
+    }
+
+    #[test]
+    pub fn error_truncated() {
+        let data = group();
+        let mut truncated = data[..data.len() - FOOTER_LEN - 3].to_vec();
+        truncated.extend_from_slice(&data[data.len() - FOOTER_LEN..]);
+        match FrameGroup::decode(0, Bytes::from(truncated)) {
+            Err(CacheError::Frame(FrameError::Truncated)) => {}
+            _ => panic!("frame base is missing its labels"),
+        }
+    }
+}
@@ -1,56 +1,105 @@
+use super::{pack_definitions, Recolour};
 use crate::{ArchiveError, CacheError, CacheFileSystem};
-use bytes::Buf;
-use mithril_buf::GameBuf;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use mithril_buf::{GameBuf, GameBufMut};
 use std::io::{prelude::*, Cursor, SeekFrom};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// An alternative appearance used when a stack holds at least `amount` of the item, such as the
+/// larger piles of coins.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackVariant {
+    pub id: u16,
+    pub amount: u16,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct ItemDefinition {
     id: u16,
     name: String,
     examine_text: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    model_id: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    sprite_zoom: Option<u16>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    sprite_rotation: [Option<u16>; 3],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    sprite_offset: [Option<i16>; 2],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    unused: Option<u16>,
     member_only: bool,
     stackable: bool,
     #[cfg_attr(
         feature = "serde",
         serde(skip_serializing_if = "crate::skip_empty_options")
     )]
+    male_models: [Option<u16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    male_offset: Option<i8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    female_models: [Option<u16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    female_offset: Option<i8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
     ground_actions: [Option<String>; 5],
     #[cfg_attr(
         feature = "serde",
         serde(skip_serializing_if = "crate::skip_empty_options")
     )]
     inventory_actions: [Option<String>; 5],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    recolours: Vec<Recolour>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    male_head_models: [Option<u16>; 2],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    female_head_models: [Option<u16>; 2],
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     noted_sprite_id: Option<u16>,
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     noted_info_id: Option<u16>,
-    value: i32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    stack_variants: [Option<StackVariant>; 10],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    scale: [Option<u16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    ambient: Option<i8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    contrast: Option<i8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    value: Option<i32>,
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     team: Option<u8>,
 }
 
-impl Default for ItemDefinition {
-    fn default() -> Self {
-        Self {
-            id: 0,
-            name: String::default(),
-            examine_text: String::default(),
-            member_only: false,
-            stackable: false,
-            ground_actions: [None, None, None, None, None],
-            inventory_actions: [None, None, None, None, None],
-            noted_sprite_id: None,
-            noted_info_id: None,
-            value: 0,
-            team: None,
-        }
-    }
-}
-
 impl ItemDefinition {
     pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
         let archive = cache.get_archive(0, 2)?;
@@ -93,6 +142,16 @@ impl ItemDefinition {
         Ok(definitions)
     }
 
+    /// Encodes the definitions into the contents of the `obj.dat` and `obj.idx` archive entries,
+    /// returned in that order.
+    ///
+    /// Noted definitions are written without the information copied from their template by
+    /// [`load`](#method.load).
+    pub fn encode(definitions: &[Self]) -> crate::Result<(Bytes, Bytes)> {
+        let encoded = definitions.iter().map(encode_definition).collect();
+        pack_definitions("Item", encoded)
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
@@ -114,7 +173,7 @@ impl ItemDefinition {
     }
 
     pub fn value(&self) -> i32 {
-        self.value
+        self.value.unwrap_or(0)
     }
 
     pub fn ground_action(&self, index: usize) -> Option<&String> {
@@ -128,6 +187,109 @@ impl ItemDefinition {
     pub fn team(&self) -> Option<u8> {
         self.team
     }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// The model used when the item is displayed on the ground or in an interface.
+    pub fn model_id(&self) -> Option<u16> {
+        self.model_id
+    }
+
+    pub fn sprite_zoom(&self) -> Option<u16> {
+        self.sprite_zoom
+    }
+
+    /// The rotation of the model around the x, y and z axes when drawn as an inventory sprite.
+    pub fn sprite_rotation(&self) -> [Option<u16>; 3] {
+        self.sprite_rotation
+    }
+
+    pub fn sprite_offset(&self) -> [Option<i16>; 2] {
+        self.sprite_offset
+    }
+
+    /// The models drawn on a male player wielding the item.
+    pub fn male_models(&self) -> impl Iterator<Item = u16> + '_ {
+        self.male_models.iter().filter_map(|model| *model)
+    }
+
+    /// The models drawn on a female player wielding the item.
+    pub fn female_models(&self) -> impl Iterator<Item = u16> + '_ {
+        self.female_models.iter().filter_map(|model| *model)
+    }
+
+    pub fn male_offset(&self) -> i8 {
+        self.male_offset.unwrap_or(0)
+    }
+
+    pub fn female_offset(&self) -> i8 {
+        self.female_offset.unwrap_or(0)
+    }
+
+    pub fn male_head_models(&self) -> impl Iterator<Item = u16> + '_ {
+        self.male_head_models.iter().filter_map(|model| *model)
+    }
+
+    pub fn female_head_models(&self) -> impl Iterator<Item = u16> + '_ {
+        self.female_head_models.iter().filter_map(|model| *model)
+    }
+
+    /// Whether the item has any models to draw on a player, which is required to wield it.
+    pub fn has_wield_models(&self) -> bool {
+        self.male_models[0].is_some() || self.female_models[0].is_some()
+    }
+
+    pub fn recolours(&self) -> &[Recolour] {
+        &self.recolours
+    }
+
+    pub fn noted_info_id(&self) -> Option<u16> {
+        self.noted_info_id
+    }
+
+    pub fn noted_sprite_id(&self) -> Option<u16> {
+        self.noted_sprite_id
+    }
+
+    pub fn stack_variants(&self) -> impl Iterator<Item = &StackVariant> {
+        self.stack_variants
+            .iter()
+            .filter_map(|variant| variant.as_ref())
+    }
+
+    /// Finds the item that should be displayed for a stack of `amount`, mirroring the client by
+    /// picking the last variant whose amount has been reached.
+    pub fn stack_variant(&self, amount: u32) -> Option<u16> {
+        if amount <= 1 {
+            return None;
+        }
+
+        self.stack_variants()
+            .filter(|variant| variant.amount != 0 && amount >= u32::from(variant.amount))
+            .last()
+            .map(|variant| variant.id)
+    }
+
+    /// The scale of the model along the x, y and z axes, where 128 is the original size.
+    pub fn scale(&self) -> [u16; 3] {
+        let mut scale = [128; 3];
+        for (scale, value) in scale.iter_mut().zip(self.scale.iter()) {
+            if let Some(value) = value {
+                *scale = *value;
+            }
+        }
+        scale
+    }
+
+    pub fn ambient(&self) -> i8 {
+        self.ambient.unwrap_or(0)
+    }
+
+    pub fn contrast(&self) -> i8 {
+        self.contrast.unwrap_or(0)
+    }
 }
 
 // TODO: Can this be made less ugly?
@@ -140,7 +302,7 @@ fn decode_definition<B: GameBuf>(item_id: u16, buf: &mut B) -> crate::Result<Ite
                 return Ok(definition);
             }
             1 => {
-                buf.get_u16();
+                definition.model_id = Some(buf.get_u16());
             }
             2 => {
                 definition.name = buf.get_rs_string();
@@ -148,31 +310,43 @@ fn decode_definition<B: GameBuf>(item_id: u16, buf: &mut B) -> crate::Result<Ite
             3 => {
                 definition.examine_text = buf.get_rs_string();
             }
-            4..=8 | 10 => {
-                buf.get_u16();
+            4 => {
+                definition.sprite_zoom = Some(buf.get_u16());
+            }
+            5 => {
+                definition.sprite_rotation[0] = Some(buf.get_u16());
+            }
+            6 => {
+                definition.sprite_rotation[1] = Some(buf.get_u16());
+            }
+            opcode @ 7..=8 => {
+                definition.sprite_offset[opcode as usize - 7] = Some(buf.get_i16());
+            }
+            10 => {
+                definition.unused = Some(buf.get_u16());
             }
             11 => {
                 definition.stackable = true;
             }
             12 => {
-                definition.value = buf.get_i32();
+                definition.value = Some(buf.get_i32());
             }
             16 => {
                 definition.member_only = true;
             }
             23 => {
-                buf.get_u16();
-                buf.get_u8();
+                definition.male_models[0] = Some(buf.get_u16());
+                definition.male_offset = Some(buf.get_i8());
             }
             24 => {
-                buf.get_u16();
+                definition.male_models[1] = Some(buf.get_u16());
             }
             25 => {
-                buf.get_u16();
-                buf.get_u8();
+                definition.female_models[0] = Some(buf.get_u16());
+                definition.female_offset = Some(buf.get_i8());
             }
             26 => {
-                buf.get_u16();
+                definition.female_models[1] = Some(buf.get_u16());
             }
             opcode if (30..=34).contains(&opcode) => {
                 definition.ground_actions[opcode as usize - 30] = Some(buf.get_rs_string());
@@ -181,14 +355,28 @@ fn decode_definition<B: GameBuf>(item_id: u16, buf: &mut B) -> crate::Result<Ite
                 definition.inventory_actions[opcode as usize - 35] = Some(buf.get_rs_string());
             }
             40 => {
-                let colours = buf.get_u8();
-                for _ in 0..colours {
-                    buf.get_u16();
-                    buf.get_u16();
-                }
+                definition.recolours = Recolour::decode_all(buf);
+            }
+            78 => {
+                definition.male_models[2] = Some(buf.get_u16());
+            }
+            79 => {
+                definition.female_models[2] = Some(buf.get_u16());
             }
-            78 | 79 | 90..=93 | 95 => {
-                buf.get_u16();
+            90 => {
+                definition.male_head_models[0] = Some(buf.get_u16());
+            }
+            91 => {
+                definition.female_head_models[0] = Some(buf.get_u16());
+            }
+            92 => {
+                definition.male_head_models[1] = Some(buf.get_u16());
+            }
+            93 => {
+                definition.female_head_models[1] = Some(buf.get_u16());
+            }
+            95 => {
+                definition.sprite_rotation[2] = Some(buf.get_u16());
             }
             97 => {
                 definition.noted_info_id = Some(buf.get_u16());
@@ -196,15 +384,20 @@ fn decode_definition<B: GameBuf>(item_id: u16, buf: &mut B) -> crate::Result<Ite
             98 => {
                 definition.noted_sprite_id = Some(buf.get_u16());
             }
-            100..=109 => {
-                buf.get_u16();
-                buf.get_u16();
+            opcode @ 100..=109 => {
+                definition.stack_variants[opcode as usize - 100] = Some(StackVariant {
+                    id: buf.get_u16(),
+                    amount: buf.get_u16(),
+                });
+            }
+            opcode @ 110..=112 => {
+                definition.scale[opcode as usize - 110] = Some(buf.get_u16());
             }
-            110..=112 => {
-                buf.get_u16();
+            113 => {
+                definition.ambient = Some(buf.get_i8());
             }
-            113 | 114 => {
-                buf.get_u8();
+            114 => {
+                definition.contrast = Some(buf.get_i8());
             }
             115 => {
                 definition.team = Some(buf.get_u8());
@@ -215,3 +408,213 @@ fn decode_definition<B: GameBuf>(item_id: u16, buf: &mut B) -> crate::Result<Ite
         }
     }
 }
+
+fn encode_definition(definition: &ItemDefinition) -> Bytes {
+    let mut buf = BytesMut::new();
+    let noted = definition.is_noted();
+
+    if let Some(model_id) = definition.model_id {
+        buf.put_u8(1);
+        buf.put_u16(model_id);
+    }
+    if !noted && !definition.name.is_empty() {
+        buf.put_u8(2);
+        buf.put_rs_string(definition.name.clone());
+    }
+    if !noted && !definition.examine_text.is_empty() {
+        buf.put_u8(3);
+        buf.put_rs_string(definition.examine_text.clone());
+    }
+    if let Some(zoom) = definition.sprite_zoom {
+        buf.put_u8(4);
+        buf.put_u16(zoom);
+    }
+    for (opcode, rotation) in [5, 6].iter().zip(definition.sprite_rotation.iter()) {
+        if let Some(rotation) = rotation {
+            buf.put_u8(*opcode);
+            buf.put_u16(*rotation);
+        }
+    }
+    for (opcode, offset) in (7..=8).zip(definition.sprite_offset.iter()) {
+        if let Some(offset) = offset {
+            buf.put_u8(opcode);
+            buf.put_i16(*offset);
+        }
+    }
+    if let Some(unused) = definition.unused {
+        buf.put_u8(10);
+        buf.put_u16(unused);
+    }
+    if !noted && definition.stackable {
+        buf.put_u8(11);
+    }
+    if let Some(value) = definition.value.filter(|_| !noted) {
+        buf.put_u8(12);
+        buf.put_i32(value);
+    }
+    if !noted && definition.member_only {
+        buf.put_u8(16);
+    }
+    encode_wield_models(
+        &mut buf,
+        (23, 24),
+        &definition.male_models,
+        definition.male_offset,
+    );
+    encode_wield_models(
+        &mut buf,
+        (25, 26),
+        &definition.female_models,
+        definition.female_offset,
+    );
+    for (opcode, action) in (30..).zip(definition.ground_actions.iter()) {
+        if let Some(action) = action {
+            buf.put_u8(opcode);
+            buf.put_rs_string(action.clone());
+        }
+    }
+    for (opcode, action) in (35..).zip(definition.inventory_actions.iter()) {
+        if let Some(action) = action {
+            buf.put_u8(opcode);
+            buf.put_rs_string(action.clone());
+        }
+    }
+    if !definition.recolours.is_empty() {
+        buf.put_u8(40);
+        Recolour::encode_all(&definition.recolours, &mut buf);
+    }
+    let models = [
+        (78, definition.male_models[2]),
+        (79, definition.female_models[2]),
+        (90, definition.male_head_models[0]),
+        (91, definition.female_head_models[0]),
+        (92, definition.male_head_models[1]),
+        (93, definition.female_head_models[1]),
+        (95, definition.sprite_rotation[2]),
+        (97, definition.noted_info_id),
+        (98, definition.noted_sprite_id),
+    ];
+    for (opcode, value) in models.iter() {
+        if let Some(value) = value {
+            buf.put_u8(*opcode);
+            buf.put_u16(*value);
+        }
+    }
+    for (opcode, variant) in (100..).zip(definition.stack_variants.iter()) {
+        if let Some(variant) = variant {
+            buf.put_u8(opcode);
+            buf.put_u16(variant.id);
+            buf.put_u16(variant.amount);
+        }
+    }
+    for (opcode, scale) in (110..).zip(definition.scale.iter()) {
+        if let Some(scale) = scale {
+            buf.put_u8(opcode);
+            buf.put_u16(*scale);
+        }
+    }
+    if let Some(ambient) = definition.ambient {
+        buf.put_u8(113);
+        buf.put_i8(ambient);
+    }
+    if let Some(contrast) = definition.contrast {
+        buf.put_u8(114);
+        buf.put_i8(contrast);
+    }
+    if let Some(team) = definition.team {
+        buf.put_u8(115);
+        buf.put_u8(team);
+    }
+    buf.put_u8(0);
+    buf.freeze()
+}
+
+fn encode_wield_models(
+    buf: &mut BytesMut,
+    opcodes: (u8, u8),
+    models: &[Option<u16>; 3],
+    offset: Option<i8>,
+) {
+    if let Some(model) = models[0] {
+        buf.put_u8(opcodes.0);
+        buf.put_u16(model);
+        buf.put_i8(offset.unwrap_or(0));
+    }
+    if let Some(model) = models[1] {
+        buf.put_u8(opcodes.1);
+        buf.put_u16(model);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn encode_round_trip() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            1, 0x04, 0xD2,
+            2, b'C', b'o', b'i', b'n', b's', 10,
+            4, 0x02, 0x58,
+            7, 0xFF, 0xFE,
+            11,
+            12, 0x00, 0x00, 0x00, 0x01,
+            23, 0x01, 0x00, 0xFC,
+            36, b'D', b'r', b'o', b'p', 10,
+            40, 1, 0x00, 0x01, 0x00, 0x02,
+            101, 0x00, 0x0A, 0x00, 0x02,
+            113, 0xF6,
+            0,
+        ];
+
+        let definition = decode_definition(5, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.sprite_offset[0], Some(-2));
+        assert_eq!(definition.male_offset, Some(-4));
+        assert_eq!(
+            definition.stack_variants[1],
+            Some(StackVariant { id: 10, amount: 2 })
+        );
+        assert_eq!(&encode_definition(&definition)[..], &data[..]);
+    }
+
+    #[test]
+    pub fn stack_variant_lookup() {
+        let mut definition = ItemDefinition::default();
+        let variants = [(996, 2), (997, 3), (998, 4), (999, 5), (1000, 25)];
+        for (slot, (id, amount)) in variants.iter().enumerate() {
+            definition.stack_variants[slot] = Some(StackVariant {
+                id: *id,
+                amount: *amount,
+            });
+        }
+
+        assert_eq!(definition.stack_variant(1), None);
+        assert_eq!(definition.stack_variant(2), Some(996));
+        assert_eq!(definition.stack_variant(24), Some(999));
+        assert_eq!(definition.stack_variant(10_000), Some(1000));
+    }
+
+    #[test]
+    pub fn encode_noted_skips_template() {
+        let definition = ItemDefinition {
+            name: "Coins".to_owned(),
+            stackable: true,
+            value: Some(1),
+            noted_info_id: Some(995),
+            ..ItemDefinition::default()
+        };
+        assert_eq!(
+            &encode_definition(&definition)[..],
+            &[97, 0x03, 0xE3, 0][..]
+        );
+    }
+
+    #[test]
+    pub fn encode_packs_definitions() {
+        let definitions = vec![ItemDefinition::default(), ItemDefinition::default()];
+        let (data, index) = ItemDefinition::encode(&definitions).expect("encode");
+        assert_eq!(&data[..], &[0, 2, 0, 0][..]);
+        assert_eq!(&index[..], &[0, 2, 0, 1, 0, 1][..]);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{ArchiveError, CacheFileSystem};
-use bytes::Buf;
+use crate::{ArchiveError, CacheFileSystem, MapError};
+use bytes::{Buf, Bytes};
 use mithril_buf::GameBuf;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter, Result};
@@ -27,7 +27,7 @@ pub struct MapIndex {
 }
 
 impl MapIndex {
-    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<HashMap<u16, Self>> {
+    pub fn load(cache: &CacheFileSystem) -> crate::Result<HashMap<u16, Self>> {
         let archive = cache.get_archive(0, 5)?;
         let mut buf = archive
             .get_entry("map_index")
@@ -67,6 +67,16 @@ impl MapIndex {
     pub fn is_member_only(&self) -> bool {
         self.member_only
     }
+
+    /// The file in index 4 holding the tiles of the region.
+    pub fn map_file_id(&self) -> u16 {
+        self.map_file_id
+    }
+
+    /// The file in index 4 holding the objects placed in the region.
+    pub fn object_file_id(&self) -> u16 {
+        self.object_file_id
+    }
 }
 
 impl Debug for MapIndex {
@@ -90,40 +100,65 @@ pub struct MapObject {
 }
 
 impl MapObject {
-    pub fn load(cache: &mut CacheFileSystem, index: &MapIndex) -> crate::Result<Vec<Self>> {
-        let buf = cache.get_file(4, index.object_file_id as usize)?;
-        let mut buf = crate::compression::decompress_gzip(buf)?;
+    pub fn load(cache: &CacheFileSystem, index: &MapIndex) -> crate::Result<Vec<Self>> {
+        Self::decode_compressed(cache.get_file(4, index.object_file_id as usize)?)
+    }
+
+    /// Decodes the objects placed in a region from its object file as it is stored in the cache.
+    pub fn decode_compressed(file: Bytes) -> crate::Result<Vec<Self>> {
+        if file.is_empty() {
+            return Err(MapError::Truncated.into());
+        }
+        Self::decode(crate::compression::decompress_gzip(file)?)
+    }
 
+    /// Decodes the objects placed in a region from the decompressed contents of its object file.
+    pub fn decode(mut buf: Bytes) -> crate::Result<Vec<Self>> {
         let mut objects = Vec::new();
 
         let mut id = -1;
-        let mut id_offset = buf.get_smart() as i32;
+        let mut id_offset = get_smart(&mut buf)? as i32;
         while id_offset != 0 {
-            id += id_offset as i32;
+            id += id_offset;
 
             let mut packed = 0;
-            let mut position_offset = buf.get_smart() as i16;
+            let mut position_offset = get_smart(&mut buf)? as i16;
             while position_offset != 0 {
                 packed += position_offset - 1;
 
-                let attributes = buf.get_u8();
+                let attributes = get_u8(&mut buf)?;
                 let ty = attributes >> 2;
                 let orientation = attributes & 0x3;
                 objects.push(MapObject {
                     id: id as u16,
                     variant: ty as u16,
-                    orientation: orientation as u8,
+                    orientation,
                     packed_coordinates: packed,
                 });
-                position_offset = buf.get_smart() as i16;
+                position_offset = get_smart(&mut buf)? as i16;
             }
 
-            id_offset = buf.get_smart() as i32;
+            id_offset = get_smart(&mut buf)? as i32;
         }
 
         Ok(objects)
     }
 
+    /// The ID of the `ObjectDefinition` placed.
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// The placement type of the object, such as a wall, wall decoration or floor decoration.
+    pub fn variant(&self) -> u16 {
+        self.variant
+    }
+
+    /// The number of quarter turns the object is rotated by, clockwise from facing west.
+    pub fn orientation(&self) -> u8 {
+        self.orientation
+    }
+
     pub fn get_x(&self) -> i16 {
         (self.packed_coordinates >> 6) & 0x3F
     }
@@ -150,27 +185,26 @@ impl Debug for MapObject {
     }
 }
 
+#[derive(Debug)]
 pub struct MapPlane {
     tiles: [[Tile; MAP_WIDTH]; MAP_WIDTH],
 }
 
 impl MapPlane {
-    pub fn get_tile(&self, x: usize, z: usize) -> &Tile {
-        assert!(x < MAP_WIDTH, "x out of bounds; {}", x);
-        assert!(z < MAP_WIDTH, "z out of bounds; {}", z);
-        &self.tiles[x][z]
+    pub fn get_tile(&self, x: usize, z: usize) -> Option<&Tile> {
+        self.tiles.get(x).and_then(|column| column.get(z))
     }
 
     pub fn is_walkable(&self, x: usize, z: usize) -> bool {
-        self.get_tile(x, z).is_walkable()
+        self.get_tile(x, z).is_some_and(|tile| tile.is_walkable())
     }
 
     pub fn is_bridge(&self, x: usize, z: usize) -> bool {
-        self.get_tile(x, z).is_bridge()
+        self.get_tile(x, z).is_some_and(|tile| tile.is_bridge())
     }
 
-    pub fn height(&self, x: usize, z: usize) -> u16 {
-        self.get_tile(x, z).height
+    pub fn height(&self, x: usize, z: usize) -> Option<u16> {
+        self.get_tile(x, z).map(|tile| tile.height)
     }
 }
 
@@ -182,47 +216,54 @@ impl Default for MapPlane {
     }
 }
 
+#[derive(Debug)]
 pub struct MapFile {
     planes: Vec<MapPlane>,
 }
 
 impl MapFile {
-    pub fn load(cache: &mut CacheFileSystem, index: &MapIndex) -> crate::Result<Self> {
-        let file = cache.get_file(4, index.map_file_id as usize)?;
+    pub fn load(cache: &CacheFileSystem, index: &MapIndex) -> crate::Result<Self> {
+        Self::decode_compressed(cache.get_file(4, index.map_file_id as usize)?)
+    }
+
+    /// Decodes the tiles of a region from its map file as it is stored in the cache.
+    pub fn decode_compressed(file: Bytes) -> crate::Result<Self> {
+        if file.is_empty() {
+            return Err(MapError::Truncated.into());
+        }
+        Self::decode(crate::compression::decompress_gzip(file)?)
+    }
 
-        let mut buf = crate::compression::decompress_gzip(file)?;
+    /// Decodes the tiles of a region from the decompressed contents of its map file.
+    pub fn decode(mut buf: Bytes) -> crate::Result<Self> {
         let mut map_file = MapFile::new();
         for plane in 0..MAP_PLANES {
             for x in 0..MAP_WIDTH {
                 for z in 0..MAP_WIDTH {
+                    let below = match plane {
+                        0 => None,
+                        _ => Some(map_file.planes[plane - 1].tiles[x][z].height),
+                    };
                     let mut tile = map_file.planes[plane].tiles[x][z];
                     let mut read = LOWEST_CONTINUED_TYPE;
                     // TODO: Optimise. ASAP.
                     while read >= LOWEST_CONTINUED_TYPE {
-                        read = buf.get_u8();
+                        read = get_u8(&mut buf)?;
                         match TileUpdate::from(read) {
                             TileUpdate::Height => {
-                                tile.height = if plane == 0 {
-                                    DEFAULT_TILE_HEIGHT
-                                } else {
-                                    map_file.planes[plane].tiles[x][z].height
-                                        + PLANE_HEIGHT_DIFFERENCE
+                                tile.height = match below {
+                                    Some(height) => height + PLANE_HEIGHT_DIFFERENCE,
+                                    None => DEFAULT_TILE_HEIGHT,
                                 }
                             }
                             TileUpdate::HeightFromLower => {
-                                let height = buf.get_u8();
-                                let below = if plane == 0 {
-                                    0
-                                } else {
-                                    map_file.planes[plane].tiles[x][z].height
-                                };
-
+                                let height = get_u8(&mut buf)?;
                                 tile.height = if height == 1 { 0 } else { height as u16 }
                                     * FROM_LOWER_MULTIPLICAND
-                                    + below as u16;
+                                    + below.unwrap_or(0);
                             }
                             TileUpdate::Overlay(tile_type) => {
-                                tile.overlay = buf.get_u8();
+                                tile.overlay = get_u8(&mut buf)?;
                                 tile.overlay_type =
                                     (tile_type - LOWEST_CONTINUED_TYPE) / ORIENTATION_COUNT;
                                 tile.overlay_orientation =
@@ -254,25 +295,40 @@ impl MapFile {
         }
     }
 
-    pub fn get_plane(&self, plane: usize) -> &MapPlane {
-        assert!(plane < MAP_PLANES, "plane out of bounds; {}", plane);
-        &self.planes[plane]
+    pub fn get_plane(&self, plane: usize) -> Option<&MapPlane> {
+        self.planes.get(plane)
     }
 
-    pub fn get_tile(&self, plane: usize, x: usize, z: usize) -> &Tile {
-        self.get_plane(plane).get_tile(x, z)
+    pub fn get_tile(&self, plane: usize, x: usize, z: usize) -> Option<&Tile> {
+        self.get_plane(plane)?.get_tile(x, z)
     }
 
     pub fn is_walkable(&self, plane: usize, x: usize, z: usize) -> bool {
-        self.get_tile(plane, x, z).is_walkable()
+        self.get_tile(plane, x, z)
+            .is_some_and(|tile| tile.is_walkable())
     }
 
     pub fn is_bridge(&self, plane: usize, x: usize, z: usize) -> bool {
-        self.get_tile(plane, x, z).is_bridge()
+        self.get_tile(plane, x, z)
+            .is_some_and(|tile| tile.is_bridge())
+    }
+
+    pub fn height(&self, plane: usize, x: usize, z: usize) -> Option<u16> {
+        self.get_tile(plane, x, z).map(|tile| tile.height)
+    }
+}
+
+fn get_u8(buf: &mut Bytes) -> crate::Result<u8> {
+    if !buf.has_remaining() {
+        return Err(MapError::Truncated.into());
     }
+    Ok(buf.get_u8())
+}
 
-    pub fn height(&self, plane: usize, x: usize, z: usize) -> u16 {
-        self.get_tile(plane, x, z).height
+fn get_smart(buf: &mut Bytes) -> crate::Result<u16> {
+    match buf.first() {
+        Some(first) if *first < 128 || buf.len() >= 2 => Ok(buf.get_smart()),
+        _ => Err(MapError::Truncated.into()),
     }
 }
 
@@ -287,6 +343,10 @@ pub struct Tile {
 }
 
 impl Tile {
+    pub fn height(self) -> u16 {
+        self.height
+    }
+
     pub fn is_walkable(self) -> bool {
         self.attributes & 0x1 != 0x1
     }
@@ -294,6 +354,24 @@ impl Tile {
     pub fn is_bridge(self) -> bool {
         self.attributes & 0x2 == 0x2
     }
+
+    /// The ID of the `FloorDefinition` painted beneath the tile.
+    pub fn underlay_id(self) -> Option<u8> {
+        self.underlay.checked_sub(1)
+    }
+
+    /// The ID of the `FloorDefinition` painted over the tile.
+    pub fn overlay_id(self) -> Option<u8> {
+        self.overlay.checked_sub(1)
+    }
+
+    pub fn overlay_type(self) -> u8 {
+        self.overlay_type
+    }
+
+    pub fn overlay_orientation(self) -> u8 {
+        self.overlay_orientation
+    }
 }
 
 impl Default for Tile {
@@ -332,3 +410,57 @@ impl From<u8> for TileUpdate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_file() -> Vec<u8> {
+        let tiles = MAP_PLANES * MAP_WIDTH * MAP_WIDTH;
+        let mut data = vec![86, 1, 10];
+        data.extend(vec![0; tiles - 1]);
+        data
+    }
+
+    #[test]
+    pub fn decode_map_file() {
+        let map_file = MapFile::decode(Bytes::from(map_file())).expect("decode");
+        let tile = map_file.get_tile(0, 0, 0).expect("tile");
+        assert_eq!(tile.underlay_id(), Some(4));
+        assert_eq!(tile.height(), 80);
+        assert_eq!(map_file.height(1, 0, 0), Some(80 + PLANE_HEIGHT_DIFFERENCE));
+        assert_eq!(map_file.height(0, 1, 0), Some(DEFAULT_TILE_HEIGHT));
+        assert!(map_file.get_tile(0, MAP_WIDTH, 0).is_none());
+        assert!(map_file.get_plane(MAP_PLANES).is_none());
+    }
+
+    #[test]
+    pub fn decode_map_objects() {
+        let data = Bytes::from(&[5, 0x90, 0xC5, 41, 0, 0][..]);
+        let objects = MapObject::decode(data).expect("decode");
+        assert_eq!(objects.len(), 1);
+
+        let object = &objects[0];
+        assert_eq!(
+            (object.id(), object.variant(), object.orientation()),
+            (4, 10, 1)
+        );
+        assert_eq!(
+            (object.get_x(), object.get_y(), object.get_plane()),
+            (3, 4, 1)
+        );
+
+        let truncated = Bytes::from(&[5, 0x90][..]);
+        assert!(MapObject::decode(truncated).is_err());
+    }
+
+    #[test]
+    pub fn error_truncated() {
+        let mut data = map_file();
+        data.pop();
+        match MapFile::decode(Bytes::from(data)) {
+            Err(crate::CacheError::Map(MapError::Truncated)) => {}
+            _ => panic!("final tile is missing"),
+        }
+    }
+}
@@ -1,19 +1,28 @@
-use crate::{ArchiveError, CacheFileSystem};
-use bytes::Buf;
-use mithril_buf::GameBuf;
+use super::{pack_definitions, Morphism, Recolour};
+use crate::{ArchiveError, CacheError, CacheFileSystem};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use mithril_buf::{GameBuf, GameBufMut};
 use std::io::{prelude::*, Cursor, SeekFrom};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ObjectDefinition {
     id: u16,
     name: String,
     examine_text: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    models: Vec<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    model_types: Vec<u8>,
+    /// Whether opcodes 1 and 5 were present, so that they are re-encoded even without models.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    model_opcodes: [bool; 2],
     impenetrable: bool,
-    interactive: bool,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    interactive: Option<bool>,
     obstructive: bool,
     solid: bool,
     #[cfg_attr(
@@ -21,13 +30,46 @@ pub struct ObjectDefinition {
         serde(skip_serializing_if = "crate::skip_empty_options")
     )]
     interact_actions: [Option<String>; 10],
-    length: u8,
-    width: u8,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    length: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    width: Option<u8>,
     rotated: bool,
     casts_shadow: bool,
     hug_terrain: bool,
     low_priority_shading: bool,
     wall: bool,
+    hollow: bool,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    animation: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    decor_displacement: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    ambient: Option<i8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    contrast: Option<i8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    recolours: Vec<Recolour>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    map_function: Option<u16>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    scale: [Option<u16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    map_scene: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    surroundings: Option<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    translation: [Option<i16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    supports_items: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    morphism: Option<Morphism>,
 }
 
 impl Default for ObjectDefinition {
@@ -36,24 +78,40 @@ impl Default for ObjectDefinition {
             id: 0,
             name: String::default(),
             examine_text: String::default(),
+            models: Vec::new(),
+            model_types: Vec::new(),
+            model_opcodes: [false; 2],
             impenetrable: true,
-            interactive: false,
+            interactive: None,
             obstructive: false,
             solid: true,
             interact_actions: [None, None, None, None, None, None, None, None, None, None],
-            length: 1,
-            width: 1,
+            length: None,
+            width: None,
             rotated: false,
             casts_shadow: true,
             hug_terrain: false,
-            low_priority_shading: true,
+            low_priority_shading: false,
             wall: false,
+            hollow: false,
+            animation: None,
+            decor_displacement: None,
+            ambient: None,
+            contrast: None,
+            recolours: Vec::new(),
+            map_function: None,
+            scale: [None; 3],
+            map_scene: None,
+            surroundings: None,
+            translation: [None; 3],
+            supports_items: None,
+            morphism: None,
         }
     }
 }
 
 impl ObjectDefinition {
-    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<Vec<Self>> {
+    pub fn load(cache: &CacheFileSystem) -> crate::Result<Vec<Self>> {
         let archive = cache.get_archive(0, 2)?;
         let mut index = archive
             .get_entry("loc.idx")
@@ -82,6 +140,152 @@ impl ObjectDefinition {
         }
         Ok(definitions)
     }
+
+    /// Encodes the definitions into the contents of the `loc.dat` and `loc.idx` archive entries,
+    /// returned in that order.
+    pub fn encode(definitions: &[Self]) -> crate::Result<(Bytes, Bytes)> {
+        let encoded = definitions.iter().map(encode_definition).collect();
+        pack_definitions("Object", encoded)
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn examine_text(&self) -> &String {
+        &self.examine_text
+    }
+
+    pub fn models(&self) -> &[u16] {
+        &self.models
+    }
+
+    /// The placement type of each model, empty when the object only has models for one type.
+    pub fn model_types(&self) -> &[u8] {
+        &self.model_types
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width.unwrap_or(1)
+    }
+
+    pub fn length(&self) -> u8 {
+        self.length.unwrap_or(1)
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.solid
+    }
+
+    pub fn is_impenetrable(&self) -> bool {
+        self.impenetrable
+    }
+
+    /// Whether players can interact with the object. When the cache does not say, the client
+    /// treats objects with actions or centrepiece models as interactive.
+    pub fn is_interactive(&self) -> bool {
+        self.interactive.unwrap_or_else(|| {
+            let centrepiece = !self.models.is_empty()
+                && (self.model_types.is_empty() || self.model_types[0] == 10);
+            centrepiece || self.interact_actions.iter().any(Option::is_some)
+        })
+    }
+
+    pub fn is_obstructive(&self) -> bool {
+        self.obstructive
+    }
+
+    pub fn is_rotated(&self) -> bool {
+        self.rotated
+    }
+
+    pub fn casts_shadow(&self) -> bool {
+        self.casts_shadow
+    }
+
+    pub fn hugs_terrain(&self) -> bool {
+        self.hug_terrain
+    }
+
+    pub fn is_wall(&self) -> bool {
+        self.wall
+    }
+
+    pub fn is_hollow(&self) -> bool {
+        self.hollow
+    }
+
+    pub fn interact_action(&self, index: usize) -> Option<&String> {
+        self.interact_actions[index].as_ref()
+    }
+
+    pub fn animation(&self) -> Option<u16> {
+        self.animation.filter(|id| *id != u16::MAX)
+    }
+
+    pub fn decor_displacement(&self) -> u8 {
+        self.decor_displacement.unwrap_or(16)
+    }
+
+    pub fn ambient(&self) -> i8 {
+        self.ambient.unwrap_or(0)
+    }
+
+    pub fn contrast(&self) -> i8 {
+        self.contrast.unwrap_or(0)
+    }
+
+    pub fn recolours(&self) -> &[Recolour] {
+        &self.recolours
+    }
+
+    /// The minimap icon drawn for the object, such as a bank or altar.
+    pub fn map_function(&self) -> Option<u16> {
+        self.map_function
+    }
+
+    /// The sprite drawn in place of the object on the minimap, such as a tree or rock.
+    pub fn map_scene(&self) -> Option<u16> {
+        self.map_scene
+    }
+
+    /// The scale of the model along the x, y and z axes, where 128 is the original size.
+    pub fn scale(&self) -> [u16; 3] {
+        let mut scale = [128; 3];
+        for (scale, value) in scale.iter_mut().zip(self.scale.iter()) {
+            if let Some(value) = value {
+                *scale = *value;
+            }
+        }
+        scale
+    }
+
+    pub fn translation(&self) -> [i16; 3] {
+        let mut translation = [0; 3];
+        for (translation, value) in translation.iter_mut().zip(self.translation.iter()) {
+            if let Some(value) = value {
+                *translation = *value;
+            }
+        }
+        translation
+    }
+
+    /// The directions from which the object can be reached when it blocks movement.
+    pub fn surroundings(&self) -> u8 {
+        self.surroundings.unwrap_or(0)
+    }
+
+    pub fn supports_items(&self) -> bool {
+        self.supports_items == Some(1)
+    }
+
+    pub fn morphism(&self) -> Option<&Morphism> {
+        self.morphism.as_ref()
+    }
 }
 
 fn decode_definition<B: GameBuf>(object_id: u16, buf: &mut B) -> crate::Result<ObjectDefinition> {
@@ -91,72 +295,241 @@ fn decode_definition<B: GameBuf>(object_id: u16, buf: &mut B) -> crate::Result<O
         match buf.get_u8() {
             0 => return Ok(definition),
             1 => {
+                definition.model_opcodes[0] = true;
                 let len = buf.get_u8();
-                let _ = (0..len)
-                    .map(|_| (buf.get_u16(), buf.get_u8()))
-                    .collect::<Vec<_>>();
+                for _ in 0..len {
+                    definition.models.push(buf.get_u16());
+                    definition.model_types.push(buf.get_u8());
+                }
             }
             2 => definition.name = buf.get_rs_string(),
             3 => definition.examine_text = buf.get_rs_string(),
             5 => {
+                definition.model_opcodes[1] = true;
                 let len = buf.get_u8();
-                let _ = (0..len).map(|_| buf.get_u16()).collect::<Vec<_>>();
+                definition.models = (0..len).map(|_| buf.get_u16()).collect();
             }
-            14 => definition.width = buf.get_u8(),
-            15 => definition.length = buf.get_u8(),
+            14 => definition.width = Some(buf.get_u8()),
+            15 => definition.length = Some(buf.get_u8()),
             17 => definition.solid = false,
             18 => definition.impenetrable = false,
-            19 => definition.interactive = buf.get_u8() == 1,
+            19 => definition.interactive = Some(buf.get_u8() == 1),
             21 => definition.hug_terrain = true,
             22 => definition.low_priority_shading = true,
             23 => definition.wall = true,
-            24 => {
-                buf.get_u16();
-            }
-            28 | 29 => {
-                buf.get_u8();
-            }
+            24 => definition.animation = Some(buf.get_u16()),
+            28 => definition.decor_displacement = Some(buf.get_u8()),
+            29 => definition.ambient = Some(buf.get_i8()),
             opcode if (30..39).contains(&opcode) => {
                 definition.interact_actions[opcode as usize - 30] = Some(buf.get_rs_string());
             }
-            39 => {
-                buf.get_u8();
-            }
-            40 => {
-                let len = buf.get_u8();
-                let _ = (0..len)
-                    .map(|_| (buf.get_u16(), buf.get_u16()))
-                    .collect::<Vec<_>>();
-            }
-            60 | 65..=68 => {
-                buf.get_u16();
-            }
+            39 => definition.contrast = Some(buf.get_i8()),
+            40 => definition.recolours = Recolour::decode_all(buf),
+            60 => definition.map_function = Some(buf.get_u16()),
             62 => definition.rotated = true,
             64 => definition.casts_shadow = false,
-            69 => {
-                buf.get_u8();
-            }
-            70..=72 => {
-                buf.get_u16();
+            opcode @ 65..=67 => definition.scale[opcode as usize - 65] = Some(buf.get_u16()),
+            68 => definition.map_scene = Some(buf.get_u16()),
+            69 => definition.surroundings = Some(buf.get_u8()),
+            opcode @ 70..=72 => {
+                definition.translation[opcode as usize - 70] = Some(buf.get_i16());
             }
             73 => definition.obstructive = true,
-            75 => {
-                buf.get_u8();
-            }
-            77 => {
-                buf.get_u16();
-                buf.get_u16();
-                let len = buf.get_u8();
-                let _morphisms = (0..=len).map(|_| buf.get_u16()).collect::<Vec<u16>>();
-            }
-            _opcode => {
-                // TODO: Discuss implementation of all fields with team: Many are not used by the server.
-                // return Err(CacheError::DecodeDefinition {
-                //     ty: "Object",
-                //     opcode: _opcode,
-                // })
-                continue;
+            74 => definition.hollow = true,
+            75 => definition.supports_items = Some(buf.get_u8()),
+            77 => definition.morphism = Some(Morphism::decode(buf)),
+            opcode => {
+                return Err(CacheError::DecodeDefinition {
+                    ty: "Object",
+                    opcode,
+                })
             }
         }
     }
 }
+
+fn encode_definition(definition: &ObjectDefinition) -> Bytes {
+    let mut buf = BytesMut::new();
+
+    if definition.model_opcodes[0] || !definition.model_types.is_empty() {
+        buf.put_u8(1);
+        buf.put_u8(definition.models.len() as u8);
+        for (model, ty) in definition.models.iter().zip(definition.model_types.iter()) {
+            buf.put_u16(*model);
+            buf.put_u8(*ty);
+        }
+    }
+    if !definition.name.is_empty() {
+        buf.put_u8(2);
+        buf.put_rs_string(definition.name.clone());
+    }
+    if !definition.examine_text.is_empty() {
+        buf.put_u8(3);
+        buf.put_rs_string(definition.examine_text.clone());
+    }
+    if definition.model_opcodes[1]
+        || definition.model_types.is_empty() && !definition.models.is_empty()
+    {
+        buf.put_u8(5);
+        buf.put_u8(definition.models.len() as u8);
+        for model in definition.models.iter() {
+            buf.put_u16(*model);
+        }
+    }
+    if let Some(width) = definition.width {
+        buf.put_u8(14);
+        buf.put_u8(width);
+    }
+    if let Some(length) = definition.length {
+        buf.put_u8(15);
+        buf.put_u8(length);
+    }
+    if !definition.solid {
+        buf.put_u8(17);
+    }
+    if !definition.impenetrable {
+        buf.put_u8(18);
+    }
+    if let Some(interactive) = definition.interactive {
+        buf.put_u8(19);
+        buf.put_u8(interactive as u8);
+    }
+    if definition.hug_terrain {
+        buf.put_u8(21);
+    }
+    if definition.low_priority_shading {
+        buf.put_u8(22);
+    }
+    if definition.wall {
+        buf.put_u8(23);
+    }
+    if let Some(animation) = definition.animation {
+        buf.put_u8(24);
+        buf.put_u16(animation);
+    }
+    if let Some(displacement) = definition.decor_displacement {
+        buf.put_u8(28);
+        buf.put_u8(displacement);
+    }
+    if let Some(ambient) = definition.ambient {
+        buf.put_u8(29);
+        buf.put_i8(ambient);
+    }
+    for (opcode, action) in (30..39).zip(definition.interact_actions.iter()) {
+        if let Some(action) = action {
+            buf.put_u8(opcode);
+            buf.put_rs_string(action.clone());
+        }
+    }
+    if let Some(contrast) = definition.contrast {
+        buf.put_u8(39);
+        buf.put_i8(contrast);
+    }
+    if !definition.recolours.is_empty() {
+        buf.put_u8(40);
+        Recolour::encode_all(&definition.recolours, &mut buf);
+    }
+    if let Some(map_function) = definition.map_function {
+        buf.put_u8(60);
+        buf.put_u16(map_function);
+    }
+    if definition.rotated {
+        buf.put_u8(62);
+    }
+    if !definition.casts_shadow {
+        buf.put_u8(64);
+    }
+    for (opcode, scale) in (65..).zip(definition.scale.iter()) {
+        if let Some(scale) = scale {
+            buf.put_u8(opcode);
+            buf.put_u16(*scale);
+        }
+    }
+    if let Some(map_scene) = definition.map_scene {
+        buf.put_u8(68);
+        buf.put_u16(map_scene);
+    }
+    if let Some(surroundings) = definition.surroundings {
+        buf.put_u8(69);
+        buf.put_u8(surroundings);
+    }
+    for (opcode, translation) in (70..).zip(definition.translation.iter()) {
+        if let Some(translation) = translation {
+            buf.put_u8(opcode);
+            buf.put_i16(*translation);
+        }
+    }
+    if definition.obstructive {
+        buf.put_u8(73);
+    }
+    if definition.hollow {
+        buf.put_u8(74);
+    }
+    if let Some(supports_items) = definition.supports_items {
+        buf.put_u8(75);
+        buf.put_u8(supports_items);
+    }
+    if let Some(morphism) = &definition.morphism {
+        buf.put_u8(77);
+        morphism.encode(&mut buf);
+    }
+    buf.put_u8(0);
+    buf.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn encode_round_trip() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            1, 2, 0x00, 0x10, 10, 0x00, 0x11, 22,
+            2, b'D', b'o', b'o', b'r', 10,
+            14, 2,
+            17,
+            19, 1,
+            22,
+            24, 0xFF, 0xFF,
+            29, 0xF0,
+            30, b'O', b'p', b'e', b'n', 10,
+            40, 1, 0x12, 0x34, 0x56, 0x78,
+            60, 0x00, 0x05,
+            64,
+            70, 0xFF, 0x80,
+            74,
+            77, 0x00, 0x01, 0xFF, 0xFF, 1, 0x00, 0x02, 0x00, 0x03,
+            0,
+        ];
+
+        let definition = decode_definition(1, &mut Cursor::new(&data[..])).expect("decode");
+        assert_eq!(definition.models, vec![0x10, 0x11]);
+        assert_eq!(definition.translation[0], Some(-128));
+        assert_eq!(&encode_definition(&definition)[..], &data[..]);
+
+        let morphism = definition.morphism().expect("morphism");
+        assert_eq!(morphism.varbit_id(), Some(1));
+        assert_eq!(morphism.varp_id(), None);
+        assert_eq!(morphism.child_id(1), Some(3));
+        assert_eq!(morphism.child_id(2), None);
+        assert_eq!(definition.animation(), None);
+    }
+
+    #[test]
+    pub fn encode_empty_models() {
+        let data = [1, 0, 5, 0, 0];
+        let definition = decode_definition(1, &mut Cursor::new(&data[..])).expect("decode");
+        assert!(definition.models().is_empty());
+        assert_eq!(&encode_definition(&definition)[..], &data[..]);
+    }
+
+    #[test]
+    pub fn error_unknown_opcode() {
+        let data = [2, b'O', 10, 200, 0];
+        match decode_definition(1, &mut Cursor::new(&data[..])) {
+            Err(CacheError::DecodeDefinition { opcode: 200, .. }) => {}
+            _ => panic!("opcode 200 should be reported"),
+        }
+    }
+}
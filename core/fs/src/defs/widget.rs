@@ -0,0 +1,612 @@
+use crate::{ArchiveError, CacheError, CacheFileSystem};
+use bytes::Buf;
+use mithril_buf::GameBuf;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetType {
+    Container,
+    ModelList,
+    Inventory,
+    Rectangle,
+    Text,
+    Sprite,
+    Model,
+    ItemList,
+    Tooltip,
+}
+
+/// What happens when a player clicks on a widget.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetAction {
+    None,
+    Button,
+    Target,
+    Close,
+    Toggle,
+    Select,
+    Continue,
+}
+
+/// A widget placed within a container, relative to the container's top left corner.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetChild {
+    pub id: u16,
+    pub x: i16,
+    pub y: i16,
+}
+
+/// A sprite drawn behind one of the first 20 slots of an inventory, such as the equipment slot
+/// silhouettes.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotSprite {
+    pub slot: u8,
+    pub x: i16,
+    pub y: i16,
+    pub name: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InventoryLayout {
+    pub swappable: bool,
+    pub interactable: bool,
+    pub usable: bool,
+    pub replaces_items: bool,
+    pub padding_x: i16,
+    pub padding_y: i16,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub slot_sprites: Vec<SlotSprite>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WidgetDefinition {
+    id: u16,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    parent_id: Option<u16>,
+    widget_type: WidgetType,
+    action: WidgetAction,
+    content_type: u16,
+    width: u16,
+    height: u16,
+    alpha: u8,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    hover_id: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    conditions: Vec<(u8, u16)>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    scripts: Vec<Vec<u16>>,
+    scroll_height: u16,
+    hidden: bool,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    children: Vec<WidgetChild>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    inventory: Option<InventoryLayout>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    actions: [Option<String>; 5],
+    filled: bool,
+    centered: bool,
+    shadowed: bool,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    font: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    text: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    secondary_text: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    colours: [Option<u32>; 4],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    sprites: [Option<String>; 2],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    models: [Option<u16>; 2],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    animations: [Option<u16>; 2],
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "crate::skip_empty_options")
+    )]
+    model_view: [Option<u16>; 3],
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    selected_action: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    spell_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    spell_targets: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    tooltip: Option<String>,
+}
+
+impl WidgetDefinition {
+    /// Loads every widget from the `data` entry of the interface archive, keyed by widget ID.
+    pub fn load(cache: &mut CacheFileSystem) -> crate::Result<HashMap<u16, Self>> {
+        let archive = cache.get_archive(0, 3)?;
+        let mut data = archive
+            .get_entry("data")
+            .map(|entry| entry.contents())
+            .ok_or(ArchiveError::EntryNotFound("data"))?;
+
+        let count = data.get_u16() as usize;
+        let mut definitions = HashMap::with_capacity(count);
+        let mut parent_id = None;
+        while data.has_remaining() {
+            let mut id = data.get_u16();
+            if id == u16::MAX {
+                parent_id = Some(data.get_u16());
+                id = data.get_u16();
+            }
+            let definition = decode_definition(id, parent_id, &mut data)?;
+            definitions.insert(id, definition);
+        }
+        Ok(definitions)
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// The ID of the root widget of the interface this widget belongs to.
+    pub fn parent_id(&self) -> Option<u16> {
+        self.parent_id
+    }
+
+    pub fn widget_type(&self) -> WidgetType {
+        self.widget_type
+    }
+
+    pub fn action(&self) -> WidgetAction {
+        self.action
+    }
+
+    /// Whether clicking the widget sends a `Button` packet to the server. Close buttons send a
+    /// `ClosedInterface` packet instead.
+    pub fn is_button(&self) -> bool {
+        matches!(
+            self.action,
+            WidgetAction::Button | WidgetAction::Toggle | WidgetAction::Select
+        )
+    }
+
+    /// Identifies widgets whose contents are drawn by the client itself, such as the minimap.
+    pub fn content_type(&self) -> u16 {
+        self.content_type
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    /// The widget shown while the mouse is over this widget.
+    pub fn hover_id(&self) -> Option<u16> {
+        self.hover_id
+    }
+
+    /// Pairs of comparison type and value that decide, alongside the matching script, whether
+    /// the widget is in its active state.
+    pub fn conditions(&self) -> &[(u8, u16)] {
+        &self.conditions
+    }
+
+    /// The client scripts evaluated for each condition.
+    pub fn scripts(&self) -> &[Vec<u16>] {
+        &self.scripts
+    }
+
+    pub fn scroll_height(&self) -> u16 {
+        self.scroll_height
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn children(&self) -> &[WidgetChild] {
+        &self.children
+    }
+
+    pub fn inventory(&self) -> Option<&InventoryLayout> {
+        self.inventory.as_ref()
+    }
+
+    /// The number of item slots for inventory and item list widgets, which are laid out in a
+    /// grid of `width` by `height`.
+    pub fn slot_count(&self) -> usize {
+        match self.widget_type {
+            WidgetType::Inventory | WidgetType::ItemList => {
+                self.width as usize * self.height as usize
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn action_name(&self, index: usize) -> Option<&String> {
+        self.actions[index].as_ref()
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.filled
+    }
+
+    pub fn is_centered(&self) -> bool {
+        self.centered
+    }
+
+    pub fn is_shadowed(&self) -> bool {
+        self.shadowed
+    }
+
+    pub fn font(&self) -> Option<u8> {
+        self.font
+    }
+
+    pub fn text(&self) -> Option<&String> {
+        self.text.as_ref()
+    }
+
+    /// The text shown while the widget is in its active state.
+    pub fn secondary_text(&self) -> Option<&String> {
+        self.secondary_text.as_ref()
+    }
+
+    /// The colour, active colour, hover colour and active hover colour of the widget.
+    pub fn colours(&self) -> [Option<u32>; 4] {
+        self.colours
+    }
+
+    /// The names of the sprites drawn normally and in the active state, in the form
+    /// `group,index`.
+    pub fn sprites(&self) -> [Option<&String>; 2] {
+        [self.sprites[0].as_ref(), self.sprites[1].as_ref()]
+    }
+
+    pub fn models(&self) -> [Option<u16>; 2] {
+        self.models
+    }
+
+    pub fn animations(&self) -> [Option<u16>; 2] {
+        self.animations
+    }
+
+    /// The zoom and x and y rotation of the model.
+    pub fn model_view(&self) -> [Option<u16>; 3] {
+        self.model_view
+    }
+
+    pub fn selected_action(&self) -> Option<&String> {
+        self.selected_action.as_ref()
+    }
+
+    pub fn spell_name(&self) -> Option<&String> {
+        self.spell_name.as_ref()
+    }
+
+    /// A bitmask of what a targeting widget may be used on.
+    pub fn spell_targets(&self) -> Option<u16> {
+        self.spell_targets
+    }
+
+    /// The option shown when hovering over the widget, with the client's defaults applied.
+    pub fn tooltip(&self) -> Option<&str> {
+        match (self.tooltip.as_deref(), self.action) {
+            (Some(""), WidgetAction::Button) => Some("Ok"),
+            (Some(""), WidgetAction::Toggle) | (Some(""), WidgetAction::Select) => Some("Select"),
+            (Some(""), WidgetAction::Continue) => Some("Continue"),
+            (tooltip, _) => tooltip,
+        }
+    }
+}
+
+fn decode_definition<B: GameBuf>(
+    widget_id: u16,
+    parent_id: Option<u16>,
+    buf: &mut B,
+) -> crate::Result<WidgetDefinition> {
+    let ty = buf.get_u8();
+    let widget_type = match ty {
+        0 => WidgetType::Container,
+        1 => WidgetType::ModelList,
+        2 => WidgetType::Inventory,
+        3 => WidgetType::Rectangle,
+        4 => WidgetType::Text,
+        5 => WidgetType::Sprite,
+        6 => WidgetType::Model,
+        7 => WidgetType::ItemList,
+        8 => WidgetType::Tooltip,
+        opcode => {
+            return Err(CacheError::DecodeDefinition {
+                ty: "Widget",
+                opcode,
+            })
+        }
+    };
+    let action = match buf.get_u8() {
+        0 => WidgetAction::None,
+        1 => WidgetAction::Button,
+        2 => WidgetAction::Target,
+        3 => WidgetAction::Close,
+        4 => WidgetAction::Toggle,
+        5 => WidgetAction::Select,
+        6 => WidgetAction::Continue,
+        opcode => {
+            return Err(CacheError::DecodeDefinition {
+                ty: "Widget",
+                opcode,
+            })
+        }
+    };
+
+    let mut definition = WidgetDefinition {
+        id: widget_id,
+        parent_id,
+        widget_type,
+        action,
+        content_type: buf.get_u16(),
+        width: buf.get_u16(),
+        height: buf.get_u16(),
+        alpha: buf.get_u8(),
+        hover_id: get_smart_id(buf),
+        conditions: Vec::new(),
+        scripts: Vec::new(),
+        scroll_height: 0,
+        hidden: false,
+        children: Vec::new(),
+        inventory: None,
+        actions: [None, None, None, None, None],
+        filled: false,
+        centered: false,
+        shadowed: false,
+        font: None,
+        text: None,
+        secondary_text: None,
+        colours: [None; 4],
+        sprites: [None, None],
+        models: [None; 2],
+        animations: [None; 2],
+        model_view: [None; 3],
+        selected_action: None,
+        spell_name: None,
+        spell_targets: None,
+        tooltip: None,
+    };
+
+    let len = buf.get_u8();
+    definition.conditions = (0..len).map(|_| (buf.get_u8(), buf.get_u16())).collect();
+    let len = buf.get_u8();
+    definition.scripts = (0..len)
+        .map(|_| {
+            let len = buf.get_u16();
+            (0..len).map(|_| buf.get_u16()).collect()
+        })
+        .collect();
+
+    match widget_type {
+        WidgetType::Container => {
+            definition.scroll_height = buf.get_u16();
+            definition.hidden = buf.get_u8() == 1;
+            let len = buf.get_u16();
+            definition.children = (0..len)
+                .map(|_| WidgetChild {
+                    id: buf.get_u16(),
+                    x: buf.get_i16(),
+                    y: buf.get_i16(),
+                })
+                .collect();
+        }
+        WidgetType::ModelList => {
+            buf.get_u16();
+            buf.get_u8();
+        }
+        WidgetType::Inventory => {
+            let mut inventory = InventoryLayout {
+                swappable: buf.get_u8() == 1,
+                interactable: buf.get_u8() == 1,
+                usable: buf.get_u8() == 1,
+                replaces_items: buf.get_u8() == 1,
+                padding_x: i16::from(buf.get_u8()),
+                padding_y: i16::from(buf.get_u8()),
+                slot_sprites: Vec::new(),
+            };
+            for slot in 0..20 {
+                if buf.get_u8() == 1 {
+                    inventory.slot_sprites.push(SlotSprite {
+                        slot,
+                        x: buf.get_i16(),
+                        y: buf.get_i16(),
+                        name: buf.get_rs_string(),
+                    });
+                }
+            }
+            definition.inventory = Some(inventory);
+            definition.actions = get_actions(buf);
+        }
+        WidgetType::Rectangle => definition.filled = buf.get_u8() == 1,
+        _ => {}
+    }
+
+    if let WidgetType::ModelList | WidgetType::Text = widget_type {
+        definition.centered = buf.get_u8() == 1;
+        definition.font = Some(buf.get_u8());
+        definition.shadowed = buf.get_u8() == 1;
+    }
+    if widget_type == WidgetType::Text {
+        definition.text = Some(buf.get_rs_string());
+        definition.secondary_text = Some(buf.get_rs_string());
+    }
+    if let WidgetType::ModelList | WidgetType::Rectangle | WidgetType::Text = widget_type {
+        definition.colours[0] = Some(buf.get_u32());
+    }
+    if let WidgetType::Rectangle | WidgetType::Text = widget_type {
+        for colour in definition.colours[1..].iter_mut() {
+            *colour = Some(buf.get_u32());
+        }
+    }
+
+    match widget_type {
+        WidgetType::Sprite => {
+            for sprite in definition.sprites.iter_mut() {
+                *sprite = Some(buf.get_rs_string()).filter(|name| !name.is_empty());
+            }
+        }
+        WidgetType::Model => {
+            definition.models = [get_smart_id(buf), get_smart_id(buf)];
+            definition.animations = [get_smart_id(buf), get_smart_id(buf)];
+            for value in definition.model_view.iter_mut() {
+                *value = Some(buf.get_u16());
+            }
+        }
+        WidgetType::ItemList => {
+            definition.centered = buf.get_u8() == 1;
+            definition.font = Some(buf.get_u8());
+            definition.shadowed = buf.get_u8() == 1;
+            definition.colours[0] = Some(buf.get_u32());
+            definition.inventory = Some(InventoryLayout {
+                padding_x: buf.get_i16(),
+                padding_y: buf.get_i16(),
+                interactable: buf.get_u8() == 1,
+                ..InventoryLayout::default()
+            });
+            definition.actions = get_actions(buf);
+        }
+        _ => {}
+    }
+
+    if action == WidgetAction::Target || widget_type == WidgetType::Inventory {
+        definition.selected_action = Some(buf.get_rs_string());
+        definition.spell_name = Some(buf.get_rs_string());
+        definition.spell_targets = Some(buf.get_u16());
+    }
+    if widget_type == WidgetType::Tooltip {
+        definition.text = Some(buf.get_rs_string());
+    }
+    if let WidgetAction::Button
+    | WidgetAction::Toggle
+    | WidgetAction::Select
+    | WidgetAction::Continue = action
+    {
+        definition.tooltip = Some(buf.get_rs_string());
+    }
+    Ok(definition)
+}
+
+/// Reads an optional ID stored as one byte when absent and two bytes offset by 256 otherwise.
+fn get_smart_id<B: GameBuf>(buf: &mut B) -> Option<u16> {
+    match buf.get_u8() {
+        0 => None,
+        high => Some(((u16::from(high) - 1) << 8) + u16::from(buf.get_u8())),
+    }
+}
+
+fn get_actions<B: GameBuf>(buf: &mut B) -> [Option<String>; 5] {
+    let mut actions = [None, None, None, None, None];
+    for action in actions.iter_mut() {
+        *action = Some(buf.get_rs_string()).filter(|action| !action.is_empty());
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    pub fn decode_container() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0, 0,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0xC8, 0,
+            0,
+            1, 1, 0x00, 0x05,
+            1, 0x00, 0x02, 0x00, 0x2C, 0x00, 0x3A,
+            0x00, 0x00, 0,
+            0x00, 0x02, 0x0B, 0xB8, 0x00, 0x10, 0xFF, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let definition = decode_definition(2423, None, &mut Cursor::new(&data[..])).unwrap();
+        assert_eq!(definition.widget_type(), WidgetType::Container);
+        assert_eq!(definition.width(), 256);
+        assert_eq!(definition.conditions(), &[(1, 5)]);
+        assert_eq!(definition.scripts(), &[vec![0x2C, 0x3A]]);
+        assert_eq!(
+            definition.children(),
+            &[
+                WidgetChild {
+                    id: 3000,
+                    x: 16,
+                    y: -16
+                },
+                WidgetChild { id: 0, x: 0, y: 0 }
+            ]
+        );
+    }
+
+    #[test]
+    pub fn decode_button() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            5, 1,
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x10, 0,
+            0x02, 0x05,
+            0,
+            0,
+            b'a', b',', b'1', 10,
+            10,
+            10,
+        ];
+
+        let definition = decode_definition(1, Some(0), &mut Cursor::new(&data[..])).unwrap();
+        assert_eq!(definition.widget_type(), WidgetType::Sprite);
+        assert!(definition.is_button());
+        assert_eq!(definition.hover_id(), Some(0x105));
+        assert_eq!(definition.sprites(), [Some(&"a,1".to_owned()), None]);
+        assert_eq!(definition.tooltip(), Some("Ok"));
+    }
+
+    #[test]
+    pub fn close_is_not_button() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            5, 3,
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x10, 0,
+            0,
+            0,
+            0,
+            10,
+            10,
+        ];
+
+        let definition = decode_definition(1, Some(0), &mut Cursor::new(&data[..])).unwrap();
+        assert_eq!(definition.action(), WidgetAction::Close);
+        assert!(!definition.is_button());
+    }
+}
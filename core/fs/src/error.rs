@@ -9,12 +9,30 @@ pub enum CacheError {
     IndexNotFound(usize),
     #[error("file {1} was not found in index {0}")]
     FileNotFound(usize, usize),
+    #[error("file {1} cannot be stored in index {0}")]
+    FileOutOfRange(usize, usize),
+    #[error("file is {0} bytes which exceeds the maximum file size")]
+    FileTooLarge(usize),
+    #[error("cache was not opened with write access")]
+    ReadOnly,
     #[error(transparent)]
     FilePart(#[from] FilePartError),
     #[error(transparent)]
     Archive(#[from] ArchiveError),
     #[error("unexpected opcode when decoding a {ty} definition: {:02X}", opcode)]
     DecodeDefinition { ty: &'static str, opcode: u8 },
+    #[error("{ty} definition {id} is too large to encode")]
+    EncodeDefinition { ty: &'static str, id: usize },
+    #[error(transparent)]
+    Sprite(#[from] SpriteError),
+    #[error(transparent)]
+    Model(#[from] ModelError),
+    #[error(transparent)]
+    Frame(#[from] FrameError),
+    #[error(transparent)]
+    Sound(#[from] SoundError),
+    #[error(transparent)]
+    Map(#[from] MapError),
     #[error(transparent)]
     Io(#[from] io::Error),
 }
@@ -33,4 +51,54 @@ pub enum ArchiveError {
     LengthMismatch { expected: usize, actual: usize },
     #[error("archive did not contain an entry named '{0}'")]
     EntryNotFound(&'static str),
+    #[error("{0} bytes is too large to be stored in an archive")]
+    TooLarge(usize),
+}
+
+#[derive(Error, Debug)]
+pub enum SpriteError {
+    #[error("sprite data ended unexpectedly")]
+    Truncated,
+    #[error("unknown pixel layout {0}")]
+    UnknownLayout(u8),
+    #[error("pixel refers to palette entry {0} which does not exist")]
+    PaletteIndex(u8),
+    #[error("sprite {0} does not exist in the group")]
+    NotFound(usize),
+}
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("model data ended unexpectedly")]
+    Truncated,
+    #[error("unknown face type {0}")]
+    FaceType(u8),
+    #[error("face refers to vertex {0} which does not exist")]
+    VertexIndex(i32),
+}
+
+#[derive(Error, Debug)]
+pub enum FrameError {
+    #[error("frame data ended unexpectedly")]
+    Truncated,
+    #[error("frame {frame_id} has {count} transforms which is more than its frame base")]
+    TransformCount { frame_id: u16, count: usize },
+}
+
+#[derive(Error, Debug)]
+pub enum SoundError {
+    #[error("sound data ended unexpectedly")]
+    Truncated,
+    #[error("filter has {0:02X} pole and zero pairs but at most 4 of each are supported")]
+    FilterPairs(u8),
+    #[error("song {0} does not exist")]
+    SongNotFound(u16),
+    #[error("song {0} is not a MIDI file")]
+    NotMidi(u16),
+}
+
+#[derive(Error, Debug)]
+pub enum MapError {
+    #[error("map data ended unexpectedly")]
+    Truncated,
 }
@@ -1,6 +1,6 @@
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::{prelude::*, Cursor, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use bytes::{Buf, Bytes, BytesMut};
 use crc32fast::Hasher;
@@ -8,17 +8,31 @@ use memmap::Mmap;
 
 mod archive;
 mod error;
+mod model;
+mod sound;
+mod sprite;
+mod texture;
+mod write;
 
 pub(crate) mod compression;
 pub mod defs;
 
-pub use archive::Archive;
-pub use error::{ArchiveError, CacheError, FilePartError};
+pub use archive::{Archive, ArchiveBuilder, ArchiveCompression};
+pub use error::{
+    ArchiveError, CacheError, FilePartError, FrameError, MapError, ModelError, SoundError,
+    SpriteError,
+};
+pub use model::{Face, Model};
+pub use sound::{Instrument, Song, SoundEffect, SAMPLE_RATE};
+pub use sprite::Sprite;
+pub use texture::Texture;
 
 const INDEX_SIZE: u64 = 6;
 const CHUNK_SIZE: u64 = 512;
 const HEADER_SIZE: u64 = 8;
 const BLOCK_SIZE: u64 = HEADER_SIZE + CHUNK_SIZE;
+const INDEX_COUNT: usize = 5;
+const MAX_FILE_SIZE: usize = 0xFF_FFFF;
 
 pub type Result<T> = std::result::Result<T, CacheError>;
 
@@ -26,6 +40,7 @@ pub type Result<T> = std::result::Result<T, CacheError>;
 pub struct CacheFileSystem {
     data_file: Mmap,
     indices: Vec<CacheIndex>,
+    writer: Option<write::CacheWriter>,
 }
 
 #[derive(Debug)]
@@ -46,30 +61,43 @@ impl CacheIndex {
 
 impl CacheFileSystem {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-        let mut indices = Vec::with_capacity(5);
+        Self::open_with(path.as_ref(), false)
+    }
+
+    /// Opens the cache with write access, enabling [`put_file`](#method.put_file) and
+    /// [`remove_file`](#method.remove_file).
+    pub fn open_writable<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path.as_ref(), true)
+    }
+
+    fn open_with(path: &Path, writable: bool) -> Result<Self> {
+        let mut indices = Vec::with_capacity(INDEX_COUNT);
+        let mut index_files = Vec::with_capacity(INDEX_COUNT);
 
-        for i in 0..5 {
+        for i in 0..INDEX_COUNT {
             let path = path.join(format!("main_file_cache.idx{}", i));
-            match File::open(&path).and_then(|file| unsafe { memmap::Mmap::map(&file) }) {
-                Ok(file) => indices.push(CacheIndex {
-                    len: file.len() / INDEX_SIZE as usize,
-                    index_file: file,
-                }),
-                Err(source) => return Err(CacheError::FileMapping { path, source }),
-            };
+            let (file, mapped) = open_mapped(path, writable)?;
+            indices.push(CacheIndex {
+                len: mapped.len() / INDEX_SIZE as usize,
+                index_file: mapped,
+            });
+            index_files.push(file);
         }
 
         let path = path.join("main_file_cache.dat");
-        let cur = match File::open(&path).and_then(|file| unsafe { memmap::Mmap::map(&file) }) {
-            Ok(file) => file,
-            Err(source) => return Err(CacheError::FileMapping { path, source }),
+        let (file, cur) = open_mapped(path, writable)?;
+
+        let writer = if writable {
+            Some(write::CacheWriter::new(file, index_files))
+        } else {
+            None
         };
 
         // TODO: Calculate CRC32 table prior to initialisation
         Ok(Self {
             data_file: cur,
             indices,
+            writer,
         })
     }
 
@@ -107,7 +135,7 @@ impl CacheFileSystem {
             .get(index_number)
             .ok_or(CacheError::IndexNotFound(index_number))?;
 
-        if file_number > index.len {
+        if file_number >= index.len {
             return Err(CacheError::FileNotFound(index_number, file_number));
         }
 
@@ -120,11 +148,7 @@ impl CacheFileSystem {
             size
         );
 
-        let num_parts = if size % CHUNK_SIZE == 0 {
-            size / CHUNK_SIZE
-        } else {
-            size / CHUNK_SIZE + 1
-        } as u16;
+        let num_parts = part_count(size) as u16;
 
         let mut position = initial_block * BLOCK_SIZE;
         let mut combined_buf = BytesMut::with_capacity(size as usize);
@@ -176,6 +200,33 @@ impl CacheFileSystem {
     }
 }
 
+fn part_count(size: u64) -> usize {
+    if size.is_multiple_of(CHUNK_SIZE) {
+        (size / CHUNK_SIZE) as usize
+    } else {
+        (size / CHUNK_SIZE + 1) as usize
+    }
+}
+
+fn open_mapped(path: PathBuf, writable: bool) -> Result<(File, Mmap)> {
+    let mapped = OpenOptions::new()
+        .read(true)
+        .write(writable)
+        .open(&path)
+        .and_then(|file| unsafe { Mmap::map(&file) }.map(|mapped| (file, mapped)));
+    mapped.map_err(|source| CacheError::FileMapping { path, source })
+}
+
+/// Reads a smart that is offset so it can store values in `-64..64` as a single byte, or values
+/// in `-16384..16384` as two bytes. Returns `None` if the buffer is too short.
+pub(crate) fn get_signed_smart(buf: &mut Bytes) -> Option<i32> {
+    match buf.bytes().first() {
+        Some(peek) if *peek < 128 => Some(i32::from(buf.get_u8()) - 64),
+        Some(_) if buf.remaining() >= 2 => Some(i32::from(buf.get_u16()) - 49152),
+        _ => None,
+    }
+}
+
 #[cfg(feature = "serde")]
 pub(crate) fn skip_empty_options<T>(options: &[Option<T>]) -> bool {
     options.iter().all(Option::is_none)
@@ -212,12 +263,12 @@ mod tests {
     pub fn load_map_definitions() {
         skip_ci!();
 
-        let mut cache = open_filesystem();
-        let map_indices = defs::MapIndex::load(&mut cache).expect("map_indices");
+        let cache = open_filesystem();
+        let map_indices = defs::MapIndex::load(&cache).expect("map_indices");
 
         let map_data = map_indices.values().take(10).map(|index| {
-            let map_file = defs::MapFile::load(&mut cache, index).expect("map_file");
-            let map_objects = defs::MapObject::load(&mut cache, index).expect("map_objects");
+            let map_file = defs::MapFile::load(&cache, index).expect("map_file");
+            let map_objects = defs::MapObject::load(&cache, index).expect("map_objects");
             (map_file, map_objects)
         });
         dbg!(map_data.len());
@@ -232,11 +283,70 @@ mod tests {
     }
 
     #[test]
-    pub fn load_object_definitions() {
+    pub fn load_floor_definitions() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let _ = defs::FloorDefinition::load(&mut cache).expect("floors");
+    }
+
+    #[test]
+    pub fn load_config_definitions() {
         skip_ci!();
 
         let mut cache = open_filesystem();
-        let _ = defs::ObjectDefinition::load(&mut cache).expect("objects");
+        let varps = defs::VarpDefinition::load(&mut cache).expect("varps");
+        let varbits = defs::VarbitDefinition::load(&mut cache).expect("varbits");
+        for varbit in varbits.iter() {
+            assert!(
+                (varbit.config_id() as usize) < varps.len(),
+                "varbit {} refers to a missing config",
+                varbit.id()
+            );
+        }
+    }
+
+    #[test]
+    pub fn load_animation_definitions() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let sequences = defs::SequenceDefinition::load(&mut cache).expect("sequences");
+        let spot_animations =
+            defs::SpotAnimationDefinition::load(&mut cache).expect("spot animations");
+        for spot_animation in spot_animations.iter() {
+            if let Some(sequence_id) = spot_animation.sequence_id() {
+                assert!(
+                    (sequence_id as usize) < sequences.len(),
+                    "spot animation {} refers to a missing sequence",
+                    spot_animation.id()
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn load_frames() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let sequences = defs::SequenceDefinition::load(&mut cache).expect("sequences");
+        let groups = defs::FrameGroup::load_all(&cache).expect("frame groups");
+        let dangling = defs::FrameGroup::validate(&groups, &sequences);
+        assert!(dangling.is_empty(), "dangling frames: {:?}", dangling);
+
+        let durations = defs::FrameGroup::durations(&groups);
+        for sequence in sequences.iter() {
+            assert!(sequence.resolved_duration_cycles(&durations) >= sequence.duration_cycles());
+        }
+    }
+
+    #[test]
+    pub fn load_object_definitions() {
+        skip_ci!();
+
+        let cache = open_filesystem();
+        let _ = defs::ObjectDefinition::load(&cache).expect("objects");
     }
 
     #[test]
@@ -267,6 +377,106 @@ mod tests {
             });
     }
 
+    #[test]
+    pub fn encode_config_definitions() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let archive = cache.get_archive(0, 2).expect("config archive");
+        let entry = |name| archive.get_entry(name).expect(name).contents();
+
+        let items = defs::ItemDefinition::load(&mut cache).expect("items");
+        let (data, index) = defs::ItemDefinition::encode(&items).expect("encode items");
+        assert_eq!(data, entry("obj.dat"));
+        assert_eq!(index, entry("obj.idx"));
+
+        let objects = defs::ObjectDefinition::load(&cache).expect("objects");
+        let (data, index) = defs::ObjectDefinition::encode(&objects).expect("encode objects");
+        assert_eq!(data, entry("loc.dat"));
+        assert_eq!(index, entry("loc.idx"));
+
+        let entities = defs::EntityDefinition::load(&mut cache).expect("entities");
+        let (data, index) = defs::EntityDefinition::encode(&entities).expect("encode entities");
+        assert_eq!(data, entry("npc.dat"));
+        assert_eq!(index, entry("npc.idx"));
+    }
+
+    #[test]
+    pub fn load_widget_definitions() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let widgets = defs::WidgetDefinition::load(&mut cache).expect("widgets");
+        for widget in widgets.values() {
+            for child in widget.children() {
+                assert!(
+                    widgets.contains_key(&child.id),
+                    "widget {} has a missing child {}",
+                    widget.id(),
+                    child.id
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn load_sprites() {
+        skip_ci!();
+
+        let cache = open_filesystem();
+        let sprite = Sprite::load(&cache, "invback", 0).expect("invback");
+        assert_eq!(sprite.to_rgba().len(), sprite.pixels().len() * 4);
+    }
+
+    #[test]
+    pub fn load_textures() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let textures = Texture::load_all(&cache).expect("textures");
+        for texture in textures.values() {
+            let size = texture.width() as usize * texture.height() as usize;
+            assert_eq!(texture.to_rgba().len(), size * 4);
+        }
+
+        let floors = defs::FloorDefinition::load(&mut cache).expect("floors");
+        for floor in floors.iter().filter(|floor| floor.texture_id().is_some()) {
+            assert!(
+                floor.texture_rgb(&textures).is_some(),
+                "floor {} refers to a missing texture",
+                floor.id()
+            );
+        }
+    }
+
+    #[test]
+    pub fn load_models() {
+        skip_ci!();
+
+        let mut cache = open_filesystem();
+        let items = defs::ItemDefinition::load(&mut cache).expect("items");
+        for model_id in items.iter().filter_map(|item| item.model_id()).take(100) {
+            let model = Model::load(&cache, model_id as usize).expect("model");
+            model.to_obj("model.mtl");
+        }
+    }
+
+    #[test]
+    pub fn load_sounds() {
+        skip_ci!();
+
+        let cache = open_filesystem();
+        let sounds = SoundEffect::load(&cache).expect("sounds");
+        for sound in sounds.values().take(20) {
+            assert_eq!(sound.to_wav(1).len(), sound.render(1).len() + 44);
+        }
+
+        let song_ids = Song::ids(&cache).expect("song ids");
+        for id in song_ids.into_iter().take(20) {
+            Song::load(&cache, id).expect("song");
+        }
+    }
+
     #[test]
     pub fn error_file_mapping() {
         match CacheFileSystem::open("invalid").err() {
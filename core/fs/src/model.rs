@@ -0,0 +1,503 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use bytes::{Buf, Bytes};
+
+use crate::defs::Hsl;
+use crate::{CacheFileSystem, ModelError, Texture};
+
+const MODEL_INDEX: usize = 1;
+const FOOTER_LEN: usize = 18;
+
+/// A triangle of a [`Model`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    /// Indices into the vertices of the model.
+    pub vertices: [u16; 3],
+    /// A packed HSL colour, or the texture ID when the face is textured.
+    pub colour: u16,
+    /// Shading and texturing flags; bit 0 selects flat shading, bit 1 texturing and the remaining
+    /// bits select the texture face.
+    pub render_type: u8,
+    /// Draw priority within the model, used to resolve overlapping faces.
+    pub priority: u8,
+    /// Transparency from 0 (opaque) to 255.
+    pub alpha: u8,
+    /// The label used by animation frames to transform this face.
+    pub label: Option<u8>,
+}
+
+impl Face {
+    pub fn is_flat(&self) -> bool {
+        self.render_type & 1 != 0
+    }
+
+    pub fn is_textured(&self) -> bool {
+        self.render_type & 2 != 0
+    }
+
+    /// The colour of the face, using the average colour of its texture when it is textured.
+    /// Returns `None` if the texture does not exist.
+    pub fn rgb(&self, textures: &HashMap<u16, Texture>) -> Option<u32> {
+        if self.is_textured() {
+            textures.get(&self.colour).map(Texture::average_rgb)
+        } else {
+            Some(Hsl::packed_to_rgb(self.colour))
+        }
+    }
+
+    /// The index of the texture face that maps this face's texture coordinates.
+    pub fn texture_face(&self) -> Option<usize> {
+        if self.is_textured() {
+            Some((self.render_type >> 2) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// A 3D model from index 1 of the cache, used by items, objects, NPCs and players.
+///
+/// Coordinates are in the client's model space, where the Y axis points down.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Model {
+    vertices: Vec<[i32; 3]>,
+    vertex_labels: Vec<u8>,
+    faces: Vec<Face>,
+    texture_faces: Vec<[u16; 3]>,
+}
+
+impl Model {
+    pub fn load(cache: &CacheFileSystem, id: usize) -> crate::Result<Self> {
+        let file = cache.get_file(MODEL_INDEX, id)?;
+        Self::decode(crate::compression::decompress_gzip(file)?)
+    }
+
+    /// Decodes a model from its decompressed contents.
+    ///
+    /// Every attribute is stored in its own section of the file, the sizes of which are described
+    /// by a footer in the final 18 bytes.
+    pub fn decode(data: Bytes) -> crate::Result<Self> {
+        if data.len() < FOOTER_LEN {
+            return Err(ModelError::Truncated.into());
+        }
+        let mut footer = data.slice(data.len() - FOOTER_LEN..);
+        let vertex_count = footer.get_u16() as usize;
+        let face_count = footer.get_u16() as usize;
+        let texture_face_count = footer.get_u8() as usize;
+        let has_render_types = footer.get_u8() == 1;
+        let priority = footer.get_u8();
+        let has_alpha = footer.get_u8() == 1;
+        let has_face_labels = footer.get_u8() == 1;
+        let has_vertex_labels = footer.get_u8() == 1;
+        let x_len = footer.get_u16() as usize;
+        let y_len = footer.get_u16() as usize;
+        let z_len = footer.get_u16() as usize;
+        let face_index_len = footer.get_u16() as usize;
+
+        let mut sections = Sections {
+            data: data.slice(..data.len() - FOOTER_LEN),
+            offset: 0,
+        };
+        let mut vertex_flags = sections.next(vertex_count)?;
+        let mut face_types = sections.next(face_count)?;
+        let mut priorities = sections.next_if(priority == 255, face_count)?;
+        let mut face_labels = sections.next_if(has_face_labels, face_count)?;
+        let mut render_types = sections.next_if(has_render_types, face_count)?;
+        let vertex_labels = sections.next_if(has_vertex_labels, vertex_count)?;
+        let mut alphas = sections.next_if(has_alpha, face_count)?;
+        let mut face_indices = sections.next(face_index_len)?;
+        let mut colours = sections.next(face_count * 2)?;
+        let mut texture_faces = sections.next(texture_face_count * 6)?;
+        let mut xs = sections.next(x_len)?;
+        let mut ys = sections.next(y_len)?;
+        let mut zs = sections.next(z_len)?;
+
+        let mut vertices = Vec::with_capacity(vertex_count);
+        let mut position = [0i32; 3];
+        for _ in 0..vertex_count {
+            let flags = vertex_flags.get_u8();
+            for (axis, deltas) in [&mut xs, &mut ys, &mut zs].iter_mut().enumerate() {
+                if flags & (1 << axis) != 0 {
+                    position[axis] +=
+                        crate::get_signed_smart(deltas).ok_or(ModelError::Truncated)?;
+                }
+            }
+            vertices.push(position);
+        }
+
+        let mut faces = Vec::with_capacity(face_count);
+        let (mut a, mut b, mut c, mut last) = (0i32, 0i32, 0i32, 0i32);
+        for _ in 0..face_count {
+            match face_types.get_u8() {
+                1 => {
+                    a = get_index(&mut face_indices)? + last;
+                    b = get_index(&mut face_indices)? + a;
+                    c = get_index(&mut face_indices)? + b;
+                    last = c;
+                }
+                2 => {
+                    b = c;
+                    c = get_index(&mut face_indices)? + last;
+                    last = c;
+                }
+                3 => {
+                    a = c;
+                    c = get_index(&mut face_indices)? + last;
+                    last = c;
+                }
+                4 => {
+                    std::mem::swap(&mut a, &mut b);
+                    c = get_index(&mut face_indices)? + last;
+                    last = c;
+                }
+                ty => return Err(ModelError::FaceType(ty).into()),
+            }
+
+            let mut face_vertices = [0; 3];
+            for (index, vertex) in face_vertices.iter_mut().zip([a, b, c].iter()) {
+                if *vertex < 0 || *vertex as usize >= vertex_count {
+                    return Err(ModelError::VertexIndex(*vertex).into());
+                }
+                *index = *vertex as u16;
+            }
+
+            faces.push(Face {
+                vertices: face_vertices,
+                colour: colours.get_u16(),
+                render_type: render_types.as_mut().map_or(0, Buf::get_u8),
+                priority: priorities.as_mut().map_or(priority, Buf::get_u8),
+                alpha: alphas.as_mut().map_or(0, Buf::get_u8),
+                label: face_labels.as_mut().map(Buf::get_u8),
+            });
+        }
+
+        let texture_faces: Vec<_> = (0..texture_face_count)
+            .map(|_| {
+                [
+                    texture_faces.get_u16(),
+                    texture_faces.get_u16(),
+                    texture_faces.get_u16(),
+                ]
+            })
+            .collect();
+        for texture_face in texture_faces.iter() {
+            if let Some(vertex) = texture_face.iter().find(|v| **v as usize >= vertex_count) {
+                return Err(ModelError::VertexIndex(i32::from(*vertex)).into());
+            }
+        }
+
+        Ok(Model {
+            vertices,
+            vertex_labels: vertex_labels
+                .map(|labels| labels.to_vec())
+                .unwrap_or_default(),
+            faces,
+            texture_faces,
+        })
+    }
+
+    pub fn vertices(&self) -> &[[i32; 3]] {
+        &self.vertices
+    }
+
+    /// The label of each vertex used by animation frames, if the model has any.
+    pub fn vertex_labels(&self) -> Option<&[u8]> {
+        if self.vertex_labels.is_empty() {
+            None
+        } else {
+            Some(&self.vertex_labels)
+        }
+    }
+
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// The vertex triples that define the texture space of textured faces.
+    pub fn texture_faces(&self) -> &[[u16; 3]] {
+        &self.texture_faces
+    }
+
+    /// The texture coordinates of each vertex of a textured face.
+    ///
+    /// Textures are projected onto the plane of the face's texture triangle `(p, m, n)`, where
+    /// `p` maps to `(0, 0)`, `m` to `(1, 0)` and `n` to `(0, 1)`.
+    pub fn texture_coordinates(&self, face: &Face) -> Option<[[f32; 2]; 3]> {
+        let [p, m, n] = *self.texture_faces.get(face.texture_face()?)?;
+        let point = |index: u16| {
+            let [x, y, z] = self.vertices[index as usize];
+            [x as f32, y as f32, z as f32]
+        };
+        let origin = point(p);
+        let sub = |a: [f32; 3], b: [f32; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+        let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+        let u_axis = sub(point(m), origin);
+        let v_axis = sub(point(n), origin);
+        let (uu, uv, vv) = (
+            dot(u_axis, u_axis),
+            dot(u_axis, v_axis),
+            dot(v_axis, v_axis),
+        );
+        let denominator = uu * vv - uv * uv;
+        if denominator == 0.0 {
+            return None;
+        }
+
+        let mut coordinates = [[0.0; 2]; 3];
+        for (coordinate, vertex) in coordinates.iter_mut().zip(face.vertices.iter()) {
+            let offset = sub(point(*vertex), origin);
+            let (du, dv) = (dot(offset, u_axis), dot(offset, v_axis));
+            *coordinate = [
+                (vv * du - uv * dv) / denominator,
+                (uu * dv - uv * du) / denominator,
+            ];
+        }
+        Some(coordinates)
+    }
+
+    /// Exports the model as a Wavefront OBJ, returning the OBJ and the MTL file it refers to.
+    ///
+    /// Untextured faces are given a material per colour, while textured faces are given a material
+    /// per texture ID which refers to `textures/<id>.png`. The Y axis is flipped so that models
+    /// are upright.
+    pub fn to_obj(&self, mtl_name: &str) -> (String, String) {
+        let mut obj = String::new();
+        let mut mtl = String::new();
+
+        writeln!(obj, "mtllib {}", mtl_name).unwrap();
+        for [x, y, z] in self.vertices.iter() {
+            writeln!(obj, "v {} {} {}", x, -y, z).unwrap();
+        }
+
+        let mut materials = BTreeSet::new();
+        let mut texture_coordinates = 0;
+        for face in self.faces.iter() {
+            let material = Material::of(face);
+            if materials.insert(material) {
+                material.write(&mut mtl);
+            }
+            writeln!(obj, "usemtl {}", material.name()).unwrap();
+
+            let [a, b, c] = face.vertices;
+            match self.texture_coordinates(face) {
+                Some(coordinates) => {
+                    for [u, v] in coordinates.iter() {
+                        writeln!(obj, "vt {} {}", u, 1.0 - v).unwrap();
+                    }
+                    texture_coordinates += 3;
+                    writeln!(
+                        obj,
+                        "f {}/{} {}/{} {}/{}",
+                        a + 1,
+                        texture_coordinates - 2,
+                        b + 1,
+                        texture_coordinates - 1,
+                        c + 1,
+                        texture_coordinates
+                    )
+                    .unwrap();
+                }
+                None => writeln!(obj, "f {} {} {}", a + 1, b + 1, c + 1).unwrap(),
+            }
+        }
+        (obj, mtl)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Material {
+    Colour { colour: u16, alpha: u8 },
+    Texture { id: u16, alpha: u8 },
+}
+
+impl Material {
+    fn of(face: &Face) -> Self {
+        if face.is_textured() {
+            Material::Texture {
+                id: face.colour,
+                alpha: face.alpha,
+            }
+        } else {
+            Material::Colour {
+                colour: face.colour,
+                alpha: face.alpha,
+            }
+        }
+    }
+
+    fn name(self) -> String {
+        match self {
+            Material::Colour { colour, alpha } => format!("colour_{}_{}", colour, alpha),
+            Material::Texture { id, alpha } => format!("texture_{}_{}", id, alpha),
+        }
+    }
+
+    fn write(self, mtl: &mut String) {
+        writeln!(mtl, "newmtl {}", self.name()).unwrap();
+        let alpha = match self {
+            Material::Colour { colour, alpha } => {
+                let rgb = Hsl::packed_to_rgb(colour);
+                let component = |shift: u32| f32::from(((rgb >> shift) & 0xFF) as u8) / 255.0;
+                writeln!(
+                    mtl,
+                    "Kd {} {} {}",
+                    component(16),
+                    component(8),
+                    component(0)
+                )
+                .unwrap();
+                alpha
+            }
+            Material::Texture { id, alpha } => {
+                writeln!(mtl, "Kd 1 1 1").unwrap();
+                writeln!(mtl, "map_Kd textures/{}.png", id).unwrap();
+                alpha
+            }
+        };
+        writeln!(mtl, "d {}", 1.0 - f32::from(alpha) / 255.0).unwrap();
+    }
+}
+
+struct Sections {
+    data: Bytes,
+    offset: usize,
+}
+
+impl Sections {
+    fn next(&mut self, len: usize) -> crate::Result<Bytes> {
+        let end = self.offset + len;
+        if end > self.data.len() {
+            return Err(ModelError::Truncated.into());
+        }
+        let section = self.data.slice(self.offset..end);
+        self.offset = end;
+        Ok(section)
+    }
+
+    fn next_if(&mut self, present: bool, len: usize) -> crate::Result<Option<Bytes>> {
+        if present {
+            self.next(len).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn get_index(buf: &mut Bytes) -> crate::Result<i32> {
+    crate::get_signed_smart(buf).ok_or_else(|| ModelError::Truncated.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CacheError;
+
+    fn footer(vertices: u16, faces: u16, textured: u8, flags: [u8; 5], lens: [u16; 4]) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(FOOTER_LEN);
+        footer.extend_from_slice(&vertices.to_be_bytes());
+        footer.extend_from_slice(&faces.to_be_bytes());
+        footer.push(textured);
+        footer.extend_from_slice(&flags);
+        for len in lens.iter() {
+            footer.extend_from_slice(&len.to_be_bytes());
+        }
+        footer
+    }
+
+    #[test]
+    pub fn decode_triangle() {
+        #[rustfmt::skip]
+        let mut data = vec![
+            // vertex flags
+            7, 1, 2,
+            // face types
+            1,
+            // face indices
+            64, 65, 65,
+            // face colours
+            0x12, 0x34,
+            // x, y and z deltas
+            74, 59,
+            44, 0xC0, 0x64,
+            67,
+        ];
+        data.extend(footer(3, 1, 0, [0, 5, 0, 0, 0], [2, 3, 1, 3]));
+
+        let model = Model::decode(Bytes::from(data)).expect("decode");
+        assert_eq!(model.vertices(), &[[10, -20, 3], [5, -20, 3], [5, 80, 3]]);
+        assert_eq!(model.vertex_labels(), None);
+        assert_eq!(
+            model.faces(),
+            &[Face {
+                vertices: [0, 1, 2],
+                colour: 0x1234,
+                render_type: 0,
+                priority: 5,
+                alpha: 0,
+                label: None,
+            }]
+        );
+
+        let (obj, mtl) = model.to_obj("triangle.mtl");
+        assert!(obj.starts_with("mtllib triangle.mtl\nv 10 20 3\n"));
+        assert!(obj.ends_with("usemtl colour_4660_0\nf 1 2 3\n"));
+        assert!(mtl.starts_with("newmtl colour_4660_0\n"));
+    }
+
+    #[test]
+    pub fn decode_textured_face() {
+        #[rustfmt::skip]
+        let mut data = vec![
+            // vertex flags
+            0, 1, 2,
+            // face types
+            1,
+            // per-face render types
+            2,
+            // per-face alpha
+            128,
+            // face indices
+            64, 65, 65,
+            // texture id
+            0x00, 0x07,
+            // texture face
+            0, 0, 0, 1, 0, 2,
+            // x and y deltas
+            74, 74,
+        ];
+        data.extend(footer(3, 1, 1, [1, 0, 1, 0, 0], [1, 1, 0, 3]));
+
+        let model = Model::decode(Bytes::from(data)).expect("decode");
+        let face = model.faces()[0];
+        assert!(face.is_textured());
+        assert_eq!(face.texture_face(), Some(0));
+        assert_eq!(face.alpha, 128);
+        assert_eq!(
+            model.texture_coordinates(&face),
+            Some([[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]])
+        );
+
+        let (obj, mtl) = model.to_obj("textured.mtl");
+        assert!(obj.contains("f 1/1 2/2 3/3\n"));
+        assert!(mtl.contains("map_Kd textures/7.png\n"));
+    }
+
+    #[test]
+    pub fn error_invalid_vertex() {
+        #[rustfmt::skip]
+        let mut data = vec![
+            0,
+            1,
+            64, 65, 65,
+            0x00, 0x00,
+        ];
+        data.extend(footer(1, 1, 0, [0, 0, 0, 0, 0], [0, 0, 0, 3]));
+
+        match Model::decode(Bytes::from(data)) {
+            Err(CacheError::Model(ModelError::VertexIndex(1))) => {}
+            other => panic!("face refers to a missing vertex; {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,899 @@
+use std::collections::HashMap;
+
+use bytes::{Buf, Bytes};
+
+use crate::{ArchiveError, CacheFileSystem, SoundError};
+
+const SOUNDS_ENTRY: &str = "sounds.dat";
+const SONG_INDEX: usize = 3;
+const MAX_INSTRUMENTS: usize = 10;
+const MAX_OSCILLATORS: usize = 10;
+
+/// The sample rate that sound effects are rendered at.
+pub const SAMPLE_RATE: u32 = 22050;
+
+/// A sound effect from the `sounds` archive, made up of up to ten synthesized instruments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundEffect {
+    id: u16,
+    instruments: Vec<Instrument>,
+    loop_start: u16,
+    loop_end: u16,
+    delay: u32,
+}
+
+impl SoundEffect {
+    /// Loads every sound effect from the `sounds` archive, keyed by ID.
+    pub fn load(cache: &CacheFileSystem) -> crate::Result<HashMap<u16, Self>> {
+        let archive = cache.get_archive(0, 8)?;
+        let data = archive
+            .get_entry(SOUNDS_ENTRY)
+            .map(|entry| entry.contents())
+            .ok_or(ArchiveError::EntryNotFound(SOUNDS_ENTRY))?;
+        Self::decode_all(data)
+    }
+
+    /// Decodes the contents of `sounds.dat`, which is a list of sound effects each prefixed with
+    /// their ID and terminated by an ID of 65535.
+    pub fn decode_all(data: Bytes) -> crate::Result<HashMap<u16, Self>> {
+        let mut reader = Reader(data);
+        let mut sounds = HashMap::new();
+        loop {
+            match reader.u16()? {
+                65535 => return Ok(sounds),
+                id => {
+                    sounds.insert(id, Self::decode(id, &mut reader)?);
+                }
+            }
+        }
+    }
+
+    fn decode(id: u16, reader: &mut Reader) -> crate::Result<Self> {
+        let mut instruments = Vec::new();
+        for _ in 0..MAX_INSTRUMENTS {
+            if reader.peek()? != 0 {
+                instruments.push(Instrument::decode(reader)?);
+            } else {
+                reader.u8()?;
+            }
+        }
+        let mut sound = SoundEffect {
+            id,
+            instruments,
+            loop_start: reader.u16()?,
+            loop_end: reader.u16()?,
+            delay: 0,
+        };
+        sound.trim_delay();
+        Ok(sound)
+    }
+
+    /// Removes any silence shared by the start of every instrument, which the client instead
+    /// waits out before playing the sound.
+    fn trim_delay(&mut self) {
+        let mut delay = self
+            .instruments
+            .iter()
+            .map(|instrument| u32::from(instrument.offset) / 20)
+            .min()
+            .unwrap_or(u32::MAX);
+        if self.loop_start < self.loop_end {
+            delay = delay.min(u32::from(self.loop_start) / 20);
+        }
+        if delay == 0 || delay == u32::MAX {
+            return;
+        }
+
+        let trim = (delay * 20) as u16;
+        for instrument in self.instruments.iter_mut() {
+            instrument.offset -= trim;
+        }
+        if self.loop_start < self.loop_end {
+            self.loop_start -= trim;
+            self.loop_end -= trim;
+        }
+        self.delay = delay;
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn instruments(&self) -> &[Instrument] {
+        &self.instruments
+    }
+
+    /// The range of the sound, in milliseconds, that is repeated when the sound loops.
+    pub fn loop_range(&self) -> Option<(u16, u16)> {
+        if self.loop_start < self.loop_end {
+            Some((self.loop_start, self.loop_end))
+        } else {
+            None
+        }
+    }
+
+    /// How long the client waits before playing the sound, in 20ms client cycles.
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    /// The length of a single play of the sound in milliseconds.
+    pub fn duration(&self) -> u32 {
+        self.instruments
+            .iter()
+            .map(|instrument| u32::from(instrument.duration) + u32::from(instrument.offset))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Synthesizes every instrument and mixes them into signed 8-bit samples at [`SAMPLE_RATE`],
+    /// playing the loop range `loops` times.
+    pub fn render(&self, loops: u32) -> Vec<i8> {
+        let len = samples(self.duration());
+        let mut mixed = vec![0i8; len];
+        for instrument in self.instruments.iter() {
+            let offset = samples(u32::from(instrument.offset));
+            let synthesized =
+                instrument.synthesize(samples(u32::from(instrument.duration)), instrument.duration);
+            for (sample, mixed) in synthesized.iter().zip(mixed[offset..].iter_mut()) {
+                *mixed = ((sample >> 8) + i32::from(*mixed)).clamp(-128, 127) as i8;
+            }
+        }
+
+        let (start, end) = match self.loop_range() {
+            Some((start, end)) if loops > 1 => (samples(u32::from(start)), samples(u32::from(end))),
+            _ => return mixed,
+        };
+        if end > len {
+            return mixed;
+        }
+        let mut looped = Vec::with_capacity(len + (end - start) * (loops as usize - 1));
+        looped.extend_from_slice(&mixed[..end]);
+        for _ in 1..loops {
+            looped.extend_from_slice(&mixed[start..end]);
+        }
+        looped.extend_from_slice(&mixed[end..]);
+        looped
+    }
+
+    /// Renders the sound as an 8-bit mono PCM WAV file.
+    pub fn to_wav(&self, loops: u32) -> Vec<u8> {
+        let samples = self.render(loops);
+        let mut wav = Vec::with_capacity(samples.len() + 44);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(samples.len() as u32 + 36).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        // 8-bit WAV samples are unsigned, centred on 128.
+        wav.extend(samples.iter().map(|sample| (*sample as u8) ^ 0x80));
+        wav
+    }
+}
+
+/// A MIDI song from index 3 of the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Song {
+    id: u16,
+    data: Bytes,
+}
+
+impl Song {
+    pub fn load(cache: &CacheFileSystem, id: u16) -> crate::Result<Self> {
+        let file = cache.get_file(SONG_INDEX, id as usize)?;
+        if file.is_empty() {
+            return Err(SoundError::SongNotFound(id).into());
+        }
+        let data = crate::compression::decompress_gzip(file)?;
+        if !data.starts_with(b"MThd") {
+            return Err(SoundError::NotMidi(id).into());
+        }
+        Ok(Song { id, data })
+    }
+
+    /// The ID of every song stored in the cache, which is useful for validating music tables.
+    pub fn ids(cache: &CacheFileSystem) -> crate::Result<Vec<u16>> {
+        let mut ids = Vec::new();
+        for id in 0..cache.len(SONG_INDEX)? {
+            if !cache.get_file(SONG_INDEX, id)?.is_empty() {
+                ids.push(id as u16);
+            }
+        }
+        Ok(ids)
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// The contents of a standard MIDI file.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+}
+
+/// A tone generated by up to ten oscillators, shaped by envelopes and a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pitch: Envelope,
+    volume: Envelope,
+    pitch_modifier: Option<(Envelope, Envelope)>,
+    volume_modifier: Option<(Envelope, Envelope)>,
+    gate: Option<(Envelope, Envelope)>,
+    oscillators: Vec<Oscillator>,
+    delay_time: u16,
+    delay_feedback: u16,
+    duration: u16,
+    offset: u16,
+    filter: Filter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Oscillator {
+    volume: u16,
+    pitch: i32,
+    delay: u16,
+}
+
+impl Instrument {
+    fn decode(reader: &mut Reader) -> crate::Result<Self> {
+        let pitch = Envelope::decode(reader)?;
+        let volume = Envelope::decode(reader)?;
+        let pitch_modifier = Self::decode_pair(reader)?;
+        let volume_modifier = Self::decode_pair(reader)?;
+        let gate = Self::decode_pair(reader)?;
+
+        let mut oscillators = Vec::new();
+        for _ in 0..MAX_OSCILLATORS {
+            let volume = reader.smart()?;
+            if volume == 0 {
+                break;
+            }
+            oscillators.push(Oscillator {
+                volume,
+                pitch: reader.signed_smart()?,
+                delay: reader.smart()?,
+            });
+        }
+
+        let delay_time = reader.smart()?;
+        let delay_feedback = reader.smart()?;
+        let duration = reader.u16()?;
+        let offset = reader.u16()?;
+        let filter = Filter::decode(reader)?;
+        Ok(Instrument {
+            pitch,
+            volume,
+            pitch_modifier,
+            volume_modifier,
+            gate,
+            oscillators,
+            delay_time,
+            delay_feedback,
+            duration,
+            offset,
+            filter,
+        })
+    }
+
+    fn decode_pair(reader: &mut Reader) -> crate::Result<Option<(Envelope, Envelope)>> {
+        if reader.peek()? == 0 {
+            reader.u8()?;
+            return Ok(None);
+        }
+        Ok(Some((Envelope::decode(reader)?, Envelope::decode(reader)?)))
+    }
+
+    /// The length of the instrument in milliseconds.
+    pub fn duration(&self) -> u16 {
+        self.duration
+    }
+
+    /// How long after the start of the sound the instrument begins playing, in milliseconds.
+    pub fn offset(&self) -> u16 {
+        self.offset
+    }
+
+    /// Synthesizes `len` signed 16-bit samples spanning `duration` milliseconds.
+    fn synthesize(&self, len: usize, duration: u16) -> Vec<i32> {
+        let mut out = vec![0i32; len];
+        if duration < 10 {
+            return out;
+        }
+        let samples_per_ms = len as f64 / f64::from(duration);
+        let step = |value: i32| (f64::from(value) * 32.768 / samples_per_ms) as i32;
+
+        let mut pitch = self.pitch.clone();
+        let mut volume = self.volume.clone();
+        pitch.reset();
+        volume.reset();
+
+        let mut pitch_modifier = self.pitch_modifier.clone();
+        let (mut pitch_mod_step, mut pitch_mod_base, mut pitch_mod_phase) = (0, 0, 0i32);
+        if let Some((modifier, amplitude)) = pitch_modifier.as_mut() {
+            modifier.reset();
+            amplitude.reset();
+            pitch_mod_step = step(modifier.end - modifier.start);
+            pitch_mod_base = step(modifier.start);
+        }
+
+        let mut volume_modifier = self.volume_modifier.clone();
+        let (mut volume_mod_step, mut volume_mod_base, mut volume_mod_phase) = (0, 0, 0i32);
+        if let Some((modifier, amplitude)) = volume_modifier.as_mut() {
+            modifier.reset();
+            amplitude.reset();
+            volume_mod_step = step(modifier.end - modifier.start);
+            volume_mod_base = step(modifier.start);
+        }
+
+        let mut oscillators: Vec<_> = self
+            .oscillators
+            .iter()
+            .map(|oscillator| OscillatorState {
+                phase: 0,
+                delay: (f64::from(oscillator.delay) * samples_per_ms) as usize,
+                volume: (i32::from(oscillator.volume) << 14) / 100,
+                pitch: (f64::from(self.pitch.end - self.pitch.start)
+                    * 32.768
+                    * 1.005_792_941_067_853_4f64.powi(oscillator.pitch)
+                    / samples_per_ms) as i32,
+                pitch_base: step(self.pitch.start),
+            })
+            .collect();
+
+        for index in 0..len {
+            let mut frequency = pitch.step(len);
+            let mut amplitude = volume.step(len);
+            if let Some((modifier, modifier_amplitude)) = pitch_modifier.as_mut() {
+                let rate = modifier.step(len);
+                let depth = modifier_amplitude.step(len);
+                frequency += evaluate_wave(pitch_mod_phase, depth, modifier.form) >> 1;
+                pitch_mod_phase = pitch_mod_phase
+                    .wrapping_add(pitch_mod_base)
+                    .wrapping_add(rate.wrapping_mul(pitch_mod_step) >> 16);
+            }
+            if let Some((modifier, modifier_amplitude)) = volume_modifier.as_mut() {
+                let rate = modifier.step(len);
+                let depth = modifier_amplitude.step(len);
+                amplitude = amplitude.wrapping_mul(
+                    (evaluate_wave(volume_mod_phase, depth, modifier.form) >> 1) + 32768,
+                ) >> 15;
+                volume_mod_phase = volume_mod_phase
+                    .wrapping_add(volume_mod_base)
+                    .wrapping_add(rate.wrapping_mul(volume_mod_step) >> 16);
+            }
+
+            for oscillator in oscillators.iter_mut() {
+                let position = oscillator.delay + index;
+                if position < len {
+                    out[position] += evaluate_wave(
+                        oscillator.phase,
+                        amplitude.wrapping_mul(oscillator.volume) >> 15,
+                        self.pitch.form,
+                    );
+                    oscillator.phase = oscillator
+                        .phase
+                        .wrapping_add(frequency.wrapping_mul(oscillator.pitch) >> 16)
+                        .wrapping_add(oscillator.pitch_base);
+                }
+            }
+        }
+
+        if let Some((release, attack)) = self.gate.as_ref() {
+            let (mut release, mut attack) = (release.clone(), attack.clone());
+            release.reset();
+            attack.reset();
+            let mut counter = 0;
+            let mut muted = true;
+            for sample in out.iter_mut() {
+                let release_step = release.step(len);
+                let attack_step = attack.step(len);
+                let threshold = if muted {
+                    (release_step.wrapping_mul(release.end - release.start) >> 8) + release.start
+                } else {
+                    (attack_step.wrapping_mul(release.end - release.start) >> 8) + release.start
+                };
+                counter += 256;
+                if counter >= threshold {
+                    counter = 0;
+                    muted = !muted;
+                }
+                if muted {
+                    *sample = 0;
+                }
+            }
+        }
+
+        if self.delay_time > 0 && self.delay_feedback > 0 {
+            let delay = (f64::from(self.delay_time) * samples_per_ms) as usize;
+            for index in delay..len {
+                let echo = out[index - delay].wrapping_mul(i32::from(self.delay_feedback)) / 100;
+                out[index] = out[index].wrapping_add(echo);
+            }
+        }
+
+        if self.filter.pairs[0] > 0 || self.filter.pairs[1] > 0 {
+            self.filter.apply(&mut out);
+        }
+
+        for sample in out.iter_mut() {
+            *sample = (*sample).clamp(-32768, 32767);
+        }
+        out
+    }
+}
+
+struct OscillatorState {
+    phase: i32,
+    delay: usize,
+    volume: i32,
+    pitch: i32,
+    pitch_base: i32,
+}
+
+/// A piecewise linear curve that varies a property of an instrument over its duration.
+#[derive(Debug, Clone, PartialEq)]
+struct Envelope {
+    form: u8,
+    start: i32,
+    end: i32,
+    durations: Vec<u16>,
+    peaks: Vec<u16>,
+    ticks: i32,
+    segment: usize,
+    step: i32,
+    amplitude: i32,
+    position: i32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            form: 0,
+            start: 0,
+            end: 0,
+            durations: vec![0, 65535],
+            peaks: vec![0, 65535],
+            ticks: 0,
+            segment: 0,
+            step: 0,
+            amplitude: 0,
+            position: 0,
+        }
+    }
+}
+
+impl Envelope {
+    fn decode(reader: &mut Reader) -> crate::Result<Self> {
+        let mut envelope = Envelope {
+            form: reader.u8()?,
+            start: reader.i32()?,
+            end: reader.i32()?,
+            ..Envelope::default()
+        };
+        envelope.decode_segments(reader)?;
+        Ok(envelope)
+    }
+
+    fn decode_segments(&mut self, reader: &mut Reader) -> crate::Result<()> {
+        let count = reader.u8()? as usize;
+        self.durations = Vec::with_capacity(count);
+        self.peaks = Vec::with_capacity(count);
+        for _ in 0..count {
+            self.durations.push(reader.u16()?);
+            self.peaks.push(reader.u16()?);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.ticks = 0;
+        self.segment = 0;
+        self.step = 0;
+        self.amplitude = 0;
+        self.position = 0;
+    }
+
+    /// Advances the envelope by one sample of a `period` sample long instrument.
+    fn step(&mut self, period: usize) -> i32 {
+        if self.peaks.is_empty() {
+            return 0;
+        }
+        if self.position >= self.ticks {
+            self.amplitude = i32::from(self.peaks[self.segment]) << 15;
+            self.segment = (self.segment + 1).min(self.peaks.len() - 1);
+            self.ticks = (f64::from(self.durations[self.segment]) / 65536.0 * period as f64) as i32;
+            if self.ticks > self.position {
+                self.step = ((i32::from(self.peaks[self.segment]) << 15) - self.amplitude)
+                    / (self.ticks - self.position);
+            }
+        }
+        self.amplitude = self.amplitude.wrapping_add(self.step);
+        self.position += 1;
+        self.amplitude.wrapping_sub(self.step) >> 15
+    }
+}
+
+/// An IIR filter whose poles and zeros move between two positions following an envelope.
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    pairs: [usize; 2],
+    unity: [u16; 2],
+    phases: [[[u16; 4]; 2]; 2],
+    magnitudes: [[[u16; 4]; 2]; 2],
+    envelope: Envelope,
+}
+
+#[derive(Default)]
+struct Coefficients {
+    forward_min: f32,
+    forward_multiplier: i32,
+    floats: [[f32; 8]; 2],
+    values: [[i32; 8]; 2],
+}
+
+impl Filter {
+    fn decode(reader: &mut Reader) -> crate::Result<Self> {
+        let mut filter = Filter {
+            pairs: [0; 2],
+            unity: [0; 2],
+            phases: Default::default(),
+            magnitudes: Default::default(),
+            envelope: Envelope::default(),
+        };
+        let pairs = reader.u8()?;
+        if pairs == 0 {
+            return Ok(filter);
+        }
+        filter.pairs = [(pairs >> 4) as usize, (pairs & 0xF) as usize];
+        if filter.pairs.iter().any(|pairs| *pairs > 4) {
+            return Err(SoundError::FilterPairs(pairs).into());
+        }
+
+        filter.unity = [reader.u16()?, reader.u16()?];
+        let migrated = reader.u8()?;
+        for direction in 0..2 {
+            for pair in 0..filter.pairs[direction] {
+                filter.phases[direction][0][pair] = reader.u16()?;
+                filter.magnitudes[direction][0][pair] = reader.u16()?;
+            }
+        }
+        for direction in 0..2 {
+            for pair in 0..filter.pairs[direction] {
+                if migrated & ((1 << (direction * 4)) << pair) != 0 {
+                    filter.phases[direction][1][pair] = reader.u16()?;
+                    filter.magnitudes[direction][1][pair] = reader.u16()?;
+                } else {
+                    filter.phases[direction][1][pair] = filter.phases[direction][0][pair];
+                    filter.magnitudes[direction][1][pair] = filter.magnitudes[direction][0][pair];
+                }
+            }
+        }
+        if migrated != 0 || filter.unity[1] != filter.unity[0] {
+            filter.envelope.decode_segments(reader)?;
+        }
+        Ok(filter)
+    }
+
+    fn magnitude(&self, direction: usize, pair: usize, f: f32) -> f32 {
+        let [from, to] = self.magnitudes[direction];
+        let magnitude = (f32::from(from[pair]) + f * (f32::from(to[pair]) - f32::from(from[pair])))
+            * 0.001_525_878_9;
+        1.0 - 10f64.powf(f64::from(-magnitude / 20.0)) as f32
+    }
+
+    fn phase(&self, direction: usize, pair: usize, f: f32) -> f32 {
+        let [from, to] = self.phases[direction];
+        let phase = (f32::from(from[pair]) + f * (f32::from(to[pair]) - f32::from(from[pair])))
+            * 1.220_703_1e-4;
+        let frequency = 32.703_197 * 2f64.powf(f64::from(phase)) as f32;
+        frequency * std::f32::consts::PI / 11025.0
+    }
+
+    /// Computes the coefficients for one direction of the filter at point `f` of its envelope,
+    /// returning the number of coefficients.
+    fn compute(&self, direction: usize, f: f32, coefficients: &mut Coefficients) -> usize {
+        if direction == 0 {
+            let unity = (f32::from(self.unity[0])
+                + (f32::from(self.unity[1]) - f32::from(self.unity[0])) * f)
+                * 0.003_051_757_8;
+            coefficients.forward_min = 0.1f64.powf(f64::from(unity / 20.0)) as f32;
+            coefficients.forward_multiplier = (coefficients.forward_min * 65536.0) as i32;
+        }
+        let pairs = self.pairs[direction];
+        if pairs == 0 {
+            return 0;
+        }
+
+        let floats = &mut coefficients.floats[direction];
+        let magnitude = self.magnitude(direction, 0, f);
+        floats[0] = -2.0 * magnitude * f64::from(self.phase(direction, 0, f)).cos() as f32;
+        floats[1] = magnitude * magnitude;
+        for pair in 1..pairs {
+            let magnitude = self.magnitude(direction, pair, f);
+            let a = -2.0 * magnitude * f64::from(self.phase(direction, pair, f)).cos() as f32;
+            let b = magnitude * magnitude;
+            floats[pair * 2 + 1] = floats[pair * 2 - 1] * b;
+            floats[pair * 2] = floats[pair * 2 - 1] * a + floats[pair * 2 - 2] * b;
+            for index in (2..pair * 2).rev() {
+                floats[index] += floats[index - 1] * a + floats[index - 2] * b;
+            }
+            floats[1] += floats[0] * a + b;
+            floats[0] += a;
+        }
+
+        if direction == 0 {
+            for float in floats.iter_mut().take(pairs * 2) {
+                *float *= coefficients.forward_min;
+            }
+        }
+        for (value, float) in coefficients.values[direction]
+            .iter_mut()
+            .zip(floats.iter())
+            .take(pairs * 2)
+        {
+            *value = (float * 65536.0) as i32;
+        }
+        pairs * 2
+    }
+
+    fn apply(&self, out: &mut [i32]) {
+        let len = out.len();
+        let mut envelope = self.envelope.clone();
+        envelope.reset();
+        let mut coefficients = Coefficients::default();
+        let mut position = envelope.step(len + 1);
+        let mut forward = self.compute(0, position as f32 / 65536.0, &mut coefficients);
+        let mut backward = self.compute(1, position as f32 / 65536.0, &mut coefficients);
+        if len < forward + backward {
+            return;
+        }
+
+        let scale = |sample: i32, coefficient: i32| {
+            ((i64::from(sample) * i64::from(coefficient)) >> 16) as i32
+        };
+        let mut index = 0;
+        let mut end = backward.min(len - forward);
+        while index < end {
+            let mut sample = scale(out[index + forward], coefficients.forward_multiplier);
+            for offset in 0..forward {
+                sample += scale(
+                    out[index + forward - 1 - offset],
+                    coefficients.values[0][offset],
+                );
+            }
+            for offset in 0..index {
+                sample -= scale(out[index - 1 - offset], coefficients.values[1][offset]);
+            }
+            out[index] = sample;
+            position = envelope.step(len + 1);
+            index += 1;
+        }
+
+        // The coefficients are recomputed every 128 samples as the envelope moves.
+        end = 128;
+        loop {
+            end = end.min(len - forward);
+            while index < end {
+                let mut sample = scale(out[index + forward], coefficients.forward_multiplier);
+                for offset in 0..forward {
+                    sample += scale(
+                        out[index + forward - 1 - offset],
+                        coefficients.values[0][offset],
+                    );
+                }
+                for offset in 0..backward {
+                    sample -= scale(out[index - 1 - offset], coefficients.values[1][offset]);
+                }
+                out[index] = sample;
+                position = envelope.step(len + 1);
+                index += 1;
+            }
+
+            if index >= len - forward {
+                while index < len {
+                    let mut sample = 0;
+                    for offset in (index + forward - len)..forward {
+                        sample += scale(
+                            out[index + forward - 1 - offset],
+                            coefficients.values[0][offset],
+                        );
+                    }
+                    for offset in 0..backward {
+                        sample -= scale(out[index - 1 - offset], coefficients.values[1][offset]);
+                    }
+                    out[index] = sample;
+                    envelope.step(len + 1);
+                    index += 1;
+                }
+                return;
+            }
+
+            forward = self.compute(0, position as f32 / 65536.0, &mut coefficients);
+            backward = self.compute(1, position as f32 / 65536.0, &mut coefficients);
+            end += 128;
+        }
+    }
+}
+
+fn evaluate_wave(phase: i32, amplitude: i32, form: u8) -> i32 {
+    let phase = phase & 0x7FFF;
+    match form {
+        1 if phase < 16384 => amplitude,
+        1 => -amplitude,
+        2 => tables().sine[phase as usize].wrapping_mul(amplitude) >> 14,
+        3 => (amplitude.wrapping_mul(phase) >> 14).wrapping_sub(amplitude),
+        4 => amplitude.wrapping_mul(tables().noise[(phase / 2607) as usize]),
+        _ => 0,
+    }
+}
+
+struct Tables {
+    sine: Vec<i32>,
+    noise: Vec<i32>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: std::sync::OnceLock<Tables> = std::sync::OnceLock::new();
+    TABLES.get_or_init(|| {
+        // The noise table is generated by java.util.Random seeded with 0.
+        let mut seed: u64 = 0x5DEE_CE66D;
+        let noise = (0..32768)
+            .map(|_| {
+                seed = (seed.wrapping_mul(0x5DEE_CE66D).wrapping_add(0xB)) & ((1 << 48) - 1);
+                ((seed >> 16) as i32 & 2) - 1
+            })
+            .collect();
+        let sine = (0..32768)
+            .map(|index| ((f64::from(index) / 5215.1903).sin() * 16384.0) as i32)
+            .collect();
+        Tables { sine, noise }
+    })
+}
+
+fn samples(millis: u32) -> usize {
+    (u64::from(millis) * u64::from(SAMPLE_RATE) / 1000) as usize
+}
+
+struct Reader(Bytes);
+
+impl Reader {
+    fn ensure(&self, len: usize) -> crate::Result<()> {
+        if self.0.remaining() < len {
+            return Err(SoundError::Truncated.into());
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> crate::Result<u8> {
+        self.ensure(1)?;
+        Ok(self.0[0])
+    }
+
+    fn u8(&mut self) -> crate::Result<u8> {
+        self.ensure(1)?;
+        Ok(self.0.get_u8())
+    }
+
+    fn u16(&mut self) -> crate::Result<u16> {
+        self.ensure(2)?;
+        Ok(self.0.get_u16())
+    }
+
+    fn i32(&mut self) -> crate::Result<i32> {
+        self.ensure(4)?;
+        Ok(self.0.get_i32())
+    }
+
+    fn smart(&mut self) -> crate::Result<u16> {
+        if self.peek()? < 128 {
+            Ok(u16::from(self.0.get_u8()))
+        } else {
+            Ok(self.u16()? - 32768)
+        }
+    }
+
+    fn signed_smart(&mut self) -> crate::Result<i32> {
+        crate::get_signed_smart(&mut self.0).ok_or_else(|| SoundError::Truncated.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CacheError;
+
+    #[rustfmt::skip]
+    fn sounds(filter: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x00, 0x05];
+        // pitch envelope, square wave from 1000 to 1000
+        data.extend_from_slice(&[1, 0, 0, 0x03, 0xE8, 0, 0, 0x03, 0xE8, 2, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        // volume envelope, constant at full volume
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        // no modifiers or gate
+        data.extend_from_slice(&[0, 0, 0]);
+        // a single oscillator at full volume
+        data.extend_from_slice(&[100, 64, 0, 0]);
+        // no delay, 100ms long starting at 40ms
+        data.extend_from_slice(&[0, 0, 0x00, 0x64, 0x00, 0x28]);
+        data.extend_from_slice(filter);
+        // the remaining instruments, then the loop range
+        data.extend_from_slice(&[0; 9]);
+        data.extend_from_slice(&[0x00, 0x3C, 0x00, 0x50]);
+        data.extend_from_slice(&[0xFF, 0xFF]);
+        data
+    }
+
+    #[test]
+    pub fn decode_and_render() {
+        let sounds = SoundEffect::decode_all(Bytes::from(sounds(&[0]))).expect("decode");
+        let sound = &sounds[&5];
+        assert_eq!(sound.instruments().len(), 1);
+        assert_eq!(sound.delay(), 2, "leading silence should be trimmed");
+        assert_eq!(sound.instruments()[0].offset(), 0);
+        assert_eq!(sound.loop_range(), Some((20, 40)));
+        assert_eq!(sound.duration(), 100);
+
+        let samples = sound.render(1);
+        assert_eq!(samples.len(), 2205);
+        assert!(samples.iter().any(|sample| *sample > 100));
+        assert!(samples.iter().any(|sample| *sample < -100));
+        assert_eq!(sound.render(3).len(), 2205 + 441 * 2);
+
+        let wav = sound.to_wav(1);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(wav.len(), 44 + 2205);
+    }
+
+    #[test]
+    pub fn render_filtered() {
+        #[rustfmt::skip]
+        let filter = [
+            0x11,
+            0x00, 0x00, 0x10, 0x00,
+            0x00,
+            0x80, 0x00, 0x40, 0x00,
+            0x40, 0x00, 0x20, 0x00,
+            2, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        let filtered = SoundEffect::decode_all(Bytes::from(sounds(&filter))).expect("decode");
+        let unfiltered = SoundEffect::decode_all(Bytes::from(sounds(&[0]))).expect("decode");
+        let samples = filtered[&5].render(1);
+        assert_eq!(samples.len(), 2205);
+        assert_ne!(samples, unfiltered[&5].render(1));
+    }
+
+    #[test]
+    pub fn render_high_feedback() {
+        let mut data = sounds(&[0]);
+        // a 10ms delay fed back at 32767%
+        data.splice(45..47, [0x0A, 0xFF, 0xFF]);
+        // a saw wave from the loudest possible oscillator
+        data[2] = 3;
+        data.splice(41..42, [0xFF, 0xFF]);
+        let sounds = SoundEffect::decode_all(Bytes::from(data)).expect("decode");
+        let samples = sounds[&5].render(1);
+        assert_eq!(samples.len(), 2205);
+        assert!(samples.iter().any(|sample| *sample != 0));
+        for form in 1..=4 {
+            evaluate_wave(0x7FFF, i32::MAX, form);
+        }
+    }
+
+    #[test]
+    pub fn error_truncated() {
+        let data = sounds(&[0]);
+        match SoundEffect::decode_all(Bytes::copy_from_slice(&data[..30])) {
+            Err(CacheError::Sound(SoundError::Truncated)) => {}
+            _ => panic!("sound effect is truncated"),
+        }
+    }
+}
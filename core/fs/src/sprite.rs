@@ -0,0 +1,257 @@
+use bytes::{Buf, Bytes};
+
+use crate::{Archive, ArchiveError, CacheFileSystem, SpriteError};
+
+const INDEX_ENTRY: &str = "index.dat";
+
+/// A palette-indexed image from the media or title archive.
+///
+/// Sprites are trimmed of transparent borders when stored; `offset_x` and `offset_y` position the
+/// stored pixels within a canvas of `max_width` by `max_height`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sprite {
+    max_width: u16,
+    max_height: u16,
+    offset_x: u8,
+    offset_y: u8,
+    width: u16,
+    height: u16,
+    palette: Vec<u32>,
+    pixels: Vec<u8>,
+}
+
+impl Sprite {
+    /// Loads a single sprite from a group in the media archive, such as `("mapscene", 3)`.
+    pub fn load(cache: &CacheFileSystem, name: &str, index: usize) -> crate::Result<Self> {
+        let archive = cache.get_archive(0, 4)?;
+        Self::decode_group(&archive, name)?
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| SpriteError::NotFound(index).into())
+    }
+
+    /// Decodes every sprite in the group named `name`, which is stored in the `name.dat` entry of
+    /// the archive.
+    pub fn decode_group(archive: &Archive, name: &str) -> crate::Result<Vec<Self>> {
+        let index = archive
+            .get_entry(INDEX_ENTRY)
+            .map(|entry| entry.contents())
+            .ok_or(ArchiveError::EntryNotFound(INDEX_ENTRY))?;
+        let data = archive
+            .get_entry(&format!("{}.dat", name))
+            .map(|entry| entry.contents())
+            .ok_or(ArchiveError::EntryNotFound("sprite group"))?;
+        Self::decode(index, data)
+    }
+
+    /// Decodes every sprite from the contents of a group's entry, using the headers stored in the
+    /// archive's `index.dat` entry.
+    pub fn decode(mut index: Bytes, mut data: Bytes) -> crate::Result<Vec<Self>> {
+        ensure(&data, 2)?;
+        let offset = data.get_u16() as usize;
+        if offset > index.len() {
+            return Err(SpriteError::Truncated.into());
+        }
+        index.advance(offset);
+
+        ensure(&index, 5)?;
+        let max_width = index.get_u16();
+        let max_height = index.get_u16();
+        let palette_size = index.get_u8() as usize;
+        let mut palette = vec![0; palette_size.max(1)];
+        ensure(&index, palette_size.saturating_sub(1) * 3)?;
+        for colour in palette.iter_mut().skip(1) {
+            // Black is stored as 1 so that 0 can mark transparent pixels.
+            *colour = match index.get_uint(3) as u32 {
+                0 => 1,
+                rgb => rgb,
+            };
+        }
+
+        let mut sprites = Vec::new();
+        while data.has_remaining() {
+            ensure(&index, 7)?;
+            let offset_x = index.get_u8();
+            let offset_y = index.get_u8();
+            let width = index.get_u16();
+            let height = index.get_u16();
+            let layout = index.get_u8();
+
+            let size = width as usize * height as usize;
+            ensure(&data, size)?;
+            let mut pixels = vec![0; size];
+            match layout {
+                0 => data.copy_to_slice(&mut pixels),
+                1 => {
+                    for x in 0..width as usize {
+                        for y in 0..height as usize {
+                            pixels[x + y * width as usize] = data.get_u8();
+                        }
+                    }
+                }
+                layout => return Err(SpriteError::UnknownLayout(layout).into()),
+            }
+            if let Some(entry) = pixels
+                .iter()
+                .find(|entry| **entry as usize >= palette.len())
+            {
+                return Err(SpriteError::PaletteIndex(*entry).into());
+            }
+
+            sprites.push(Sprite {
+                max_width,
+                max_height,
+                offset_x,
+                offset_y,
+                width,
+                height,
+                palette: palette.clone(),
+                pixels,
+            });
+        }
+        Ok(sprites)
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn offset_x(&self) -> u8 {
+        self.offset_x
+    }
+
+    pub fn offset_y(&self) -> u8 {
+        self.offset_y
+    }
+
+    pub fn max_width(&self) -> u16 {
+        self.max_width
+    }
+
+    pub fn max_height(&self) -> u16 {
+        self.max_height
+    }
+
+    /// The RGB colours referred to by the pixels, where entry 0 is transparent.
+    pub fn palette(&self) -> &[u32] {
+        &self.palette
+    }
+
+    /// The palette entry of every stored pixel, in row-major order.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// The RGB colour of a stored pixel, or `None` if it is transparent or out of bounds.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width as usize || y >= self.height as usize {
+            return None;
+        }
+        match self.pixels[x + y * self.width as usize] {
+            0 => None,
+            entry => Some(self.palette[entry as usize]),
+        }
+    }
+
+    /// Converts the stored pixels to RGBA, without the transparent borders.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                rgba.extend_from_slice(&to_rgba(self.get_pixel(x, y)));
+            }
+        }
+        rgba
+    }
+
+    /// Converts the sprite to RGBA, restoring the transparent borders trimmed when it was stored.
+    pub fn to_rgba_untrimmed(&self) -> Vec<u8> {
+        let (offset_x, offset_y) = (self.offset_x as usize, self.offset_y as usize);
+        let mut rgba = Vec::with_capacity(self.max_width as usize * self.max_height as usize * 4);
+        for y in 0..self.max_height as usize {
+            for x in 0..self.max_width as usize {
+                let pixel = if x >= offset_x && y >= offset_y {
+                    self.get_pixel(x - offset_x, y - offset_y)
+                } else {
+                    None
+                };
+                rgba.extend_from_slice(&to_rgba(pixel));
+            }
+        }
+        rgba
+    }
+}
+
+fn to_rgba(pixel: Option<u32>) -> [u8; 4] {
+    match pixel {
+        Some(rgb) => [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255],
+        None => [0; 4],
+    }
+}
+
+fn ensure(buf: &Bytes, len: usize) -> crate::Result<()> {
+    if buf.remaining() < len {
+        return Err(SpriteError::Truncated.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CacheError;
+
+    fn index() -> Bytes {
+        #[rustfmt::skip]
+        let index: &[u8] = &[
+            0xFF, 0xFF,
+            0x00, 0x04, 0x00, 0x03,
+            3, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00,
+            1, 0, 0x00, 0x02, 0x00, 0x02, 0,
+            0, 0, 0x00, 0x02, 0x00, 0x02, 1,
+        ];
+        Bytes::from(index)
+    }
+
+    #[test]
+    pub fn decode_layouts() {
+        #[rustfmt::skip]
+        let data = Bytes::from(&[
+            0x00, 0x02,
+            1, 0, 2, 1,
+            1, 0, 2, 1,
+        ][..]);
+
+        let sprites = Sprite::decode(index(), data).expect("decode");
+        assert_eq!(sprites.len(), 2);
+
+        // The row-major and column-major sprites share pixels, so are read in a different order.
+        assert_eq!(sprites[0].pixels(), &[1, 0, 2, 1]);
+        assert_eq!(sprites[1].pixels(), &[1, 2, 0, 1]);
+        assert_eq!(sprites[0].get_pixel(0, 0), Some(0xFF0000));
+        assert_eq!(sprites[0].get_pixel(1, 0), None);
+        assert_eq!(
+            sprites[0].get_pixel(0, 1),
+            Some(1),
+            "black is not transparent"
+        );
+
+        let rgba = sprites[0].to_rgba_untrimmed();
+        assert_eq!(rgba.len(), 4 * 3 * 4);
+        assert_eq!(&rgba[..4], &[0, 0, 0, 0]);
+        assert_eq!(&rgba[4..8], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    pub fn error_truncated() {
+        let data = Bytes::from(&[0x00, 0x02, 1, 0, 2][..]);
+        match Sprite::decode(index(), data) {
+            Err(CacheError::Sprite(SpriteError::Truncated)) => {}
+            _ => panic!("sprite is missing a pixel"),
+        }
+    }
+}
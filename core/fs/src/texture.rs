@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+
+use crate::{Archive, ArchiveError, CacheFileSystem, Sprite};
+
+/// The exponent the client applies to each channel of a texture's average colour.
+const AVERAGE_BRIGHTNESS: f64 = 1.4;
+
+/// A palette-indexed image from the textures archive, mapped onto textured floors and model
+/// faces. Textures are usually 128x128, although some are stored at 64x64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Texture {
+    id: u16,
+    image: Sprite,
+}
+
+impl Texture {
+    pub fn load(cache: &CacheFileSystem, id: u16) -> crate::Result<Self> {
+        let archive = cache.get_archive(0, 6)?;
+        Self::decode_entry(&archive, id)
+    }
+
+    /// Loads every texture in the textures archive, keyed by ID.
+    pub fn load_all(cache: &CacheFileSystem) -> crate::Result<HashMap<u16, Self>> {
+        let archive = cache.get_archive(0, 6)?;
+        let mut textures = HashMap::new();
+        for id in 0..=u16::from(u8::MAX) {
+            if archive.get_entry(&format!("{}.dat", id)).is_some() {
+                textures.insert(id, Self::decode_entry(&archive, id)?);
+            }
+        }
+        Ok(textures)
+    }
+
+    fn decode_entry(archive: &Archive, id: u16) -> crate::Result<Self> {
+        let image = Sprite::decode_group(archive, &id.to_string())?
+            .into_iter()
+            .next()
+            .ok_or(ArchiveError::EntryNotFound("texture"))?;
+        Ok(Texture { id, image })
+    }
+
+    /// Decodes a texture from the contents of its entry, using the headers stored in the
+    /// archive's `index.dat` entry.
+    pub fn decode(id: u16, index: Bytes, data: Bytes) -> crate::Result<Self> {
+        let image = Sprite::decode(index, data)?
+            .into_iter()
+            .next()
+            .ok_or(ArchiveError::EntryNotFound("texture"))?;
+        Ok(Texture { id, image })
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn width(&self) -> u16 {
+        self.image.max_width()
+    }
+
+    pub fn height(&self) -> u16 {
+        self.image.max_height()
+    }
+
+    /// Whether any pixel of the texture is transparent, in which case the client draws it
+    /// without overwriting the pixels behind it.
+    pub fn is_transparent(&self) -> bool {
+        self.image.width() != self.width()
+            || self.image.height() != self.height()
+            || self.image.pixels().contains(&0)
+    }
+
+    /// Converts the texture to RGBA at its full size.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.image.to_rgba_untrimmed()
+    }
+
+    /// The average colour of the texture's palette, which the client uses to draw textured
+    /// floors on the minimap and textured faces at a distance.
+    ///
+    /// Like the client, the average is darkened by raising each channel to the power of 1.4.
+    pub fn average_rgb(&self) -> u32 {
+        let palette = self.image.palette();
+        let len = palette.len() as u32;
+        let (r, g, b) = palette.iter().fold((0, 0, 0), |(r, g, b), rgb| {
+            (
+                r + ((rgb >> 16) & 0xFF),
+                g + ((rgb >> 8) & 0xFF),
+                b + (rgb & 0xFF),
+            )
+        });
+        let average = ((r / len) << 16) | ((g / len) << 8) | (b / len);
+        match adjust_brightness(average, AVERAGE_BRIGHTNESS) {
+            0 => 1,
+            rgb => rgb,
+        }
+    }
+}
+
+/// Raises each channel of `rgb`, as a fraction of 256, to the power of `exponent`.
+fn adjust_brightness(rgb: u32, exponent: f64) -> u32 {
+    let channel = |shift: u32| {
+        let value = f64::from((rgb >> shift) & 0xFF) / 256.0;
+        (value.powf(exponent) * 256.0) as u32
+    };
+    (channel(16) << 16) | (channel(8) << 8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> Bytes {
+        #[rustfmt::skip]
+        let index: &[u8] = &[
+            0xFF, 0xFF,
+            0x00, 0x02, 0x00, 0x02,
+            3, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF,
+            0, 0, 0x00, 0x02, 0x00, 0x02, 0,
+        ];
+        Bytes::from(index)
+    }
+
+    #[test]
+    pub fn decode_texture() {
+        let data = Bytes::from(&[0x00, 0x02, 1, 2, 2, 1][..]);
+        let texture = Texture::decode(3, index(), data).expect("decode");
+        assert_eq!((texture.width(), texture.height()), (2, 2));
+        assert!(!texture.is_transparent());
+        assert_eq!(&texture.to_rgba()[..8], &[255, 0, 0, 255, 0, 0, 255, 255]);
+
+        // Averaged over the whole palette, including the transparent entry, then darkened.
+        assert_eq!(texture.average_rgb(), 0x360036);
+    }
+
+    #[test]
+    pub fn transparent_texture() {
+        let data = Bytes::from(&[0x00, 0x02, 1, 0, 2, 1][..]);
+        let texture = Texture::decode(3, index(), data).expect("decode");
+        assert!(texture.is_transparent());
+    }
+}
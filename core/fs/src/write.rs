@@ -0,0 +1,311 @@
+use std::fs::File;
+use std::io::{self, prelude::*, Cursor, SeekFrom};
+
+use bytes::{Buf, BufMut, BytesMut};
+use memmap::Mmap;
+
+use crate::{
+    part_count, CacheError, CacheFileSystem, CacheIndex, Result, BLOCK_SIZE, CHUNK_SIZE,
+    HEADER_SIZE, INDEX_SIZE, MAX_FILE_SIZE,
+};
+
+#[derive(Debug)]
+pub(crate) struct CacheWriter {
+    data_file: File,
+    index_files: Vec<File>,
+}
+
+impl CacheWriter {
+    pub(crate) fn new(data_file: File, index_files: Vec<File>) -> Self {
+        Self {
+            data_file,
+            index_files,
+        }
+    }
+
+    fn write_block(
+        &mut self,
+        block: u64,
+        header: (u16, u16, u64, u8),
+        contents: &[u8],
+    ) -> io::Result<()> {
+        let (file_number, file_part, next_block, next_type) = header;
+        let mut buf = BytesMut::with_capacity(HEADER_SIZE as usize + contents.len());
+        buf.put_u16(file_number);
+        buf.put_u16(file_part);
+        buf.put_uint(next_block, 3);
+        buf.put_u8(next_type);
+        buf.put_slice(contents);
+
+        self.data_file.seek(SeekFrom::Start(block * BLOCK_SIZE))?;
+        self.data_file.write_all(&buf)
+    }
+
+    fn write_index(
+        &mut self,
+        index_number: usize,
+        file_number: usize,
+        size: u64,
+        initial_block: u64,
+    ) -> io::Result<()> {
+        let mut buf = BytesMut::with_capacity(INDEX_SIZE as usize);
+        buf.put_uint(size, 3);
+        buf.put_uint(initial_block, 3);
+
+        let index_file = &mut self.index_files[index_number];
+        index_file.seek(SeekFrom::Start(INDEX_SIZE * file_number as u64))?;
+        index_file.write_all(&buf)
+    }
+}
+
+impl CacheFileSystem {
+    /// Writes `contents` to the cache, replacing the file if it already exists.
+    ///
+    /// The blocks of an existing file are reused before new blocks are appended to the end of
+    /// the data file. Requires the cache to have been opened with
+    /// [`open_writable`](#method.open_writable).
+    pub fn put_file(
+        &mut self,
+        index_number: usize,
+        file_number: usize,
+        contents: &[u8],
+    ) -> Result<()> {
+        if self.writer.is_none() {
+            return Err(CacheError::ReadOnly);
+        }
+
+        if index_number >= self.indices.len() {
+            return Err(CacheError::IndexNotFound(index_number));
+        }
+
+        if file_number > u16::MAX as usize {
+            return Err(CacheError::FileOutOfRange(index_number, file_number));
+        }
+
+        if contents.len() > MAX_FILE_SIZE {
+            return Err(CacheError::FileTooLarge(contents.len()));
+        }
+
+        let num_parts = part_count(contents.len() as u64);
+        let mut blocks = self.block_chain(index_number, file_number)?;
+        let mut next_free = self.next_free_block();
+        while blocks.len() < num_parts {
+            blocks.push(next_free);
+            next_free += 1;
+        }
+        blocks.truncate(num_parts);
+
+        log::trace!(
+            "Writing file (idx: {}, num: {}) of {} bytes across blocks {:?}",
+            index_number,
+            file_number,
+            contents.len(),
+            blocks
+        );
+
+        let writer = self.writer.as_mut().ok_or(CacheError::ReadOnly)?;
+        for (file_part, chunk) in contents.chunks(CHUNK_SIZE as usize).enumerate() {
+            let next_block = blocks.get(file_part + 1).copied().unwrap_or(0);
+            let header = (
+                file_number as u16,
+                file_part as u16,
+                next_block,
+                (index_number + 1) as u8,
+            );
+            writer.write_block(blocks[file_part], header, chunk)?;
+        }
+
+        let initial_block = blocks.first().copied().unwrap_or(0);
+        writer.write_index(
+            index_number,
+            file_number,
+            contents.len() as u64,
+            initial_block,
+        )?;
+        self.remap(index_number)
+    }
+
+    /// Removes a file from the cache by clearing its index entry.
+    ///
+    /// The blocks used by the file are left in the data file and will not be reclaimed.
+    pub fn remove_file(&mut self, index_number: usize, file_number: usize) -> Result<()> {
+        let writer = self.writer.as_mut().ok_or(CacheError::ReadOnly)?;
+        let index = self
+            .indices
+            .get(index_number)
+            .ok_or(CacheError::IndexNotFound(index_number))?;
+
+        if file_number >= index.len {
+            return Err(CacheError::FileNotFound(index_number, file_number));
+        }
+
+        writer.write_index(index_number, file_number, 0, 0)?;
+        self.remap(index_number)
+    }
+
+    /// Follows the blocks of an existing file, stopping at the first block whose header does not
+    /// belong to the file.
+    fn block_chain(&self, index_number: usize, file_number: usize) -> Result<Vec<u64>> {
+        let index = &self.indices[index_number];
+        if file_number >= index.len {
+            return Ok(Vec::new());
+        }
+
+        let (size, mut block) = index.get_block(file_number)?;
+        let num_parts = part_count(size);
+        let mut blocks = Vec::with_capacity(num_parts);
+
+        let mut cursor = Cursor::new(&self.data_file);
+        for file_part in 0..num_parts {
+            let position = block * BLOCK_SIZE;
+            if position + HEADER_SIZE > self.data_file.len() as u64 {
+                break;
+            }
+
+            cursor.seek(SeekFrom::Start(position))?;
+            let read_file_number = cursor.get_u16() as usize;
+            let read_file_part = cursor.get_u16() as usize;
+            let next_block = cursor.get_uint(3);
+            let next_type = cursor.get_u8() as usize;
+
+            if read_file_number != file_number
+                || read_file_part != file_part
+                || next_type != index_number + 1
+            {
+                break;
+            }
+
+            blocks.push(block);
+            block = next_block;
+        }
+        Ok(blocks)
+    }
+
+    fn next_free_block(&self) -> u64 {
+        let len = self.data_file.len() as u64;
+        let blocks = len / BLOCK_SIZE + u64::from(!len.is_multiple_of(BLOCK_SIZE));
+        // Block 0 is never allocated; a next block of 0 marks the end of a file.
+        std::cmp::max(blocks, 1)
+    }
+
+    fn remap(&mut self, index_number: usize) -> Result<()> {
+        let writer = self.writer.as_ref().ok_or(CacheError::ReadOnly)?;
+        self.data_file = unsafe { Mmap::map(&writer.data_file) }?;
+
+        let index_file = unsafe { Mmap::map(&writer.index_files[index_number]) }?;
+        self.indices[index_number] = CacheIndex {
+            len: index_file.len() / INDEX_SIZE as usize,
+            index_file,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn create_filesystem(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("mithril-fs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("cache dir");
+        // Empty files cannot be mapped, so start with the reserved block and an empty entry.
+        fs::write(
+            path.join("main_file_cache.dat"),
+            vec![0; BLOCK_SIZE as usize],
+        )
+        .expect("data");
+        for i in 0..5 {
+            let index_path = path.join(format!("main_file_cache.idx{}", i));
+            fs::write(index_path, vec![0; INDEX_SIZE as usize]).expect("index file");
+        }
+        path
+    }
+
+    fn contents(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+            .collect()
+    }
+
+    #[test]
+    pub fn put_file_round_trip() {
+        let path = create_filesystem("round-trip");
+        let mut cache = CacheFileSystem::open_writable(&path).expect("cache");
+
+        let files = [0, 1, 511, 512, 513, 1024, 5000];
+        for (file_number, len) in files.iter().enumerate() {
+            let data = contents(*len, file_number as u8);
+            cache.put_file(1, file_number, &data).expect("put_file");
+        }
+
+        for (file_number, len) in files.iter().enumerate() {
+            let data = contents(*len, file_number as u8);
+            let read = cache.get_file(1, file_number).expect("get_file");
+            assert_eq!(&read[..], &data[..], "file {} differs", file_number);
+        }
+        assert_eq!(cache.len(1).unwrap(), files.len());
+
+        drop(cache);
+        let cache = CacheFileSystem::open(&path).expect("reopen");
+        let read = cache.get_file(1, 6).expect("get_file");
+        assert_eq!(&read[..], &contents(5000, 6)[..]);
+
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    pub fn put_file_reuses_blocks() {
+        let path = create_filesystem("reuse");
+        let mut cache = CacheFileSystem::open_writable(&path).expect("cache");
+
+        cache.put_file(2, 0, &contents(2000, 1)).expect("put_file");
+        cache.put_file(2, 1, &contents(700, 2)).expect("put_file");
+        let data_len = cache.data_file.len();
+
+        cache.put_file(2, 0, &contents(1900, 3)).expect("overwrite");
+        assert_eq!(data_len, cache.data_file.len(), "blocks should be reused");
+        assert_eq!(&cache.get_file(2, 0).unwrap()[..], &contents(1900, 3)[..]);
+        assert_eq!(&cache.get_file(2, 1).unwrap()[..], &contents(700, 2)[..]);
+
+        cache.put_file(2, 0, &contents(3000, 4)).expect("grow");
+        assert_eq!(&cache.get_file(2, 0).unwrap()[..], &contents(3000, 4)[..]);
+        assert_eq!(&cache.get_file(2, 1).unwrap()[..], &contents(700, 2)[..]);
+
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    pub fn remove_file() {
+        let path = create_filesystem("remove");
+        let mut cache = CacheFileSystem::open_writable(&path).expect("cache");
+
+        cache.put_file(0, 3, &contents(100, 0)).expect("put_file");
+        assert_eq!(cache.len(0).unwrap(), 4);
+        cache.remove_file(0, 3).expect("remove_file");
+        assert!(cache.get_file(0, 3).expect("get_file").is_empty());
+
+        match cache.remove_file(0, 4) {
+            Err(CacheError::FileNotFound(0, 4)) => {}
+            _ => panic!("file 4 was never written"),
+        }
+
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    pub fn error_read_only() {
+        let path = create_filesystem("read-only");
+        let mut cache = CacheFileSystem::open(&path).expect("cache");
+
+        match cache.put_file(0, 0, &[1, 2, 3]) {
+            Err(CacheError::ReadOnly) => {}
+            _ => panic!("cache was opened without write access"),
+        }
+
+        let _ = fs::remove_dir_all(&path);
+    }
+}
@@ -3,7 +3,9 @@ use rand::Rng;
 use rand_isaac::IsaacRng;
 
 use crate::packets::PacketEvent;
-use crate::{Packet, PacketDirection, PacketId, PacketLength, PacketStage, PacketType};
+use crate::{
+    DecodeError, Packet, PacketDirection, PacketId, PacketLength, PacketStage, PacketType,
+};
 
 pub fn encode_packet(
     isaac: Option<&mut IsaacRng>,
@@ -49,10 +51,93 @@ pub fn encode_packet(
     Ok(())
 }
 
+/// Decodes a packet that has been received in full.
+///
+/// Use a [`PacketDecoder`] for packets read from a stream, which may only be partially received.
 pub fn decode_packet(
     isaac: Option<&mut IsaacRng>,
     src: &mut BytesMut,
-) -> anyhow::Result<PacketEvent> {
+) -> Result<PacketEvent, DecodeError> {
+    if !src.has_remaining() {
+        return Err(DecodeError::Truncated);
+    }
+    let packet_type = decode_packet_type(isaac, src)?;
+    match split_payload(packet_type, src)? {
+        Some(mut payload) => read_packet(packet_type, &mut payload),
+        None => Err(DecodeError::Truncated),
+    }
+}
+
+/// Splits the bytes received from a connection into packets, keeping any packet that has only
+/// been partially received until the rest of it arrives.
+#[derive(Debug, Default)]
+pub struct PacketDecoder {
+    buffer: BytesMut,
+    packet_type: Option<PacketType>,
+}
+
+impl PacketDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues bytes received from the connection to be decoded.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Decodes the next packet, or returns `None` if it has not been received in full.
+    ///
+    /// The ISAAC generator is only advanced once per packet, when its opcode is first read, so
+    /// the decoder can be called again with the same generator once more bytes have arrived.
+    ///
+    /// When the opcode is not recognised the rest of the stream cannot be split into packets, so
+    /// every queued byte is discarded.
+    pub fn decode(
+        &mut self,
+        isaac: Option<&mut IsaacRng>,
+    ) -> Result<Option<PacketEvent>, DecodeError> {
+        let packet_type = match self.packet_type {
+            Some(packet_type) => packet_type,
+            None if self.buffer.is_empty() => return Ok(None),
+            None => match decode_packet_type(isaac, &mut self.buffer) {
+                Ok(packet_type) => *self.packet_type.insert(packet_type),
+                Err(cause) => {
+                    self.buffer.clear();
+                    return Err(cause);
+                }
+            },
+        };
+
+        let mut payload = match split_payload(packet_type, &mut self.buffer) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => return Ok(None),
+            Err(cause) => {
+                self.clear();
+                return Err(cause);
+            }
+        };
+        self.packet_type = None;
+        read_packet(packet_type, &mut payload).map(Some)
+    }
+
+    /// The number of bytes received that have not yet been decoded.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.packet_type = None;
+    }
+}
+
+/// Reads the type of the next packet. The opcode of gameplay packets is consumed, while the
+/// opcode of handshake packets is left in place as it is read by the packet itself.
+fn decode_packet_type(
+    isaac: Option<&mut IsaacRng>,
+    src: &mut BytesMut,
+) -> Result<PacketType, DecodeError> {
     let packet_id = match isaac {
         Some(isaac) => {
             let decoded_id = src.get_u8().wrapping_sub(isaac.gen::<u8>());
@@ -65,27 +150,48 @@ pub fn decode_packet(
         None => PacketId::new(src[0], PacketDirection::Serverbound, PacketStage::Handshake),
     };
 
-    let packet_type = match PacketType::get_from_id(packet_id) {
-        Some(packet_type) => packet_type,
-        None => anyhow::bail!("Unknown packet"),
-    };
+    PacketType::get_from_id(packet_id).ok_or(DecodeError::UnknownOpcode(packet_id))
+}
 
-    log::info!("Decoding a {:?}", packet_type);
-    let mut read_buffer = match packet_type.packet_length() {
-        Some(PacketLength::VariableByte) => {
-            let split_index = src.get_u8() as usize;
-            src.split_to(split_index)
-        }
-        Some(PacketLength::VariableShort) => {
-            let split_index = src.get_u16() as usize;
-            src.split_to(split_index)
+/// Splits the payload of a packet from `src`, or returns `None` without consuming anything if
+/// the payload has not been received in full.
+fn split_payload(
+    packet_type: PacketType,
+    src: &mut BytesMut,
+) -> Result<Option<BytesMut>, DecodeError> {
+    let (header, length) = match (packet_type, packet_type.packet_length()) {
+        (_, Some(PacketLength::Fixed(length))) => (0, length),
+        (_, Some(PacketLength::VariableByte)) if src.is_empty() => return Ok(None),
+        (_, Some(PacketLength::VariableByte)) => (1, src[0] as usize),
+        (_, Some(PacketLength::VariableShort)) if src.len() < 2 => return Ok(None),
+        (_, Some(PacketLength::VariableShort)) => {
+            (2, u16::from_be_bytes([src[0], src[1]]) as usize)
         }
-        Some(PacketLength::Fixed(expected_len)) => src.split_to(expected_len),
-        None => src.split_to(src.len()),
+        // The handshake packets begin with their opcode, followed by the length of the rest of
+        // the login packet.
+        (PacketType::HandshakeHello, None) => (0, 2),
+        (PacketType::HandshakeAttemptConnect, None) if src.len() < 2 => return Ok(None),
+        (PacketType::HandshakeAttemptConnect, None) => (0, 2 + src[1] as usize),
+        (_, None) => return Err(DecodeError::UnsupportedDirection(packet_type)),
     };
 
-    let mut packet = packet_type.create().expect("packet creation");
-    packet.try_read(&mut read_buffer).map(|_| packet)
+    if src.len() < header + length {
+        return Ok(None);
+    }
+    src.advance(header);
+    Ok(Some(src.split_to(length)))
+}
+
+/// Reads a packet from its payload. Packets without a factory are only ever sent by the server.
+fn read_packet(
+    packet_type: PacketType,
+    payload: &mut BytesMut,
+) -> Result<PacketEvent, DecodeError> {
+    log::info!("Decoding a {:?}", packet_type);
+    let mut packet = packet_type
+        .create()
+        .map_err(|_| DecodeError::UnsupportedDirection(packet_type))?;
+    packet.try_read(payload).map(|_| packet)
 }
 
 #[cfg(test)]
@@ -137,4 +243,148 @@ mod tests {
         println!("{:02X}", buf);
         println!("{:?}", buf);
     }
+
+    fn walk(isaac: &mut IsaacRng) -> BytesMut {
+        let mut buf = BytesMut::new();
+        buf.put_u8(164u8.wrapping_add(isaac.gen::<u8>()));
+        buf.put_u8(7);
+        buf.put_u16_le(3200 + 128);
+        buf.put_slice(&[1, 1]);
+        buf.put_u16_le(3200);
+        buf.put_u8(0);
+        buf
+    }
+
+    #[test]
+    fn decode_fragmented_packets() {
+        let mut encoder = IsaacRng::seed_from_u64(0);
+        let mut decoder = IsaacRng::seed_from_u64(0);
+
+        let mut stream = walk(&mut encoder);
+        stream.extend_from_slice(&walk(&mut encoder));
+
+        let mut packets = PacketDecoder::new();
+        let mut decoded = 0;
+        for byte in stream.iter() {
+            packets.extend(&[*byte]);
+            while let Some(packet) = packets.decode(Some(&mut decoder)).expect("packet") {
+                assert_eq!(packet.get_type(), PacketType::Walk);
+                decoded += 1;
+            }
+        }
+        assert_eq!(decoded, 2);
+        assert_eq!(packets.buffered(), 0);
+        assert_eq!(
+            decoder.gen::<u8>(),
+            encoder.gen::<u8>(),
+            "ISAAC should only advance once per packet"
+        );
+    }
+
+    #[test]
+    fn decode_handshake() {
+        let mut packets = PacketDecoder::new();
+        packets.extend(&[14]);
+        assert!(packets.decode(None).expect("hello").is_none());
+        packets.extend(&[3, 16]);
+        let hello = packets.decode(None).expect("hello").expect("complete");
+        assert_eq!(hello.get_type(), PacketType::HandshakeHello);
+        assert!(packets.decode(None).expect("connect").is_none());
+        assert_eq!(packets.buffered(), 1);
+    }
+
+    #[test]
+    fn decode_truncated_packet() {
+        let mut isaac = IsaacRng::seed_from_u64(0);
+        let mut buf = walk(&mut isaac);
+        buf.truncate(4);
+        let mut isaac = IsaacRng::seed_from_u64(0);
+        assert!(decode_packet(Some(&mut isaac), &mut buf).is_err());
+        assert!(decode_packet(None, &mut BytesMut::new()).is_err());
+
+        let mut packets = PacketDecoder::new();
+        packets.extend(&[255, 1, 2]);
+        assert!(packets.decode(None).is_err());
+        assert_eq!(
+            packets.buffered(),
+            0,
+            "an unknown opcode discards the stream"
+        );
+    }
+
+    /// Payloads that have crashed the decoder before, following their opcode.
+    const REGRESSIONS: [(u8, &[u8]); 1] = [
+        // A walk starting at x = 32767 with a step east.
+        (164, &[7, 0x7F, 0x7F, 1, 0, 0x80, 0x0C, 0]),
+    ];
+
+    #[test]
+    fn decode_walk_past_map_edge() {
+        let mut encoder = IsaacRng::seed_from_u64(0);
+        let mut decoder = IsaacRng::seed_from_u64(0);
+        let (opcode, payload) = REGRESSIONS[0];
+        let mut src = BytesMut::new();
+        src.put_u8(opcode.wrapping_add(encoder.gen::<u8>()));
+        src.extend_from_slice(payload);
+        match decode_packet(Some(&mut decoder), &mut src) {
+            Err(DecodeError::PathOutOfBounds) => {}
+            _ => panic!("walk path overflows the map"),
+        }
+    }
+
+    /// Random bytes following each opcode, with lengths around the size of most packets, after
+    /// the known regressions.
+    fn fuzz_payloads(rng: &mut IsaacRng) -> impl Iterator<Item = (u8, BytesMut)> + '_ {
+        let regressions = REGRESSIONS
+            .iter()
+            .map(|(opcode, payload)| (*opcode, BytesMut::from(*payload)));
+        let random = (0..=255u8).cycle().take(256 * 16).map(move |opcode| {
+            let len = rng.gen_range(0, 64);
+            let payload = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<u8>>();
+            (opcode, BytesMut::from(&payload[..]))
+        });
+        regressions.chain(random)
+    }
+
+    #[test]
+    fn fuzz_decode_packet() {
+        let mut rng = IsaacRng::seed_from_u64(317);
+        let mut encoder = IsaacRng::seed_from_u64(0);
+        let mut decoder = IsaacRng::seed_from_u64(0);
+        for (opcode, payload) in fuzz_payloads(&mut rng) {
+            let mut src = BytesMut::new();
+            src.put_u8(opcode.wrapping_add(encoder.gen::<u8>()));
+            src.extend_from_slice(&payload);
+            let _ = decode_packet(Some(&mut decoder), &mut src);
+
+            let mut src = BytesMut::new();
+            src.put_u8(opcode);
+            src.extend_from_slice(&payload);
+            let _ = decode_packet(None, &mut src);
+        }
+    }
+
+    #[test]
+    fn fuzz_packet_decoder() {
+        let mut rng = IsaacRng::seed_from_u64(377);
+        let mut encoder = IsaacRng::seed_from_u64(0);
+        let mut decoder = IsaacRng::seed_from_u64(0);
+        let mut packets = PacketDecoder::new();
+        let stream = fuzz_payloads(&mut rng)
+            .flat_map(|(opcode, payload)| {
+                let mut chunk = vec![opcode.wrapping_add(encoder.gen::<u8>())];
+                chunk.extend_from_slice(&payload);
+                chunk
+            })
+            .collect::<Vec<u8>>();
+
+        let mut chunk_rng = IsaacRng::seed_from_u64(377);
+        let mut remaining = &stream[..];
+        while !remaining.is_empty() {
+            let (chunk, rest) = remaining.split_at(chunk_rng.gen_range(1, 32).min(remaining.len()));
+            remaining = rest;
+            packets.extend(chunk);
+            while let Ok(Some(_)) = packets.decode(Some(&mut decoder)) {}
+        }
+    }
 }
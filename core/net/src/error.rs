@@ -0,0 +1,31 @@
+use mithril_buf::EndOfBuffer;
+use thiserror::Error;
+
+use crate::{PacketId, PacketType};
+
+/// Why bytes sent by the client could not be decoded into a packet.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("packet ended unexpectedly")]
+    Truncated,
+    #[error("expected magic byte {expected} but found {found}")]
+    BadMagic { expected: u8, found: u8 },
+    #[error("{ty:?} cannot be {len} bytes long")]
+    BadLength { ty: PacketType, len: usize },
+    #[error("unknown opcode {0:?}")]
+    UnknownOpcode(PacketId),
+    #[error("{0:?} cannot be decoded by the server")]
+    UnsupportedDirection(PacketType),
+    #[error("{0} is not a valid base37 name")]
+    InvalidName(u64),
+    #[error("{packet} has no action with index {index}")]
+    InvalidActionIndex { packet: &'static str, index: usize },
+    #[error("walk path does not fit within the map")]
+    PathOutOfBounds,
+}
+
+impl From<EndOfBuffer> for DecodeError {
+    fn from(_: EndOfBuffer) -> Self {
+        DecodeError::Truncated
+    }
+}
@@ -4,9 +4,13 @@ extern crate mithril_codegen;
 pub use packet::{Packet, PacketDirection, PacketId, PacketLength, PacketStage, PacketType};
 
 mod codec;
+mod error;
 #[cfg(feature = "jaggrab")]
 pub mod jaggrab;
 mod packet;
+mod rsa;
 
 pub mod packets;
-pub use codec::{decode_packet, encode_packet};
+pub use codec::{decode_packet, encode_packet, PacketDecoder};
+pub use error::DecodeError;
+pub use rsa::RsaKey;
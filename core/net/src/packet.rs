@@ -1,4 +1,5 @@
 use crate::packets::PacketEvent;
+use crate::DecodeError;
 use ahash::AHashMap;
 use bytes::BytesMut;
 use once_cell::sync::Lazy;
@@ -433,14 +434,34 @@ static PACKET_ID_MAP: Lazy<AHashMap<PacketId, PacketType>> = Lazy::new(|| {
         PacketId::new(8, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::SetWidgetModel,
     );
+    packets.insert(
+        PacketId::new(24, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::FlashTabInterface,
+    );
     packets.insert(
         PacketId::new(27, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::EnterAmount,
     );
+    packets.insert(
+        PacketId::new(34, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::UpdateSlottedItems,
+    );
+    packets.insert(
+        PacketId::new(36, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::ConfigByte,
+    );
     packets.insert(
         PacketId::new(44, PacketDirection::Serverbound, PacketStage::Gameplay),
         PacketType::AddTileItem,
     );
+    packets.insert(
+        PacketId::new(50, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::SendFriend,
+    );
+    packets.insert(
+        PacketId::new(53, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::UpdateItems,
+    );
     packets.insert(
         PacketId::new(60, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::GroupedRegionUpdate,
@@ -477,6 +498,14 @@ static PACKET_ID_MAP: Lazy<AHashMap<PacketId, PacketType>> = Lazy::new(|| {
         PacketId::new(84, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::UpdateTileItem,
     );
+    packets.insert(
+        PacketId::new(85, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::SetUpdatedRegion,
+    );
+    packets.insert(
+        PacketId::new(87, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::ConfigInt,
+    );
     packets.insert(
         PacketId::new(97, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::OpenInterface,
@@ -509,6 +538,10 @@ static PACKET_ID_MAP: Lazy<AHashMap<PacketId, PacketType>> = Lazy::new(|| {
         PacketId::new(134, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::UpdateSkill,
     );
+    packets.insert(
+        PacketId::new(142, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::OpenSidebar,
+    );
     packets.insert(
         PacketId::new(151, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::SendObject,
@@ -529,6 +562,10 @@ static PACKET_ID_MAP: Lazy<AHashMap<PacketId, PacketType>> = Lazy::new(|| {
         PacketId::new(185, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::SetWidgetPlayerModel,
     );
+    packets.insert(
+        PacketId::new(196, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::ForwardPrivateChat,
+    );
     packets.insert(
         PacketId::new(200, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::SetWidgetModelAnimation,
@@ -537,14 +574,30 @@ static PACKET_ID_MAP: Lazy<AHashMap<PacketId, PacketType>> = Lazy::new(|| {
         PacketId::new(206, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::PrivacyOption,
     );
+    packets.insert(
+        PacketId::new(208, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::OpenOverlay,
+    );
+    packets.insert(
+        PacketId::new(214, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::IgnoreList,
+    );
     packets.insert(
         PacketId::new(215, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::AddGlobalTileItem,
     );
+    packets.insert(
+        PacketId::new(218, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::OpenDialogueOverlay,
+    );
     packets.insert(
         PacketId::new(219, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::CloseInterface,
     );
+    packets.insert(
+        PacketId::new(221, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::FriendServerStatus,
+    );
     packets.insert(
         PacketId::new(240, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::UpdateWeight,
@@ -565,6 +618,10 @@ static PACKET_ID_MAP: Lazy<AHashMap<PacketId, PacketType>> = Lazy::new(|| {
         PacketId::new(253, PacketDirection::Clientbound, PacketStage::Gameplay),
         PacketType::ServerMessage,
     );
+    packets.insert(
+        PacketId::new(254, PacketDirection::Clientbound, PacketStage::Gameplay),
+        PacketType::PositionHintIcon,
+    );
     //endregion
 
     packets
@@ -575,6 +632,11 @@ static PACKET_TYPE_MAP: Lazy<AHashMap<PacketType, PacketId>> = Lazy::new(|| {
     for (key, value) in PACKET_ID_MAP.clone() {
         packets.insert(value, key);
     }
+    // Both hint icons are sent with the same opcode, which is mapped to PositionHintIcon above.
+    packets.insert(
+        PacketType::MobHintIcon,
+        PacketId::new(254, PacketDirection::Clientbound, PacketStage::Gameplay),
+    );
     packets
 });
 
@@ -653,6 +715,7 @@ impl PacketType {
             PacketType::PublicChat => Some(PacketLength::VariableByte),
             PacketType::FlaggedMouseEvent => Some(PacketLength::VariableByte),
             PacketType::Walk => Some(PacketLength::VariableByte),
+            PacketType::WalkWithAnticheat => Some(PacketLength::VariableByte),
             PacketType::Command => Some(PacketLength::VariableByte),
             PacketType::PrivateChat => Some(PacketLength::VariableByte),
             PacketType::ServerMessage => Some(PacketLength::VariableByte),
@@ -685,12 +748,14 @@ impl PacketFactory {
 }
 
 pub trait Packet: Send + Sync {
-    fn try_read(&mut self, _src: &mut BytesMut) -> anyhow::Result<()> {
-        unimplemented!()
+    /// Reads the packet from its payload. Packets only sent by the server cannot be read.
+    fn try_read(&mut self, _src: &mut BytesMut) -> Result<(), DecodeError> {
+        Err(DecodeError::UnsupportedDirection(self.get_type()))
     }
 
+    /// Writes the packet's payload. Packets only sent by the client cannot be written.
     fn try_write(&self, _dst: &mut BytesMut) -> anyhow::Result<()> {
-        unimplemented!()
+        anyhow::bail!("{:?} cannot be encoded by the server", self.get_type())
     }
 
     fn get_type(&self) -> PacketType;
@@ -16,7 +16,7 @@ pub use events::*;
 mod prelude {
     pub use bytes::{Buf, BufMut, BytesMut};
 
-    pub use crate::{Packet, PacketType};
+    pub use crate::{DecodeError, Packet, PacketType};
     pub use mithril_buf::*;
 }
 
@@ -111,9 +111,11 @@ pub(crate) static PACKET_FACTORIES: Lazy<AHashMap<PacketType, PacketFactory>> =
     default_factory!(factories, GameplayEvent, MagicOnPlayer);
     default_factory!(factories, GameplayEvent, ArrowKey);
     default_factory!(factories, GameplayEvent, EnteredAmount);
+    default_factory!(factories, GameplayEvent, SwitchItem);
     default_factory!(factories, GameplayEvent, ReportAbuse);
     default_factory!(factories, GameplayEvent, TakeTileItem);
     default_factory!(factories, GameplayEvent, MouseClicked);
+    default_factory!(factories, GameplayEvent, FlaggedMouseEvent);
     default_factory!(factories, GameplayEvent, PlayerDesign);
 
     item_option_factory!(factories, FirstItemOption, 0);
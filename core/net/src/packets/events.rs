@@ -1,5 +1,5 @@
 use super::*;
-use crate::Packet;
+use crate::{DecodeError, Packet};
 use bytes::BytesMut;
 use std::fmt::Debug;
 
@@ -34,7 +34,7 @@ pub enum GameplayEvent {
     DialogueContinue(DialogueContinue),
     SecondItemOption(ItemOption),
     ThirdItemAction(ItemAction),
-    //    FlaggedMouseEvent(FlaggedMouseEvent),
+    FlaggedMouseEvent(FlaggedMouseEvent),
     ItemOnItem(ItemOnItem),
     ItemOnNpc(ItemOnNpc),
     ThirdObjectAction(ObjectAction),
@@ -67,7 +67,7 @@ pub enum GameplayEvent {
     AddFriend(AddFriend),
     ItemOnObject(ItemOnObject),
     EnteredAmount(EnteredAmount),
-    //    SwitchItem(SwitchItem),
+    SwitchItem(SwitchItem),
     RemoveFriend(RemoveFriend),
     ReportAbuse(ReportAbuse),
     TakeTileItem(TakeTileItem),
@@ -80,20 +80,20 @@ pub enum GameplayEvent {
     // endregion
 
     // region Gameplay - Clientbound
-    //    ForwardPrivateChat(ForwardPrivateChat),
-    //    OpenOverlay(OpenOverlay),
+    ForwardPrivateChat(ForwardPrivateChat),
+    OpenOverlay(OpenOverlay),
     SetWidgetItemModel(SetWidgetItemModel),
     //    SendObject(SendObject),
     ServerMessage(ServerMessage),
     GroupedRegionUpdate(GroupedRegionUpdate),
     //    RemoveObject(RemoveObject),
-    //    SetUpdatedRegion(SetUpdatedRegion),
+    SetUpdatedRegion(SetUpdatedRegion),
     //    RemoveTileItem(RemoveTileItem),
     Logout(Logout),
     OpenInterface(OpenInterface),
-    //    SendFriend(SendFriend),
-    //    ConfigByte(ConfigByte),
-    //    ConfigInt(ConfigInt),
+    SendFriend(SendFriend),
+    ConfigByte(Config),
+    ConfigInt(Config),
     UpdateRunEnergy(UpdateRunEnergy),
     ClearRegion(ClearRegion),
     SetWidgetModel(SetWidgetModel),
@@ -104,30 +104,30 @@ pub enum GameplayEvent {
     DisplayTabInterface(DisplayTabInterface),
     CloseInterface(CloseInterface),
     SetWidgetPlayerModel(SetWidgetPlayerModel),
-    //    PositionHintIcon(PositionHintIcon),
+    PositionHintIcon(PositionHintIcon),
     RegionChange(RegionChange),
     EnterAmount(EnterAmount),
-    //    UpdateSlottedItems(UpdateSlottedItems),
+    UpdateSlottedItems(UpdateSlottedItems),
     SetWidgetText(SetWidgetText),
     //    UpdateTileItem(UpdateTileItem),
     IdAssignment(IdAssignment),
     OpenDialogueInterface(OpenDialogueInterface),
-    //    UpdateItems(UpdateItems),
-    //    IgnoreList(IgnoreList),
+    UpdateItems(UpdateItems),
+    IgnoreList(IgnoreList),
     SetWidgetNpcModel(SetWidgetNpcModel),
-    //    FriendServerStatus(FriendServerStatus),
+    FriendServerStatus(FriendServerStatus),
     //    AddTileItem(AddTileItem),
     DisplayCrossbones(DisplayCrossbones),
     PlayerSynchronization(PlayerSynchronization),
     SetWidgetModelAnimation(SetWidgetModelAnimation),
     OpenInterfaceSidebar(OpenInterfaceSidebar),
-    //    FlashTabInterface(FlashTabInterface),
+    FlashTabInterface(FlashTabInterface),
     UpdateSkill(UpdateSkill),
     UpdateWeight(UpdateWeight),
-    //    MobHintIcon(MobHintIcon),
+    MobHintIcon(MobHintIcon),
     SwitchTabInterface(SwitchTabInterface),
-    //    OpenDialogueOverlay(OpenDialogueOverlay),
-    //    OpenSidebar(OpenSidebar),
+    OpenDialogueOverlay(OpenDialogueOverlay),
+    OpenSidebar(OpenSidebar),
     // endregion
 }
 
@@ -164,6 +164,7 @@ macro_rules! save_my_sanity {
                 GameplayEvent::DialogueContinue(packet) => packet.$method($buf),
                 GameplayEvent::SecondItemOption(packet) => packet.$method($buf),
                 GameplayEvent::ThirdItemAction(packet) => packet.$method($buf),
+                GameplayEvent::FlaggedMouseEvent(packet) => packet.$method($buf),
                 GameplayEvent::ItemOnItem(packet) => packet.$method($buf),
                 GameplayEvent::ItemOnNpc(packet) => packet.$method($buf),
                 GameplayEvent::ThirdObjectAction(packet) => packet.$method($buf),
@@ -196,6 +197,7 @@ macro_rules! save_my_sanity {
                 GameplayEvent::AddFriend(packet) => packet.$method($buf),
                 GameplayEvent::ItemOnObject(packet) => packet.$method($buf),
                 GameplayEvent::EnteredAmount(packet) => packet.$method($buf),
+                GameplayEvent::SwitchItem(packet) => packet.$method($buf),
                 GameplayEvent::RemoveFriend(packet) => packet.$method($buf),
                 GameplayEvent::ReportAbuse(packet) => packet.$method($buf),
                 GameplayEvent::TakeTileItem(packet) => packet.$method($buf),
@@ -205,11 +207,17 @@ macro_rules! save_my_sanity {
                 GameplayEvent::WalkWithAnticheat(packet) => packet.$method($buf),
                 GameplayEvent::MagicOnPlayer(packet) => packet.$method($buf),
                 GameplayEvent::SecondObjectAction(packet) => packet.$method($buf),
+                GameplayEvent::ForwardPrivateChat(packet) => packet.$method($buf),
+                GameplayEvent::OpenOverlay(packet) => packet.$method($buf),
                 GameplayEvent::SetWidgetItemModel(packet) => packet.$method($buf),
                 GameplayEvent::ServerMessage(packet) => packet.$method($buf),
                 GameplayEvent::GroupedRegionUpdate(packet) => packet.$method($buf),
+                GameplayEvent::SetUpdatedRegion(packet) => packet.$method($buf),
                 GameplayEvent::Logout(packet) => packet.$method($buf),
                 GameplayEvent::OpenInterface(packet) => packet.$method($buf),
+                GameplayEvent::SendFriend(packet) => packet.$method($buf),
+                GameplayEvent::ConfigByte(packet) => packet.$method($buf),
+                GameplayEvent::ConfigInt(packet) => packet.$method($buf),
                 GameplayEvent::UpdateRunEnergy(packet) => packet.$method($buf),
                 GameplayEvent::ClearRegion(packet) => packet.$method($buf),
                 GameplayEvent::SetWidgetModel(packet) => packet.$method($buf),
@@ -219,19 +227,28 @@ macro_rules! save_my_sanity {
                 GameplayEvent::DisplayTabInterface(packet) => packet.$method($buf),
                 GameplayEvent::CloseInterface(packet) => packet.$method($buf),
                 GameplayEvent::SetWidgetPlayerModel(packet) => packet.$method($buf),
+                GameplayEvent::PositionHintIcon(packet) => packet.$method($buf),
                 GameplayEvent::RegionChange(packet) => packet.$method($buf),
                 GameplayEvent::EnterAmount(packet) => packet.$method($buf),
+                GameplayEvent::UpdateSlottedItems(packet) => packet.$method($buf),
                 GameplayEvent::SetWidgetText(packet) => packet.$method($buf),
                 GameplayEvent::IdAssignment(packet) => packet.$method($buf),
                 GameplayEvent::OpenDialogueInterface(packet) => packet.$method($buf),
+                GameplayEvent::UpdateItems(packet) => packet.$method($buf),
+                GameplayEvent::IgnoreList(packet) => packet.$method($buf),
                 GameplayEvent::SetWidgetNpcModel(packet) => packet.$method($buf),
+                GameplayEvent::FriendServerStatus(packet) => packet.$method($buf),
                 GameplayEvent::DisplayCrossbones(packet) => packet.$method($buf),
                 GameplayEvent::PlayerSynchronization(packet) => packet.$method($buf),
                 GameplayEvent::SetWidgetModelAnimation(packet) => packet.$method($buf),
                 GameplayEvent::OpenInterfaceSidebar(packet) => packet.$method($buf),
+                GameplayEvent::FlashTabInterface(packet) => packet.$method($buf),
                 GameplayEvent::UpdateSkill(packet) => packet.$method($buf),
                 GameplayEvent::UpdateWeight(packet) => packet.$method($buf),
+                GameplayEvent::MobHintIcon(packet) => packet.$method($buf),
                 GameplayEvent::SwitchTabInterface(packet) => packet.$method($buf),
+                GameplayEvent::OpenDialogueOverlay(packet) => packet.$method($buf),
+                GameplayEvent::OpenSidebar(packet) => packet.$method($buf),
             },
         }
     };
@@ -254,9 +271,8 @@ impl PacketEvent {
 }
 
 // TODO: Code generation pls.
-// TODO: Return an error in the appropriate method if the packet cannot be read/write.
 impl Packet for PacketEvent {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
         save_my_sanity!(self, try_read, src)
     }
 
@@ -284,6 +300,7 @@ impl Packet for PacketEvent {
                 GameplayEvent::DialogueContinue(packet) => packet.get_type(),
                 GameplayEvent::SecondItemOption(packet) => packet.get_type(),
                 GameplayEvent::ThirdItemAction(packet) => packet.get_type(),
+                GameplayEvent::FlaggedMouseEvent(packet) => packet.get_type(),
                 GameplayEvent::ItemOnItem(packet) => packet.get_type(),
                 GameplayEvent::ItemOnNpc(packet) => packet.get_type(),
                 GameplayEvent::ThirdObjectAction(packet) => packet.get_type(),
@@ -316,6 +333,7 @@ impl Packet for PacketEvent {
                 GameplayEvent::AddFriend(packet) => packet.get_type(),
                 GameplayEvent::ItemOnObject(packet) => packet.get_type(),
                 GameplayEvent::EnteredAmount(packet) => packet.get_type(),
+                GameplayEvent::SwitchItem(packet) => packet.get_type(),
                 GameplayEvent::RemoveFriend(packet) => packet.get_type(),
                 GameplayEvent::ReportAbuse(packet) => packet.get_type(),
                 GameplayEvent::TakeTileItem(packet) => packet.get_type(),
@@ -325,11 +343,17 @@ impl Packet for PacketEvent {
                 GameplayEvent::WalkWithAnticheat(packet) => packet.get_type(),
                 GameplayEvent::MagicOnPlayer(packet) => packet.get_type(),
                 GameplayEvent::SecondObjectAction(packet) => packet.get_type(),
+                GameplayEvent::ForwardPrivateChat(packet) => packet.get_type(),
+                GameplayEvent::OpenOverlay(packet) => packet.get_type(),
                 GameplayEvent::SetWidgetItemModel(packet) => packet.get_type(),
                 GameplayEvent::ServerMessage(packet) => packet.get_type(),
                 GameplayEvent::GroupedRegionUpdate(packet) => packet.get_type(),
+                GameplayEvent::SetUpdatedRegion(packet) => packet.get_type(),
                 GameplayEvent::Logout(packet) => packet.get_type(),
                 GameplayEvent::OpenInterface(packet) => packet.get_type(),
+                GameplayEvent::SendFriend(packet) => packet.get_type(),
+                GameplayEvent::ConfigByte(packet) => packet.get_type(),
+                GameplayEvent::ConfigInt(packet) => packet.get_type(),
                 GameplayEvent::UpdateRunEnergy(packet) => packet.get_type(),
                 GameplayEvent::ClearRegion(packet) => packet.get_type(),
                 GameplayEvent::SetWidgetModel(packet) => packet.get_type(),
@@ -339,19 +363,28 @@ impl Packet for PacketEvent {
                 GameplayEvent::DisplayTabInterface(packet) => packet.get_type(),
                 GameplayEvent::CloseInterface(packet) => packet.get_type(),
                 GameplayEvent::SetWidgetPlayerModel(packet) => packet.get_type(),
+                GameplayEvent::PositionHintIcon(packet) => packet.get_type(),
                 GameplayEvent::RegionChange(packet) => packet.get_type(),
                 GameplayEvent::EnterAmount(packet) => packet.get_type(),
+                GameplayEvent::UpdateSlottedItems(packet) => packet.get_type(),
                 GameplayEvent::SetWidgetText(packet) => packet.get_type(),
                 GameplayEvent::IdAssignment(packet) => packet.get_type(),
                 GameplayEvent::OpenDialogueInterface(packet) => packet.get_type(),
+                GameplayEvent::UpdateItems(packet) => packet.get_type(),
+                GameplayEvent::IgnoreList(packet) => packet.get_type(),
                 GameplayEvent::SetWidgetNpcModel(packet) => packet.get_type(),
+                GameplayEvent::FriendServerStatus(packet) => packet.get_type(),
                 GameplayEvent::DisplayCrossbones(packet) => packet.get_type(),
                 GameplayEvent::PlayerSynchronization(packet) => packet.get_type(),
                 GameplayEvent::SetWidgetModelAnimation(packet) => packet.get_type(),
                 GameplayEvent::OpenInterfaceSidebar(packet) => packet.get_type(),
+                GameplayEvent::FlashTabInterface(packet) => packet.get_type(),
                 GameplayEvent::UpdateSkill(packet) => packet.get_type(),
                 GameplayEvent::UpdateWeight(packet) => packet.get_type(),
+                GameplayEvent::MobHintIcon(packet) => packet.get_type(),
                 GameplayEvent::SwitchTabInterface(packet) => packet.get_type(),
+                GameplayEvent::OpenDialogueOverlay(packet) => packet.get_type(),
+                GameplayEvent::OpenSidebar(packet) => packet.get_type(),
             },
         }
     }
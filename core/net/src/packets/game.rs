@@ -10,6 +10,12 @@ pub use sync::*;
 mod region;
 pub use region::*;
 
+mod inventory;
+pub use inventory::*;
+
+mod social;
+pub use social::*;
+
 #[derive(Debug, Default, Packet, EventFromPacket)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub struct KeepAlive;
@@ -29,7 +35,8 @@ pub struct PublicChat {
 }
 
 impl Packet for PublicChat {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(2)?;
         self.effects = src.get_u8t(Transform::Subtract);
         self.colour = src.get_u8t(Transform::Subtract);
 
@@ -54,8 +61,10 @@ pub struct PrivateChat {
 }
 
 impl Packet for PrivateChat {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
-        self.recipient = mithril_text::decode_base37(src.get_u64())?;
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        let recipient = src.get_checked(8, |src| src.get_u64())?;
+        self.recipient = mithril_text::decode_base37(recipient)
+            .map_err(|_| DecodeError::InvalidName(recipient))?;
         let len = src.remaining();
         let mut compressed = vec![0u8; len];
         src.copy_to_slice(&mut compressed[..]);
@@ -81,6 +90,24 @@ pub struct EnteredAmount {
     pub amount: u32,
 }
 
+/// Sent when an item is dragged onto another slot of the same interface.
+#[derive(Debug, Default, Packet, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct SwitchItem {
+    #[transform = "add"]
+    #[endian = "little"]
+    pub interface_id: u16,
+    /// Whether the item is inserted before the target slot, shifting the items after it, rather
+    /// than swapped with the item in the target slot, as in the bank.
+    #[transform = "negate"]
+    pub inserting: bool,
+    #[transform = "add"]
+    #[endian = "little"]
+    pub source_slot: u16,
+    #[endian = "little"]
+    pub target_slot: u16,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub struct ItemOption {
@@ -102,7 +129,8 @@ impl Default for ItemOption {
 }
 
 impl Packet for ItemOption {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(6)?;
         match self.option_index {
             0 => {
                 self.interface_id = src.get_u16t_le(Transform::Add);
@@ -129,7 +157,12 @@ impl Packet for ItemOption {
                 self.interface_id = src.get_u16();
                 self.slot = src.get_u16t(Transform::Add);
             }
-            _ => anyhow::bail!("invalid ItemOption index"),
+            index => {
+                return Err(DecodeError::InvalidActionIndex {
+                    packet: "ItemOption",
+                    index,
+                })
+            }
         }
         Ok(())
     }
@@ -178,7 +211,8 @@ impl Default for ItemAction {
 }
 
 impl Packet for ItemAction {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(6)?;
         match self.action_index {
             0 => {
                 self.interface_id = src.get_u16t(Transform::Add);
@@ -205,7 +239,12 @@ impl Packet for ItemAction {
                 self.interface_id = src.get_u16t(Transform::Add);
                 self.slot = src.get_u16_le();
             }
-            _ => anyhow::bail!("invalid ItemOption index"),
+            index => {
+                return Err(DecodeError::InvalidActionIndex {
+                    packet: "ItemAction",
+                    index,
+                })
+            }
         }
         Ok(())
     }
@@ -251,14 +290,20 @@ impl Default for NpcAction {
 }
 
 impl Packet for NpcAction {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(2)?;
         match self.action_index {
             0 => self.npc_id = src.get_u16_le(),
             1 => self.npc_id = src.get_u16t(Transform::Add),
             2 => self.npc_id = src.get_u16t_le(Transform::Add),
             3 => self.npc_id = src.get_u16(),
             4 => self.npc_id = src.get_u16_le(),
-            _ => anyhow::bail!("invalid NpcAction index"),
+            index => {
+                return Err(DecodeError::InvalidActionIndex {
+                    packet: "NpcAction",
+                    index: usize::from(index),
+                })
+            }
         }
         Ok(())
     }
@@ -303,14 +348,17 @@ impl Default for PlayerAction {
 }
 
 impl Packet for PlayerAction {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(2)?;
         match self.action_index {
             0 => self.player_id = src.get_u16(),
-            1 => self.player_id = src.get_u16_le(),
-            2 => self.player_id = src.get_u16_le(),
-            3 => self.player_id = src.get_u16_le(),
-            4 => self.player_id = src.get_u16_le(),
-            _ => anyhow::bail!("invalid NpcAction index"),
+            1..=4 => self.player_id = src.get_u16_le(),
+            index => {
+                return Err(DecodeError::InvalidActionIndex {
+                    packet: "PlayerAction",
+                    index: usize::from(index),
+                })
+            }
         }
         Ok(())
     }
@@ -359,7 +407,8 @@ impl Default for ObjectAction {
 }
 
 impl Packet for ObjectAction {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(6)?;
         match self.action_index {
             0 => {
                 self.x = src.get_u16t_le(Transform::Add);
@@ -376,7 +425,12 @@ impl Packet for ObjectAction {
                 self.y = src.get_u16();
                 self.object_id = src.get_u16t_le(Transform::Add);
             }
-            _ => anyhow::bail!("invalid ObjectAction index"),
+            index => {
+                return Err(DecodeError::InvalidActionIndex {
+                    packet: "ObjectAction",
+                    index: usize::from(index),
+                })
+            }
         }
         Ok(())
     }
@@ -553,7 +607,7 @@ pub struct ReportAbuse {
 pub struct SpamPacket(pub PacketLength);
 
 impl Packet for SpamPacket {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
         if !src.is_empty() {
             src.advance(src.len());
         }
@@ -586,8 +640,8 @@ pub struct MouseClicked {
 }
 
 impl Packet for MouseClicked {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
-        let value = src.get_u32();
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        let value = src.get_checked(4, |src| src.get_u32())?;
         self.delay = (value >> 20) as u64 * 50;
         self.right_click = (value >> 19 & 0x1) == 1;
 
@@ -602,6 +656,67 @@ impl Packet for MouseClicked {
     }
 }
 
+/// The value sent in place of a position when the cursor is outside of the client.
+const MOUSE_OUTSIDE: u32 = 0x7FFFF;
+
+/// A movement of the cursor, recorded after `delay` milliseconds without any movement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseMovement {
+    Relative { delay: u64, dx: i8, dy: i8 },
+    Absolute { delay: u64, x: u32, y: u32 },
+    Outside { delay: u64 },
+}
+
+/// The cursor movements recorded by clients that have been flagged for mouse recording.
+#[derive(Debug, Default, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct FlaggedMouseEvent {
+    pub movements: Vec<MouseMovement>,
+}
+
+impl Packet for FlaggedMouseEvent {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        self.movements.clear();
+        while src.has_remaining() {
+            // The highest bits of each movement determine how it is encoded. Small movements are
+            // sent as a 2 byte offset, and the rest as a 3 or 4 byte position depending on the
+            // length of the delay.
+            let movement = match src[0] >> 6 {
+                0 | 1 => {
+                    let value = src.get_checked(2, |src| src.get_u16())?;
+                    MouseMovement::Relative {
+                        delay: (value >> 12) as u64 * 50,
+                        dx: (value >> 6 & 0x3F) as i8 - 32,
+                        dy: (value & 0x3F) as i8 - 32,
+                    }
+                }
+                flags => {
+                    let value = if flags == 2 {
+                        src.get_checked(3, |src| src.get_uint(3) as u32 & 0x3FFFFF)?
+                    } else {
+                        src.get_checked(4, |src| src.get_u32() & 0x3FFF_FFFF)?
+                    };
+                    let delay = (value >> 19) as u64 * 50;
+                    match value & 0x7FFFF {
+                        MOUSE_OUTSIDE => MouseMovement::Outside { delay },
+                        coordinates => MouseMovement::Absolute {
+                            delay,
+                            x: coordinates % 765,
+                            y: coordinates / 765,
+                        },
+                    }
+                }
+            };
+            self.movements.push(movement);
+        }
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::FlaggedMouseEvent
+    }
+}
+
 #[derive(Debug, Default, EventFromPacket)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub struct PlayerDesign {
@@ -611,7 +726,8 @@ pub struct PlayerDesign {
 }
 
 impl Packet for PlayerDesign {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(13)?;
         self.style = [0u8; 7];
         self.colours = [0u8; 5];
         src.copy_to_slice(&mut self.style);
@@ -634,25 +750,38 @@ pub struct Walk {
 }
 
 impl Packet for Walk {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        // The client appends 14 bytes of anti-cheat data after the path when walking from the
+        // minimap.
         let length = match self.packet_type {
-            PacketType::Walk => src.remaining(),
-            PacketType::WalkWithAnticheat => src.remaining() - 14,
-            _ => unreachable!("packet type should always be Walk or WalkWithAnticheat"),
+            PacketType::Walk => Some(src.remaining()),
+            PacketType::WalkWithAnticheat => src.remaining().checked_sub(14),
+            packet_type => return Err(DecodeError::UnsupportedDirection(packet_type)),
+        };
+        let steps = match length {
+            Some(length) if length >= 5 => (length - 5) / 2,
+            _ => {
+                return Err(DecodeError::BadLength {
+                    ty: self.packet_type,
+                    len: src.remaining(),
+                })
+            }
         };
-        let steps = (length - 5) / 2;
-        let mut path = Vec::with_capacity(steps + 1);
         let x = src.get_u16t_le(Transform::Add) as i16;
-        for _ in 0..steps {
-            path.push((src.get_i8() as i16, src.get_i8() as i16));
-        }
+        let offsets = (0..steps)
+            .map(|_| (src.get_i8() as i16, src.get_i8() as i16))
+            .collect::<Vec<_>>();
         let y = src.get_i16_le();
         self.running = src.get_u8t(Transform::Negate) == 1;
-        self.path = path
-            .iter()
-            .map(|(a, b)| Position::new(a + x, b + y))
-            .collect::<Vec<_>>();
-        self.path.insert(0, Position::new(x, y));
+
+        let mut path = Vec::with_capacity(steps + 1);
+        path.push(Position::new(x, y));
+        for (dx, dy) in offsets {
+            let step_x = x.checked_add(dx).ok_or(DecodeError::PathOutOfBounds)?;
+            let step_y = y.checked_add(dy).ok_or(DecodeError::PathOutOfBounds)?;
+            path.push(Position::new(step_x, step_y));
+        }
+        self.path = path;
         Ok(())
     }
 
@@ -804,6 +933,35 @@ pub struct OpenInterfaceSidebar {
     pub sidebar_id: u16,
 }
 
+#[derive(Debug, Packet, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct OpenSidebar {
+    #[endian = "little"]
+    pub interface_id: u16,
+}
+
+#[derive(Debug, Packet, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct OpenOverlay {
+    #[endian = "little"]
+    pub interface_id: u16,
+}
+
+#[derive(Debug, Packet, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct OpenDialogueOverlay {
+    #[transform = "add"]
+    #[endian = "little"]
+    pub interface_id: u16,
+}
+
+#[derive(Debug, Packet, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct FlashTabInterface {
+    #[transform = "add"]
+    pub tab_id: u8,
+}
+
 #[derive(Debug, Packet, EventFromPacket)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub struct IdAssignment {
@@ -820,12 +978,34 @@ pub struct ServerMessage {
     pub message: String,
 }
 
+#[derive(Debug)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub enum Config {
     Byte(u16, u8),
     Int(u16, u32),
 }
 
+impl Config {
+    /// Creates the smallest packet that can set the config to `value`. The client reads the
+    /// value of a `Byte` as signed, so only values below 128 can be sent as a byte.
+    pub fn new(id: u16, value: u32) -> Self {
+        if value < 128 {
+            Config::Byte(id, value as u8)
+        } else {
+            Config::Int(id, value)
+        }
+    }
+}
+
+impl From<Config> for GameplayEvent {
+    fn from(packet: Config) -> Self {
+        match packet {
+            Config::Byte(_, _) => GameplayEvent::ConfigByte(packet),
+            Config::Int(_, _) => GameplayEvent::ConfigInt(packet),
+        }
+    }
+}
+
 impl Packet for Config {
     fn try_write(&self, src: &mut BytesMut) -> anyhow::Result<()> {
         match self {
@@ -887,6 +1067,93 @@ impl ClearRegion {
     }
 }
 
+/// Sets the region that the object and tile item packets that follow are positioned within.
+#[derive(Packet, Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct SetUpdatedRegion {
+    #[transform = "negate"]
+    local_y: u8,
+    #[transform = "negate"]
+    local_x: u8,
+}
+
+impl SetUpdatedRegion {
+    pub fn new(player: Position, region: Region) -> Self {
+        let local_x = ((region.x - (player.get_x() / 8 - 6)) * 8) as u8;
+        let local_y = ((region.y - (player.get_y() / 8 - 6)) * 8) as u8;
+
+        SetUpdatedRegion { local_y, local_x }
+    }
+}
+
+/// Where the hint arrow is drawn on the tile it points at.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub enum HintIconPlacement {
+    Centre = 2,
+    West = 3,
+    East = 4,
+    South = 5,
+    North = 6,
+}
+
+/// Points a hint arrow at a tile. Shares its opcode with [`MobHintIcon`].
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct PositionHintIcon {
+    pub placement: HintIconPlacement,
+    pub position: Position,
+    /// How far above the tile the arrow is drawn.
+    pub height: u8,
+}
+
+impl Packet for PositionHintIcon {
+    fn try_write(&self, src: &mut BytesMut) -> anyhow::Result<()> {
+        src.put_u8(self.placement as u8);
+        src.put_u16(self.position.get_x() as u16);
+        src.put_u16(self.position.get_y() as u16);
+        src.put_u8(self.height);
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::PositionHintIcon
+    }
+}
+
+/// Points a hint arrow at the NPC or player with the given index. Shares its opcode with
+/// [`PositionHintIcon`].
+#[derive(Debug)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub enum MobHintIcon {
+    Npc(u16),
+    Player(u16),
+}
+
+impl From<MobHintIcon> for GameplayEvent {
+    fn from(packet: MobHintIcon) -> Self {
+        GameplayEvent::MobHintIcon(packet)
+    }
+}
+
+impl Packet for MobHintIcon {
+    fn try_write(&self, src: &mut BytesMut) -> anyhow::Result<()> {
+        let (kind, index) = match self {
+            MobHintIcon::Npc(index) => (1, *index),
+            MobHintIcon::Player(index) => (10, *index),
+        };
+        src.put_u8(kind);
+        src.put_u16(index);
+        // The packet is padded to the length of a PositionHintIcon.
+        src.put_slice(&[0; 3]);
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::MobHintIcon
+    }
+}
+
 #[derive(Debug, EventFromPacket)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub struct NpcSynchronization;
@@ -904,3 +1171,114 @@ impl Packet for NpcSynchronization {
         PacketType::NpcSynchronization
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(packet: impl Packet) -> BytesMut {
+        let mut buf = BytesMut::new();
+        packet.try_write(&mut buf).expect("Write failed?");
+        buf
+    }
+
+    #[test]
+    fn test_switch_item() {
+        let mut src = BytesMut::from(&[0x0E, 0x0C, 0xFF, 0x84, 0x00, 0x09, 0x00][..]);
+        let mut packet = SwitchItem::default();
+        packet.try_read(&mut src).expect("Read failed?");
+        assert_eq!(packet.interface_id, 3214);
+        assert!(packet.inserting);
+        assert_eq!(packet.source_slot, 4);
+        assert_eq!(packet.target_slot, 9);
+    }
+
+    #[test]
+    fn test_flagged_mouse_event() {
+        let mut src = BytesMut::from(&[0x18, 0xDE, 0x90, 0x95, 0xCE, 0xC3, 0x27, 0xFF, 0xFF][..]);
+        let mut packet = FlaggedMouseEvent::default();
+        packet.try_read(&mut src).expect("Read failed?");
+        assert_eq!(
+            packet.movements,
+            vec![
+                MouseMovement::Relative {
+                    delay: 50,
+                    dx: 3,
+                    dy: -2
+                },
+                MouseMovement::Absolute {
+                    delay: 100,
+                    x: 100,
+                    y: 50
+                },
+                MouseMovement::Outside { delay: 5000 },
+            ]
+        );
+
+        let mut src = BytesMut::from(&[0x90, 0x95][..]);
+        assert_eq!(packet.try_read(&mut src), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_invalid_action_index() {
+        let mut src = BytesMut::from(&[0x00; 6][..]);
+        let mut packet = ObjectAction {
+            action_index: 3,
+            ..ObjectAction::default()
+        };
+        assert_eq!(
+            packet.try_read(&mut src),
+            Err(DecodeError::InvalidActionIndex {
+                packet: "ObjectAction",
+                index: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_interface_packets() {
+        let open_sidebar = write(OpenSidebar { interface_id: 3213 });
+        assert_eq!(&open_sidebar[..], &[0x8D, 0x0C][..]);
+        let open_overlay = write(OpenOverlay { interface_id: 3213 });
+        assert_eq!(&open_overlay[..], &[0x8D, 0x0C][..]);
+        let open_dialogue_overlay = write(OpenDialogueOverlay { interface_id: 3213 });
+        assert_eq!(&open_dialogue_overlay[..], &[0x0D, 0x0C][..]);
+        let flash_tab = write(FlashTabInterface { tab_id: 3 });
+        assert_eq!(&flash_tab[..], &[0x83][..]);
+    }
+
+    #[test]
+    fn test_config() {
+        let byte = write(Config::new(173, 1));
+        assert_eq!(&byte[..], &[0xAD, 0x00, 0x01][..]);
+        let int = write(Config::new(300, 12_345_678));
+        assert_eq!(&int[..], &[0x2C, 0x01, 0x61, 0x4E, 0x00, 0xBC][..]);
+        assert_eq!(Config::new(43, 128).get_type(), PacketType::ConfigInt);
+    }
+
+    #[test]
+    fn test_set_updated_region() {
+        let player = Position::new(3222, 3218);
+        let region = Region::from(&Position::new(3232, 3200));
+        let packet = write(SetUpdatedRegion::new(player, region));
+        assert_eq!(&packet[..], &[0xE0, 0xC0][..]);
+    }
+
+    #[test]
+    fn test_hint_icons() {
+        let position = write(PositionHintIcon {
+            placement: HintIconPlacement::Centre,
+            position: Position::new(3222, 3218),
+            height: 120,
+        });
+        assert_eq!(&position[..], &[0x02, 0x0C, 0x96, 0x0C, 0x92, 0x78][..]);
+        let npc = write(MobHintIcon::Npc(300));
+        assert_eq!(&npc[..], &[0x01, 0x01, 0x2C, 0x00, 0x00, 0x00][..]);
+        let player = write(MobHintIcon::Player(2));
+        assert_eq!(&player[..], &[0x0A, 0x00, 0x02, 0x00, 0x00, 0x00][..]);
+        assert_eq!(
+            PacketType::MobHintIcon.get_id(),
+            PacketType::PositionHintIcon.get_id()
+        );
+    }
+}
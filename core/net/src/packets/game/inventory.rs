@@ -0,0 +1,154 @@
+use crate::{Packet, PacketType};
+use bytes::buf::BufMut;
+use bytes::BytesMut;
+use mithril_buf::{GameBufMut, Transform};
+
+/// Amounts above this are sent as a marker byte followed by the full amount.
+const MAX_BYTE_AMOUNT: u32 = 254;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItemStack {
+    pub id: u16,
+    pub amount: u32,
+}
+
+/// The client reserves item ID 0 for empty slots, so every other item is sent as its ID plus one.
+fn id_and_amount(item: &Option<ItemStack>) -> (u16, u32) {
+    match item {
+        Some(item) => (item.id + 1, item.amount),
+        None => (0, 0),
+    }
+}
+
+/// Replaces every item in an interface's container, such as the inventory or bank.
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct UpdateItems {
+    pub interface_id: u16,
+    pub items: Vec<Option<ItemStack>>,
+}
+
+impl Packet for UpdateItems {
+    fn try_write(&self, buf: &mut BytesMut) -> anyhow::Result<()> {
+        buf.put_u16(self.interface_id);
+        buf.put_u16(self.items.len() as u16);
+        for item in &self.items {
+            let (id, amount) = id_and_amount(item);
+            if amount > MAX_BYTE_AMOUNT {
+                buf.put_u8(255);
+                buf.put_u32_inv_me(amount);
+            } else {
+                buf.put_u8(amount as u8);
+            }
+            buf.put_u16t_le(id, Transform::Add);
+        }
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::UpdateItems
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlottedItem {
+    pub slot: u16,
+    pub item: Option<ItemStack>,
+}
+
+/// Replaces the items in some of the slots of an interface's container, leaving the rest as they
+/// are.
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct UpdateSlottedItems {
+    pub interface_id: u16,
+    pub items: Vec<SlottedItem>,
+}
+
+impl Packet for UpdateSlottedItems {
+    fn try_write(&self, buf: &mut BytesMut) -> anyhow::Result<()> {
+        buf.put_u16(self.interface_id);
+        for slotted in &self.items {
+            let (id, amount) = id_and_amount(&slotted.item);
+            buf.put_smart(slotted.slot);
+            buf.put_u16(id);
+            if amount > MAX_BYTE_AMOUNT {
+                buf.put_u8(255);
+                buf.put_u32(amount);
+            } else {
+                buf.put_u8(amount as u8);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::UpdateSlottedItems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_items() {
+        const PACKET: [u8; 17] = [
+            0x0C, 0x8E, 0x00, 0x03, 0xFF, 0x00, 0x00, 0xE8, 0x03, 0x64, 0x03, 0x00, 0x80, 0x00,
+            0x01, 0xC8, 0x05,
+        ];
+        let mut buf = BytesMut::new();
+        UpdateItems {
+            interface_id: 3214,
+            items: vec![
+                Some(ItemStack {
+                    id: 995,
+                    amount: 1000,
+                }),
+                None,
+                Some(ItemStack {
+                    id: 1351,
+                    amount: 1,
+                }),
+            ],
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        assert_eq!(&buf[..], &PACKET[..]);
+    }
+
+    #[test]
+    fn test_update_slotted_items() {
+        const PACKET: [u8; 19] = [
+            0x06, 0x98, 0x03, 0x04, 0x8C, 0x01, 0x80, 0xC8, 0x00, 0x00, 0x00, 0x05, 0x03, 0xE4,
+            0xFF, 0x00, 0x01, 0x11, 0x70,
+        ];
+        let mut buf = BytesMut::new();
+        UpdateSlottedItems {
+            interface_id: 1688,
+            items: vec![
+                SlottedItem {
+                    slot: 3,
+                    item: Some(ItemStack {
+                        id: 1163,
+                        amount: 1,
+                    }),
+                },
+                SlottedItem {
+                    slot: 200,
+                    item: None,
+                },
+                SlottedItem {
+                    slot: 5,
+                    item: Some(ItemStack {
+                        id: 995,
+                        amount: 70000,
+                    }),
+                },
+            ],
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        assert_eq!(&buf[..], &PACKET[..]);
+    }
+}
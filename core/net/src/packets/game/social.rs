@@ -0,0 +1,179 @@
+use crate::{Packet, PacketType};
+use bytes::buf::BufMut;
+use bytes::BytesMut;
+use mithril_text::{compress, encode_base37};
+
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct ForwardPrivateChat {
+    pub sender: String,
+    /// The client ignores messages with an ID it has already seen, so every message forwarded to
+    /// a player must have a different ID.
+    pub message_id: u32,
+    pub privilege_level: u8,
+    pub message: String,
+}
+
+impl Packet for ForwardPrivateChat {
+    fn try_write(&self, buf: &mut BytesMut) -> anyhow::Result<()> {
+        buf.put_u64(encode_base37(&self.sender));
+        buf.put_u32(self.message_id);
+        buf.put_u8(self.privilege_level);
+        buf.put_slice(&compress(&self.message));
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::ForwardPrivateChat
+    }
+}
+
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct SendFriend {
+    pub username: String,
+    /// The world the friend is logged in to, or 0 if they are offline.
+    pub world: u8,
+}
+
+impl Packet for SendFriend {
+    fn try_write(&self, buf: &mut BytesMut) -> anyhow::Result<()> {
+        // The client subtracts 9 from the world before displaying it.
+        let world = match self.world {
+            0 => 0,
+            world => world
+                .checked_add(9)
+                .ok_or_else(|| anyhow::anyhow!("world {} cannot be sent to the client", world))?,
+        };
+        buf.put_u64(encode_base37(&self.username));
+        buf.put_u8(world);
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::SendFriend
+    }
+}
+
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct IgnoreList {
+    pub usernames: Vec<String>,
+}
+
+impl Packet for IgnoreList {
+    fn try_write(&self, buf: &mut BytesMut) -> anyhow::Result<()> {
+        for username in &self.usernames {
+            buf.put_u64(encode_base37(username));
+        }
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::IgnoreList
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub enum FriendServerState {
+    Loading = 0,
+    Connecting = 1,
+    Online = 2,
+}
+
+#[derive(Debug, EventFromPacket)]
+#[cfg_attr(feature = "test-equality", derive(PartialEq))]
+pub struct FriendServerStatus {
+    pub state: FriendServerState,
+}
+
+impl Packet for FriendServerStatus {
+    fn try_write(&self, buf: &mut BytesMut) -> anyhow::Result<()> {
+        buf.put_u8(self.state as u8);
+        Ok(())
+    }
+
+    fn get_type(&self) -> PacketType {
+        PacketType::FriendServerStatus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward_private_chat() {
+        const PACKET: [u8; 21] = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0xD2, 0x00, 0x00, 0x00, 0x01, 0x02, 0x61,
+            0xBB, 0x4E, 0xC0, 0xD1, 0x49, 0xBA, 0xE9,
+        ];
+        let mut buf = BytesMut::new();
+        ForwardPrivateChat {
+            sender: String::from("csh"),
+            message_id: 1,
+            privilege_level: 2,
+            message: String::from("hello, world!"),
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        assert_eq!(&buf[..], &PACKET[..]);
+    }
+
+    #[test]
+    fn test_send_friend() {
+        const PACKET: [u8; 18] = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0xD2, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x12, 0xD2, 0x00,
+        ];
+        let mut buf = BytesMut::new();
+        SendFriend {
+            username: String::from("csh"),
+            world: 1,
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        SendFriend {
+            username: String::from("csh"),
+            world: 0,
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        assert_eq!(&buf[..], &PACKET[..]);
+
+        let too_high = SendFriend {
+            username: String::from("csh"),
+            world: 247,
+        };
+        let mut buf = BytesMut::new();
+        assert!(too_high.try_write(&mut buf).is_err());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_ignore_list() {
+        const PACKET: [u8; 16] = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0xD2, 0x00, 0x00, 0x00, 0x00, 0x02, 0x29,
+            0xC8, 0x03,
+        ];
+        let mut buf = BytesMut::new();
+        IgnoreList {
+            usernames: vec![String::from("csh"), String::from("smrkn")],
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        assert_eq!(&buf[..], &PACKET[..]);
+    }
+
+    #[test]
+    fn test_friend_server_status() {
+        let mut buf = BytesMut::new();
+        FriendServerStatus {
+            state: FriendServerState::Online,
+        }
+        .try_write(&mut buf)
+        .expect("Write failed?");
+        assert_eq!(&buf[..], &[0x02][..]);
+    }
+}
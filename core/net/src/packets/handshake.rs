@@ -1,6 +1,19 @@
 use super::prelude::*;
+use bytes::Bytes;
 use mithril_codegen::EventFromPacket;
 
+use crate::RsaKey;
+
+/// The first byte of the first packet sent by the client.
+const HELLO_MAGIC: u8 = 14;
+
+/// The first byte of every login block.
+const LOGIN_BLOCK_MAGIC: u8 = 10;
+
+/// The length of the login packet up to the login block, from the client version to the length
+/// of the block.
+const LOGIN_HEADER_LENGTH: usize = 41;
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
@@ -36,8 +49,15 @@ pub struct HandshakeHello {
 }
 
 impl Packet for HandshakeHello {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
-        assert_eq!(14, src.get_u8(), "invalid packet");
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(2)?;
+        let magic = src.get_u8();
+        if magic != HELLO_MAGIC {
+            return Err(DecodeError::BadMagic {
+                expected: HELLO_MAGIC,
+                found: magic,
+            });
+        }
         self.name_hash = src.get_u8();
         Ok(())
     }
@@ -47,6 +67,9 @@ impl Packet for HandshakeHello {
     }
 }
 
+/// The client's login details. The ISAAC keys and credentials are sent in a separate login
+/// block, which is left unread until [`read_login_block`](Self::read_login_block) is called
+/// because it may be encrypted.
 #[derive(Default, Debug, EventFromPacket)]
 #[cfg_attr(feature = "test-equality", derive(PartialEq))]
 pub struct HandshakeAttemptConnect {
@@ -60,26 +83,64 @@ pub struct HandshakeAttemptConnect {
     pub user_id: u32,
     pub username: String,
     pub password: String,
+    login_block: Bytes,
+}
+
+impl HandshakeAttemptConnect {
+    /// Reads the ISAAC keys and credentials from the login block, first decrypting it with `key`
+    /// if the server expects clients to encrypt their login.
+    ///
+    /// Without a key the block is read as plaintext, as sent by clients with RSA disabled. A
+    /// [`BadMagic`](DecodeError::BadMagic) error usually means the client encrypted the block
+    /// with a different key, or did not encrypt it at all.
+    pub fn read_login_block(&mut self, key: Option<&RsaKey>) -> Result<(), DecodeError> {
+        let mut block = match key {
+            Some(key) => key.decrypt(&self.login_block),
+            None => self.login_block.clone(),
+        };
+
+        block.ensure_remaining(21)?;
+        let magic = block.get_u8();
+        if magic != LOGIN_BLOCK_MAGIC {
+            return Err(DecodeError::BadMagic {
+                expected: LOGIN_BLOCK_MAGIC,
+                found: magic,
+            });
+        }
+        self.client_isaac_key = block.get_u64();
+        self.server_isaac_key = block.get_u64();
+        self.user_id = block.get_u32();
+        self.username = block.try_get_rs_string()?;
+        self.password = block.try_get_rs_string()?;
+        Ok(())
+    }
 }
 
 impl Packet for HandshakeAttemptConnect {
-    fn try_read(&mut self, src: &mut BytesMut) -> anyhow::Result<()> {
+    fn try_read(&mut self, src: &mut BytesMut) -> Result<(), DecodeError> {
+        src.ensure_remaining(2)?;
         let connection_type = src.get_u8();
-        let login_length = src.get_u8();
-        assert!(src.remaining() >= login_length as usize);
+        let login_length = src.get_u8() as usize;
+        if login_length <= LOGIN_HEADER_LENGTH {
+            return Err(DecodeError::BadLength {
+                ty: self.get_type(),
+                len: login_length,
+            });
+        }
+        src.ensure_remaining(login_length)?;
         self.is_reconnect = connection_type == 18;
         self.version = 255 - src.get_u8();
         self.release = src.get_u16();
         self.low_memory = src.get_u8() == 1;
         self.crc = (0..9).map(|_| src.get_u32()).collect::<Vec<u32>>();
-        let remaining = src.get_u8();
-        assert_eq!(remaining, login_length - 41, "malformed login packet");
-        assert_eq!(10, src.get_u8());
-        self.client_isaac_key = src.get_u64();
-        self.server_isaac_key = src.get_u64();
-        self.user_id = src.get_u32();
-        self.username = src.get_rs_string();
-        self.password = src.get_rs_string();
+        let block_length = src.get_u8() as usize;
+        if block_length != login_length - LOGIN_HEADER_LENGTH {
+            return Err(DecodeError::BadLength {
+                ty: self.get_type(),
+                len: login_length,
+            });
+        }
+        self.login_block = src.split_to(block_length).freeze();
         Ok(())
     }
 
@@ -139,3 +200,87 @@ impl Packet for HandshakeConnectResponse {
         PacketType::HandshakeConnectResponse
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::tests::{encrypt, PKCS1_KEY};
+
+    fn login_block() -> Vec<u8> {
+        let mut block = vec![LOGIN_BLOCK_MAGIC];
+        block.extend_from_slice(&0x0102_0304_0506_0708u64.to_be_bytes());
+        block.extend_from_slice(&0x1112_1314_1516_1718u64.to_be_bytes());
+        block.extend_from_slice(&42u32.to_be_bytes());
+        block.extend_from_slice(b"mithril\npassword\n");
+        block
+    }
+
+    fn login_packet(block: &[u8]) -> BytesMut {
+        let mut src = BytesMut::new();
+        src.put_u8(16);
+        src.put_u8(41 + block.len() as u8);
+        src.put_u8(255 - 1);
+        src.put_u16(317);
+        src.put_u8(0);
+        src.put_slice(&[0; 36]);
+        src.put_u8(block.len() as u8);
+        src.put_slice(block);
+        src
+    }
+
+    #[test]
+    pub fn plaintext_login() {
+        let mut packet = HandshakeAttemptConnect::default();
+        packet
+            .try_read(&mut login_packet(&login_block()))
+            .expect("packet");
+        packet.read_login_block(None).expect("login block");
+        assert_eq!(packet.release, 317);
+        assert_eq!(packet.client_isaac_key, 0x0102_0304_0506_0708);
+        assert_eq!(packet.server_isaac_key, 0x1112_1314_1516_1718);
+        assert_eq!(packet.user_id, 42);
+        assert_eq!(packet.username, "mithril");
+        assert_eq!(packet.password, "password");
+    }
+
+    #[test]
+    pub fn encrypted_login() {
+        let key = RsaKey::from_pem(PKCS1_KEY).expect("key");
+        let block = encrypt(&key, &login_block());
+
+        let mut packet = HandshakeAttemptConnect::default();
+        packet.try_read(&mut login_packet(&block)).expect("packet");
+        packet.read_login_block(Some(&key)).expect("login block");
+        assert_eq!(packet.username, "mithril");
+        assert_eq!(packet.password, "password");
+
+        assert!(matches!(
+            packet.read_login_block(None),
+            Err(DecodeError::BadMagic { expected: 10, .. })
+        ));
+    }
+
+    #[test]
+    pub fn malformed_login() {
+        let mut packet = HandshakeAttemptConnect::default();
+        let mut src = login_packet(&login_block());
+        src[1] += 1;
+        assert_eq!(packet.try_read(&mut src), Err(DecodeError::Truncated));
+
+        let block = login_block();
+        let mut packet = HandshakeAttemptConnect::default();
+        packet
+            .try_read(&mut login_packet(&block[..block.len() - 1]))
+            .expect("packet");
+        assert_eq!(packet.read_login_block(None), Err(DecodeError::Truncated));
+
+        let mut src = BytesMut::from(&[15, 0][..]);
+        assert_eq!(
+            HandshakeHello::default().try_read(&mut src),
+            Err(DecodeError::BadMagic {
+                expected: 14,
+                found: 15
+            })
+        );
+    }
+}
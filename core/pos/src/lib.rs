@@ -16,6 +16,33 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Every direction an entity can take a step in.
+    pub const ALL: [Direction; 8] = [
+        Direction::NorthWest,
+        Direction::North,
+        Direction::NorthEast,
+        Direction::West,
+        Direction::East,
+        Direction::SouthWest,
+        Direction::South,
+        Direction::SouthEast,
+    ];
+
+    /// The change in x and y coordinates of a single step in this direction.
+    pub fn delta(self) -> (i16, i16) {
+        match self {
+            Self::None => (0, 0),
+            Self::NorthWest => (-1, 1),
+            Self::North => (0, 1),
+            Self::NorthEast => (1, 1),
+            Self::West => (-1, 0),
+            Self::East => (1, 0),
+            Self::SouthWest => (-1, -1),
+            Self::South => (0, -1),
+            Self::SouthEast => (1, -1),
+        }
+    }
+
     pub fn to_orientation(&self) -> anyhow::Result<u8> {
         match &self {
             Self::West | Self::NorthWest => Ok(0),
@@ -1,3 +1,4 @@
 pub extern crate mithril_fs as fs;
 pub extern crate mithril_net as net;
 pub extern crate mithril_pos as pos;
+pub extern crate mithril_text as text;
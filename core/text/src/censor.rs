@@ -0,0 +1,750 @@
+use anyhow::{anyhow, Result};
+
+/// Words that contain a bad word but are never censored.
+const EXCEPTIONS: [&str; 10] = [
+    "cook", "cook's", "cooks", "seeks", "sheet", "woop", "woops", "faq", "noob", "noobs",
+];
+
+/// A bad word, along with the characters that may surround it in an innocent word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BadWord {
+    word: Vec<char>,
+    /// Sorted pairs of the characters either side of the word which mean it is not censored,
+    /// encoded by `combination_code`.
+    combinations: Vec<[u8; 2]>,
+}
+
+/// A top level domain and how aggressively it is censored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TopLevelDomain {
+    ty: u8,
+    name: Vec<char>,
+}
+
+/// Censors chat messages the same way as the client, using the word lists stored in the wordenc
+/// archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Censor {
+    bad_words: Vec<BadWord>,
+    domains: Vec<Vec<char>>,
+    fragments: Vec<u16>,
+    top_level_domains: Vec<TopLevelDomain>,
+}
+
+impl Censor {
+    /// Decodes the contents of the `badenc.txt`, `domainenc.txt`, `fragmentsenc.txt` and
+    /// `tldlist.txt` entries of the wordenc archive.
+    pub fn decode(
+        badenc: &[u8],
+        domainenc: &[u8],
+        fragmentsenc: &[u8],
+        tldlist: &[u8],
+    ) -> Result<Self> {
+        let mut reader = Reader(badenc);
+        let bad_words = (0..reader.u32()?)
+            .map(|_| {
+                let word = reader.chars()?;
+                let combinations = (0..reader.u8()?)
+                    .map(|_| Ok([reader.u8()?, reader.u8()?]))
+                    .collect::<Result<_>>()?;
+                Ok(BadWord { word, combinations })
+            })
+            .collect::<Result<_>>()?;
+
+        let mut reader = Reader(domainenc);
+        let domains = (0..reader.u32()?)
+            .map(|_| reader.chars())
+            .collect::<Result<_>>()?;
+
+        let mut reader = Reader(fragmentsenc);
+        let fragments = (0..reader.u32()?)
+            .map(|_| reader.u16())
+            .collect::<Result<_>>()?;
+
+        let mut reader = Reader(tldlist);
+        let top_level_domains = (0..reader.u32()?)
+            .map(|_| {
+                Ok(TopLevelDomain {
+                    ty: reader.u8()?,
+                    name: reader.chars()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Censor {
+            bad_words,
+            domains,
+            fragments,
+            top_level_domains,
+        })
+    }
+
+    /// Replaces any bad words, domains, URLs and IP addresses in `message` with asterisks.
+    pub fn censor(&self, message: &str) -> String {
+        let original: Vec<char> = format_chars(message).trim().chars().collect();
+        let mut chars: Vec<char> = original.iter().map(char::to_ascii_lowercase).collect();
+        let lowered: String = chars.iter().collect();
+
+        self.censor_top_level_domains(&mut chars);
+        for _ in 0..2 {
+            for bad_word in self.bad_words.iter().rev() {
+                self.censor_word(&mut chars, &bad_word.word, Some(&bad_word.combinations));
+            }
+        }
+        self.censor_domains(&mut chars);
+        censor_ip_addresses(&mut chars);
+
+        for exception in EXCEPTIONS.iter() {
+            for (start, _) in lowered.match_indices(exception) {
+                let start = lowered[..start].chars().count();
+                for (offset, c) in exception.chars().enumerate() {
+                    chars[start + offset] = c;
+                }
+            }
+        }
+
+        for (censored, original) in chars.iter_mut().zip(original.iter()) {
+            if *censored != '*' && original.is_ascii_uppercase() {
+                *censored = *original;
+            }
+        }
+        format_case(&mut chars);
+        chars.iter().collect::<String>().trim().to_string()
+    }
+
+    /// Whether `message` contains anything that would be censored.
+    pub fn is_censored(&self, message: &str) -> bool {
+        self.censor(message).contains('*')
+    }
+
+    fn censor_word(&self, chars: &mut [char], word: &[char], combinations: Option<&[[u8; 2]]>) {
+        if word.len() > chars.len() {
+            return;
+        }
+        let mut start = 0;
+        while start <= chars.len() - word.len() {
+            let mut index = start;
+            let mut matched = 0;
+            let mut step = 1;
+            let mut fillers = 0;
+            let mut has_symbol = false;
+            let mut matched_digit = false;
+            let mut filler_digit = false;
+            while index < chars.len() && !(matched_digit && filler_digit) {
+                let c = chars[index];
+                let next = chars.get(index + 1).copied().unwrap_or('\0');
+                if matched < word.len() {
+                    let len = bad_word_char_len(word[matched], c, next);
+                    if len > 0 {
+                        if (len == 1 && is_digit(c))
+                            || (len == 2 && (is_digit(c) || is_digit(next)))
+                        {
+                            matched_digit = true;
+                        }
+                        index += len;
+                        matched += 1;
+                        continue;
+                    }
+                }
+                if matched == 0 {
+                    break;
+                }
+                let len = bad_word_char_len(word[matched - 1], c, next);
+                if len > 0 {
+                    index += len;
+                    if matched == 1 {
+                        step += 1;
+                    }
+                    continue;
+                }
+                if matched >= word.len() || !is_bad_word_filler(c) {
+                    break;
+                }
+                if is_symbol(c) && c != '\'' {
+                    has_symbol = true;
+                }
+                if is_digit(c) {
+                    filler_digit = true;
+                }
+                index += 1;
+                fillers += 1;
+                if fillers * 100 / (index - start) > 90 {
+                    break;
+                }
+            }
+
+            if matched >= word.len() && !(matched_digit && filler_digit) {
+                let mut censor = true;
+                if !has_symbol {
+                    let before = if start > 0 { chars[start - 1] } else { ' ' };
+                    let after = chars.get(index).copied().unwrap_or(' ');
+                    let pair = [combination_code(before), combination_code(after)];
+                    if let Some(combinations) = combinations {
+                        if combinations.binary_search(&pair).is_ok() {
+                            censor = false;
+                        }
+                    }
+                } else {
+                    let separated_before = start == 0 || is_separator(chars[start - 1]);
+                    let separated_after = index >= chars.len() || is_separator(chars[index]);
+                    if !separated_before || !separated_after {
+                        censor = self.has_unknown_fragment(chars, start, index, separated_before);
+                    }
+                }
+
+                if censor {
+                    let mut digits = 0;
+                    let mut letters = 0;
+                    let mut last_letter = None;
+                    for (position, c) in chars.iter().enumerate().take(index).skip(start) {
+                        if is_digit(*c) {
+                            digits += 1;
+                        } else if is_letter(*c) {
+                            letters += 1;
+                            last_letter = Some(position);
+                        }
+                    }
+                    if let Some(last_letter) = last_letter {
+                        digits -= (index - 1 - last_letter) as i32;
+                    }
+                    if digits <= letters {
+                        for c in chars.iter_mut().take(index).skip(start) {
+                            *c = '*';
+                        }
+                    } else {
+                        step = 1;
+                    }
+                }
+            }
+            start += step;
+        }
+    }
+
+    /// Whether the word between `start` and `end` is joined to text that is not a known
+    /// fragment, meaning the bad word was disguised by splitting it with symbols.
+    fn has_unknown_fragment(
+        &self,
+        chars: &[char],
+        start: usize,
+        end: usize,
+        separated_before: bool,
+    ) -> bool {
+        let from = if separated_before {
+            start
+        } else {
+            start.saturating_sub(2)
+        };
+        for position in from..end {
+            if is_separator(chars[position]) {
+                continue;
+            }
+            let fragment: Vec<char> = chars[position..]
+                .iter()
+                .take(3)
+                .take_while(|c| !is_separator(**c))
+                .copied()
+                .collect();
+            let joined_before = position > 0 && !is_separator(chars[position - 1]);
+            if fragment.is_empty() || (fragment.len() < 3 && joined_before) {
+                continue;
+            }
+            if !self.is_fragment(&fragment) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn is_fragment(&self, fragment: &[char]) -> bool {
+        if fragment.iter().all(|c| is_digit(*c)) {
+            return true;
+        }
+        match fragment_code(fragment) {
+            Some(code) => self.fragments.binary_search(&code).is_ok(),
+            None => false,
+        }
+    }
+
+    fn censor_domains(&self, chars: &mut [char]) {
+        let mut at_censored = chars.to_vec();
+        self.censor_word(&mut at_censored, &['(', 'a', ')'], None);
+        let mut dot_censored = chars.to_vec();
+        self.censor_word(&mut dot_censored, &['d', 'o', 't'], None);
+
+        for domain in self.domains.iter().rev() {
+            for (start, end) in find_domain_matches(chars, domain) {
+                let before = domain_prefix(chars, &at_censored, start, '@');
+                let after = domain_suffix(&dot_censored, end, chars, &['.', ','], 3);
+                if before > 2 || after > 2 {
+                    for c in chars.iter_mut().take(end).skip(start) {
+                        *c = '*';
+                    }
+                }
+            }
+        }
+    }
+
+    fn censor_top_level_domains(&self, chars: &mut [char]) {
+        let mut dot_censored = chars.to_vec();
+        self.censor_word(&mut dot_censored, &['d', 'o', 't'], None);
+        let mut slash_censored = chars.to_vec();
+        self.censor_word(&mut slash_censored, &['s', 'l', 'a', 's', 'h'], None);
+
+        for tld in self.top_level_domains.iter() {
+            for (start, end) in find_domain_matches(chars, &tld.name) {
+                let before = tld_prefix(chars, start, &dot_censored);
+                let after = domain_suffix(&slash_censored, end, chars, &['\\', '/'], 5);
+                let censor = match tld.ty {
+                    1 => before > 0 && after > 0,
+                    2 => (before > 2 && after > 0) || (before > 0 && after > 2),
+                    3 => before > 0 && after > 2,
+                    _ => false,
+                };
+                if !censor {
+                    continue;
+                }
+
+                let mut from = start;
+                let mut to = end - 1;
+                if before > 2 {
+                    if before == 4 {
+                        from = extend_back(&dot_censored, from, |c| c == '*');
+                    }
+                    from = extend_back(chars, from, |c| !is_symbol(c));
+                }
+                if after > 2 {
+                    if after == 4 {
+                        to = extend_forward(&slash_censored, to, |c| c == '*');
+                    }
+                    to = extend_forward(chars, to, |c| !is_symbol(c));
+                }
+                for c in chars.iter_mut().take(to + 1).skip(from) {
+                    *c = '*';
+                }
+            }
+        }
+    }
+}
+
+/// Finds each occurrence of a domain or TLD, allowing symbols between its characters, returning
+/// the start and end of each match.
+fn find_domain_matches(chars: &[char], domain: &[char]) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    if domain.len() > chars.len() {
+        return matches;
+    }
+    let mut start = 0;
+    while start <= chars.len() - domain.len() {
+        let mut index = start;
+        let mut matched = 0;
+        let mut step = 1;
+        while index < chars.len() {
+            let c = chars[index];
+            let next = chars.get(index + 1).copied().unwrap_or('\0');
+            if matched < domain.len() {
+                let len = domain_char_len(c, domain[matched], next);
+                if len > 0 {
+                    index += len;
+                    matched += 1;
+                    continue;
+                }
+            }
+            if matched == 0 {
+                break;
+            }
+            let len = domain_char_len(c, domain[matched - 1], next);
+            if len > 0 {
+                index += len;
+                if matched == 1 {
+                    step += 1;
+                }
+                continue;
+            }
+            if matched >= domain.len() || !is_symbol(c) {
+                break;
+            }
+            index += 1;
+        }
+        if matched >= domain.len() {
+            matches.push((start, index));
+        }
+        start += step;
+    }
+    matches
+}
+
+/// Classifies what precedes a domain: 0 for text, 1 for symbols, 2 for the start of the message,
+/// 3 for an `@` and 4 for a spelt out `(a)`.
+fn domain_prefix(chars: &[char], at_censored: &[char], start: usize, symbol: char) -> u8 {
+    if start == 0 {
+        return 2;
+    }
+    for c in chars[..start].iter().rev() {
+        if !is_symbol(*c) {
+            break;
+        }
+        if *c == symbol {
+            return 3;
+        }
+    }
+    let censored = at_censored[..start]
+        .iter()
+        .rev()
+        .take_while(|c| is_symbol(**c))
+        .filter(|c| **c == '*')
+        .count();
+    if censored >= 3 {
+        return 4;
+    }
+    if is_symbol(chars[start - 1]) {
+        1
+    } else {
+        0
+    }
+}
+
+fn tld_prefix(chars: &[char], start: usize, dot_censored: &[char]) -> u8 {
+    if start == 0 {
+        return 2;
+    }
+    for c in chars[..start].iter().rev() {
+        if !is_symbol(*c) {
+            break;
+        }
+        if *c == ',' || *c == '.' {
+            return 3;
+        }
+    }
+    domain_prefix(chars, dot_censored, start, '\0')
+}
+
+/// Classifies what follows a domain: 0 for text, 1 for symbols, 2 for the end of the message,
+/// 3 for one of `symbols` and 4 for at least `spelt_len` censored characters, such as a spelt
+/// out `dot`.
+fn domain_suffix(
+    censored: &[char],
+    end: usize,
+    chars: &[char],
+    symbols: &[char],
+    spelt_len: usize,
+) -> u8 {
+    if end == chars.len() {
+        return 2;
+    }
+    for c in chars[end..].iter() {
+        if !is_symbol(*c) {
+            break;
+        }
+        if symbols.contains(c) {
+            return 3;
+        }
+    }
+    let count = censored[end..]
+        .iter()
+        .take_while(|c| is_symbol(**c))
+        .filter(|c| **c == '*')
+        .count();
+    if count >= spelt_len {
+        return 4;
+    }
+    if is_symbol(chars[end]) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Moves `from` back over the first run of characters matching `predicate` before it.
+fn extend_back(chars: &[char], mut from: usize, predicate: impl Fn(char) -> bool) -> usize {
+    let mut found = false;
+    for position in (0..from).rev() {
+        if found {
+            if !predicate(chars[position]) {
+                break;
+            }
+            from = position;
+        } else if predicate(chars[position]) {
+            from = position;
+            found = true;
+        }
+    }
+    from
+}
+
+/// Moves `to` forward over the first run of characters matching `predicate` after it.
+fn extend_forward(chars: &[char], mut to: usize, predicate: impl Fn(char) -> bool) -> usize {
+    let mut found = false;
+    for (position, c) in chars.iter().enumerate().skip(to + 1) {
+        if found {
+            if !predicate(*c) {
+                break;
+            }
+            to = position;
+        } else if predicate(*c) {
+            to = position;
+            found = true;
+        }
+    }
+    to
+}
+
+/// Censors anything that looks like an IP address, which is four numbers no larger than 255.
+fn censor_ip_addresses(chars: &mut [char]) {
+    let mut end = 0;
+    let mut numbers = 0;
+    let mut address_start = 0;
+    while let Some(start) = chars[end..]
+        .iter()
+        .position(|c| is_digit(*c))
+        .map(|p| p + end)
+    {
+        if chars[end..start].iter().any(|c| c.is_ascii_lowercase()) {
+            numbers = 0;
+        }
+        if numbers == 0 {
+            address_start = start;
+        }
+        end = chars[start..]
+            .iter()
+            .position(|c| !is_digit(*c))
+            .map_or(chars.len(), |p| p + start);
+
+        // Long runs are rejected before they are folded, which would otherwise overflow.
+        let digits = &chars[start..end];
+        let value = || {
+            digits
+                .iter()
+                .fold(0u64, |value, c| value * 10 + u64::from(*c as u8 - b'0'))
+        };
+        if digits.len() > 8 || value() > 255 {
+            numbers = 0;
+        } else {
+            numbers += 1;
+        }
+        if numbers == 4 {
+            for c in chars.iter_mut().take(end).skip(address_start) {
+                *c = '*';
+            }
+            numbers = 0;
+        }
+    }
+}
+
+/// The number of characters of `c` and `next` that disguise the bad word character `pattern`,
+/// or 0 if they don't match.
+fn bad_word_char_len(pattern: char, c: char, next: char) -> usize {
+    if pattern == c {
+        return 1;
+    }
+    let pair = |pairs: &[(char, char)]| pairs.contains(&(c, next));
+    let matched = match pattern {
+        'a' if matches!(c, '4' | '@' | '^') => return 1,
+        'a' => pair(&[('/', '\\')]),
+        'b' if matches!(c, '6' | '8') => return 1,
+        'b' => pair(&[('1', '3'), ('i', '3')]),
+        'c' => return matches!(c, '(' | '<' | '{' | '[') as usize,
+        'd' => pair(&[('[', ')'), ('i', ')')]),
+        'e' => return matches!(c, '3' | '€') as usize,
+        'f' if c == 'p' && next == 'h' => return 2,
+        'f' => return (c == '£') as usize,
+        'g' => return matches!(c, '9' | '6' | 'q') as usize,
+        'h' => return (c == '#') as usize,
+        'i' => return matches!(c, 'y' | 'l' | 'j' | '1' | '!' | ':' | ';' | '|') as usize,
+        'l' => return matches!(c, '1' | '|' | 'i') as usize,
+        'o' if matches!(c, '0' | '*') => return 1,
+        'o' => pair(&[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')]),
+        's' => return matches!(c, '5' | 'z' | '$' | '2') as usize,
+        't' => return matches!(c, '7' | '+') as usize,
+        'u' if c == 'v' => return 1,
+        'u' | 'v' => pair(&[('\\', '/'), ('\\', '|'), ('|', '/')]),
+        'w' => pair(&[('v', 'v')]),
+        'x' => pair(&[(')', '('), ('}', '{'), (']', '['), ('>', '<')]),
+        '0' if matches!(c, 'o' | 'O') => return 1,
+        '0' => pair(&[('(', ')'), ('{', '}'), ('[', ']')]),
+        '1' => return (c == 'l') as usize,
+        ',' => return (c == '.') as usize,
+        '.' => return (c == ',') as usize,
+        '!' => return (c == 'i') as usize,
+        _ => false,
+    };
+    if matched {
+        2
+    } else {
+        0
+    }
+}
+
+/// The number of characters of `c` and `next` that disguise the domain character `pattern`, or
+/// 0 if they don't match.
+fn domain_char_len(c: char, pattern: char, next: char) -> usize {
+    match (pattern, c) {
+        _ if pattern == c => 1,
+        ('o', '0') => 1,
+        ('o', '(') if next == ')' => 2,
+        ('c', '(') | ('c', '<') | ('c', '[') => 1,
+        ('e', '€') | ('s', '$') | ('l', 'i') => 1,
+        _ => 0,
+    }
+}
+
+/// Encodes the character either side of a bad word, to be looked up in its combinations.
+fn combination_code(c: char) -> u8 {
+    match c {
+        'a'..='z' => c as u8 - b'a' + 1,
+        '\'' => 28,
+        '0'..='9' => c as u8 - b'0' + 29,
+        _ => 27,
+    }
+}
+
+/// Encodes a fragment of up to three characters, in reverse, to be looked up in the fragments.
+fn fragment_code(fragment: &[char]) -> Option<u16> {
+    let mut code: u32 = 0;
+    for c in fragment.iter().rev() {
+        code = code * 38
+            + match c {
+                'a'..='z' => u32::from(*c as u8 - b'a') + 1,
+                '\'' => 27,
+                '0'..='9' => u32::from(*c as u8 - b'0') + 28,
+                _ => return None,
+            };
+    }
+    Some(code as u16)
+}
+
+/// Replaces characters the client can't display with spaces and collapses repeated spaces.
+fn format_chars(message: &str) -> String {
+    let mut formatted = String::with_capacity(message.len());
+    for c in message.chars() {
+        let c = if matches!(c, ' '..='\u{7F}' | '\n' | '\t' | '£' | '€') {
+            c
+        } else {
+            ' '
+        };
+        if c != ' ' || !formatted.ends_with(' ') {
+            formatted.push(c);
+        }
+    }
+    formatted
+}
+
+/// Lowercases any capitals that follow a lowercase letter in the same word.
+fn format_case(chars: &mut [char]) {
+    let mut word_start = true;
+    for c in chars.iter_mut() {
+        if is_letter(*c) {
+            if word_start {
+                if c.is_ascii_lowercase() {
+                    word_start = false;
+                }
+            } else {
+                *c = c.to_ascii_lowercase();
+            }
+        } else {
+            word_start = true;
+        }
+    }
+}
+
+fn is_letter(c: char) -> bool {
+    c.is_ascii_alphabetic()
+}
+
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
+
+fn is_symbol(c: char) -> bool {
+    !is_letter(c) && !is_digit(c)
+}
+
+fn is_separator(c: char) -> bool {
+    is_symbol(c) && c != '\''
+}
+
+/// Characters that may be inserted between the letters of a bad word without preventing a match.
+fn is_bad_word_filler(c: char) -> bool {
+    !c.is_ascii_lowercase() || matches!(c, 'v' | 'x' | 'j' | 'q' | 'z')
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(anyhow!("wordenc entry ended unexpectedly"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a Latin-1 string prefixed by its length.
+    fn chars(&mut self) -> Result<Vec<char>> {
+        let len = self.u8()? as usize;
+        Ok(self.take(len)?.iter().map(|c| char::from(*c)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn censor() -> Censor {
+        #[rustfmt::skip]
+        let badenc = [
+            0, 0, 0, 1,
+            4, b'd', b'a', b'r', b'n',
+            0,
+        ];
+        let domainenc = [0, 0, 0, 1, 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e'];
+        let fragmentsenc = [0, 0, 0, 0];
+        let tldlist = [0, 0, 0, 1, 1, 3, b'c', b'o', b'm'];
+        Censor::decode(&badenc, &domainenc, &fragmentsenc, &tldlist).expect("decode")
+    }
+
+    #[test]
+    pub fn censor_bad_words() {
+        let censor = censor();
+        assert_eq!(censor.censor("oh darn it"), "oh **** it");
+        assert_eq!(censor.censor("oh d4rn it"), "oh **** it");
+        assert_eq!(censor.censor("Oh DARN"), "Oh ****");
+        assert_eq!(censor.censor("hello there"), "hello there");
+        assert!(!censor.is_censored("cook"));
+    }
+
+    #[test]
+    pub fn censor_domains() {
+        let censor = censor();
+        assert_eq!(censor.censor("visit example.com"), "visit ***********");
+        assert_eq!(censor.censor("come on"), "come on");
+    }
+
+    #[test]
+    pub fn censor_ip_address() {
+        let censor = censor();
+        assert_eq!(censor.censor("join 127.0.0.1 now"), "join ********* now");
+        assert_eq!(censor.censor("i have 1000 gp"), "i have 1000 gp");
+        let digits = "1".repeat(40);
+        assert_eq!(censor.censor(&digits), digits);
+    }
+
+    #[test]
+    pub fn error_truncated() {
+        assert!(Censor::decode(&[0, 0, 0, 1, 4, b'd'], &[0; 4], &[0; 4], &[0; 4]).is_err());
+    }
+}
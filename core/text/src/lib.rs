@@ -1,5 +1,9 @@
 use ahash::AHashMap;
 
+pub use censor::Censor;
+
+mod censor;
+
 const FREQUENCY_ORDERED_CHARS: [char; 61] = [
     ' ', 'e', 't', 'a', 'o', 'i', 'h', 'n', 's', 'r', 'd', 'l', 'u', 'm', 'w', 'c', 'y', 'f', 'g',
     'p', 'b', 'v', 'k', 'x', 'j', 'q', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ' ',
@@ -40,7 +44,7 @@ pub fn decode_base37(mut input: u64) -> anyhow::Result<String> {
         let local_input = input;
         input /= 37;
         index += 1;
-        result[11 - index] = VALID_NAME_CHARS[(local_input - input * 37) as usize];
+        result[12 - index] = VALID_NAME_CHARS[(local_input - input * 37) as usize];
     }
     Ok(result.iter().filter(|c| **c != '\0').collect::<String>())
 }
@@ -156,5 +160,10 @@ mod tests {
     pub fn test_decode_base37() {
         assert_eq!(decode_base37(36_292_611).unwrap(), String::from("smrkn"));
         assert_eq!(decode_base37(4818).unwrap(), String::from("csh"));
+        let longest = encode_base37("mithrilrs123");
+        assert_eq!(
+            decode_base37(longest).unwrap(),
+            String::from("mithrilrs123")
+        );
     }
 }
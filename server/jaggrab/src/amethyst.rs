@@ -2,6 +2,7 @@ use std::collections::VecDeque;
 use std::io;
 use std::net::{TcpListener, TcpStream};
 use std::ops::DerefMut;
+use std::sync::Arc;
 
 use amethyst::{
     core::SystemBundle,
@@ -93,7 +94,7 @@ struct JaggrabSendingSystem;
 impl<'a> System<'a> for JaggrabSendingSystem {
     type SystemData = (
         Write<'a, JaggrabServerResource>,
-        ReadExpect<'a, CacheFileSystem>,
+        ReadExpect<'a, Arc<CacheFileSystem>>,
     );
 
     fn run(&mut self, (mut net, cache): Self::SystemData) {
@@ -10,13 +10,14 @@ use amethyst::{
 };
 
 use ahash::AHashMap;
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{BufMut, BytesMut};
 use mithril_core::net::{
     self,
     packets::{HandshakeConnectResponse, HandshakeExchangeKey, LoginResponse},
+    RsaKey,
 };
 use mithril_server_types::auth::Authenticator;
-use mithril_server_types::{ConnectionIsaac, NetworkAddress, NewPlayer};
+use mithril_server_types::{ConnectionDecoder, ConnectionIsaac, NetworkAddress, NewPlayer};
 use std::collections::VecDeque;
 use std::net::SocketAddr;
 
@@ -98,9 +99,13 @@ impl<'a> System<'a> for MithrilEntityManagementSystem {
         Read<'a, EventChannel<NetworkSimulationEvent>>,
         Write<'a, PlayerEntitiesResource>,
         WriteStorage<'a, NetworkAddress>,
+        WriteStorage<'a, ConnectionDecoder>,
     );
 
-    fn run(&mut self, (entities, net_events, mut players, mut network_address): Self::SystemData) {
+    fn run(
+        &mut self,
+        (entities, net_events, mut players, mut network_address, mut decoders): Self::SystemData,
+    ) {
         #[cfg(feature = "profiler")]
         profile_scope!("entity management");
         for event in net_events.read(&mut self.reader) {
@@ -111,6 +116,7 @@ impl<'a> System<'a> for MithrilEntityManagementSystem {
                         entities
                             .build_entity()
                             .with(NetworkAddress(*addr), &mut network_address)
+                            .with(ConnectionDecoder::default(), &mut decoders)
                             .build()
                     });
                 }
@@ -212,9 +218,14 @@ impl<'a> System<'a> for MithrilDecodingSystem {
         Read<'a, PlayerEntitiesResource>,
         Write<'a, PacketEventChannel>,
         WriteStorage<'a, ConnectionIsaac>,
+        WriteStorage<'a, ConnectionDecoder>,
+        Option<Read<'a, RsaKey>>,
     );
 
-    fn run(&mut self, (net_events, players, mut incoming, mut rng): Self::SystemData) {
+    fn run(
+        &mut self,
+        (net_events, players, mut incoming, mut rng, mut decoders, key): Self::SystemData,
+    ) {
         #[cfg(feature = "profiler")]
         profile_scope!("packet decoding");
         for event in net_events.read(&mut self.reader) {
@@ -226,31 +237,43 @@ impl<'a> System<'a> for MithrilDecodingSystem {
 
                 log::info!("{}: {:?}", addr, payload);
 
-                let mut payload = {
-                    let mut buf = bytes::BytesMut::with_capacity(payload.len());
-                    buf.extend_from_slice(payload);
-                    buf
+                let decoder = match decoders.get_mut(entity) {
+                    Some(decoder) => &mut decoder.0,
+                    None => continue,
                 };
 
                 /*
-                 * Multiple packets may arrive in a single payload due to the timing of flushes.
-                 * Tokio previously made this behaviour transparent, here we have a while loop to
-                 * restore the effect.
-                 *
-                 * An example of packets that may arrive together are MouseClicked and PrivacyOption
+                 * A payload may hold several packets due to the timing of flushes, such as
+                 * MouseClicked and PrivacyOption, or only part of a packet that was split across
+                 * TCP segments. Whatever is left over is kept until the next payload arrives.
                  */
-                while payload.has_remaining() {
+                decoder.extend(payload);
+                loop {
                     let packet = match rng.get_mut(entity) {
-                        Some(isaac) => net::decode_packet(Some(&mut isaac.decoding), &mut payload),
-                        None => net::decode_packet(None, &mut payload),
+                        Some(isaac) => decoder.decode(Some(&mut isaac.decoding)),
+                        None => decoder.decode(None),
                     };
 
-                    match packet {
-                        Ok(packet) => incoming.single_write((entity, packet)),
-                        Err(cause) => {
-                            log::error!("Failed to decode packet; {}", cause);
-                            break;
+                    // Without a key the server accepts the plaintext login blocks sent by clients
+                    // with RSA disabled.
+                    let packet = packet.and_then(|packet| {
+                        let mut packet = match packet {
+                            Some(packet) => packet,
+                            None => return Ok(None),
+                        };
+                        if let PacketEvent::Handshake(HandshakeEvent::HandshakeAttemptConnect(
+                            attempt,
+                        )) = &mut packet
+                        {
+                            attempt.read_login_block(key.as_deref())?;
                         }
+                        Ok(Some(packet))
+                    });
+
+                    match packet {
+                        Ok(Some(packet)) => incoming.single_write((entity, packet)),
+                        Ok(None) => break,
+                        Err(cause) => log::error!("Failed to decode packet; {}", cause),
                     }
                 }
             }
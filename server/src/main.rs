@@ -1,4 +1,5 @@
 use std::net::TcpListener;
+use std::sync::Arc;
 use std::time::Duration;
 
 use amethyst::core::frame_limiter::FrameRateLimitStrategy;
@@ -7,7 +8,11 @@ use amethyst::{
 };
 
 use mithril::{
-    core::fs::CacheFileSystem,
+    core::{
+        fs::{ArchiveError, CacheFileSystem},
+        net::RsaKey,
+        text::Censor,
+    },
     net::MithrilNetworkBundle,
     player::PlayerEntityBundle,
     types::{
@@ -74,7 +79,7 @@ impl SimpleState for LoadingState {
 
         match CacheFileSystem::open(cache_path) {
             Ok(cache) => {
-                data.world.insert(cache);
+                data.world.insert(Arc::new(cache));
             }
             Err(cause) => {
                 log::error!("Failed to open cache; {}", cause);
@@ -82,8 +87,9 @@ impl SimpleState for LoadingState {
             }
         };
 
-        let mut cache = data.world.get_mut::<CacheFileSystem>().unwrap();
-        match CollisionDetector::new(&mut cache) {
+        let cache = Arc::clone(&data.world.read_resource::<Arc<CacheFileSystem>>());
+        let detector = CollisionDetector::new(cache);
+        match detector {
             Ok(detector) => data.world.insert(detector),
             Err(cause) => {
                 log::error!("Failed to map collisions; {}", cause);
@@ -91,6 +97,27 @@ impl SimpleState for LoadingState {
             }
         }
 
+        let censor = load_censor(&data.world.read_resource::<Arc<CacheFileSystem>>());
+        match censor {
+            Ok(censor) => data.world.insert(censor),
+            Err(cause) => {
+                log::error!("Failed to load word filter; {}", cause);
+                return;
+            }
+        }
+
+        match load_login_key() {
+            Ok(Some(key)) => {
+                log::info!("Login blocks will be decrypted with {:?}", key);
+                data.world.insert(key);
+            }
+            Ok(None) => log::warn!("No RSA key configured; login blocks will be read in plaintext"),
+            Err(cause) => {
+                log::error!("Failed to load RSA key; {}", cause);
+                return;
+            }
+        }
+
         data.world.insert(Authenticator::new(AlwaysAllowStrategy));
         self.loaded = true;
     }
@@ -104,6 +131,38 @@ impl SimpleState for LoadingState {
     }
 }
 
+fn load_censor(cache: &CacheFileSystem) -> anyhow::Result<Censor> {
+    let archive = cache.get_archive(0, 7)?;
+    let entry = |name: &'static str| {
+        archive
+            .get_entry(name)
+            .map(|entry| entry.contents())
+            .ok_or(ArchiveError::EntryNotFound(name))
+    };
+    Censor::decode(
+        &entry("badenc.txt")?,
+        &entry("domainenc.txt")?,
+        &entry("fragmentsenc.txt")?,
+        &entry("tldlist.txt")?,
+    )
+}
+
+/// Loads the key login blocks are encrypted with, either from the PEM file named by
+/// `MITHRIL_RSA_KEY` or from the decimal `MITHRIL_RSA_MODULUS` and `MITHRIL_RSA_EXPONENT`.
+fn load_login_key() -> anyhow::Result<Option<RsaKey>> {
+    if let Ok(path) = std::env::var("MITHRIL_RSA_KEY") {
+        let pem = std::fs::read_to_string(path)?;
+        return RsaKey::from_pem(&pem).map(Some);
+    }
+    match (
+        std::env::var("MITHRIL_RSA_MODULUS"),
+        std::env::var("MITHRIL_RSA_EXPONENT"),
+    ) {
+        (Ok(modulus), Ok(exponent)) => RsaKey::from_decimal(&modulus, &exponent).map(Some),
+        _ => Ok(None),
+    }
+}
+
 pub struct GameState;
 
 impl SimpleState for GameState {
@@ -0,0 +1,67 @@
+//! The clipping flags of a tile, matching the flags of the client's collision maps.
+//!
+//! Wall flags are set on both tiles either side of a wall, so the tile west of a wall on the
+//! east edge of a tile has [`WALL_WEST`] set. Every flag that blocks movement has a matching
+//! projectile flag, set only when the object also blocks projectiles.
+
+pub const WALL_NORTH_WEST: u32 = 0x1;
+pub const WALL_NORTH: u32 = 0x2;
+pub const WALL_NORTH_EAST: u32 = 0x4;
+pub const WALL_EAST: u32 = 0x8;
+pub const WALL_SOUTH_EAST: u32 = 0x10;
+pub const WALL_SOUTH: u32 = 0x20;
+pub const WALL_SOUTH_WEST: u32 = 0x40;
+pub const WALL_WEST: u32 = 0x80;
+pub const OBJECT: u32 = 0x100;
+
+pub const PROJECTILE_WALL_NORTH_WEST: u32 = 0x200;
+pub const PROJECTILE_WALL_NORTH: u32 = 0x400;
+pub const PROJECTILE_WALL_NORTH_EAST: u32 = 0x800;
+pub const PROJECTILE_WALL_EAST: u32 = 0x1000;
+pub const PROJECTILE_WALL_SOUTH_EAST: u32 = 0x2000;
+pub const PROJECTILE_WALL_SOUTH: u32 = 0x4000;
+pub const PROJECTILE_WALL_SOUTH_WEST: u32 = 0x8000;
+pub const PROJECTILE_WALL_WEST: u32 = 0x1_0000;
+pub const PROJECTILE_OBJECT: u32 = 0x2_0000;
+
+/// Set on tiles occupied by a solid, interactive floor decoration.
+pub const FLOOR_DECORATION: u32 = 0x4_0000;
+
+/// Set on tiles the map marks as blocked, such as water and cliffs.
+pub const BLOCKED: u32 = 0x20_0000;
+
+/// Flags that stop an entity from standing on the tile at all.
+pub const IMPASSABLE: u32 = OBJECT | FLOOR_DECORATION | BLOCKED;
+
+/// The wall flag on the side or corner of a tile facing the neighbouring tile offset by `dx`
+/// and `dy`, or `0` if the offset is not to a neighbour.
+pub fn wall(dx: i16, dy: i16) -> u32 {
+    match (dx, dy) {
+        (-1, 1) => WALL_NORTH_WEST,
+        (0, 1) => WALL_NORTH,
+        (1, 1) => WALL_NORTH_EAST,
+        (1, 0) => WALL_EAST,
+        (1, -1) => WALL_SOUTH_EAST,
+        (0, -1) => WALL_SOUTH,
+        (-1, -1) => WALL_SOUTH_WEST,
+        (-1, 0) => WALL_WEST,
+        _ => 0,
+    }
+}
+
+/// Converts wall or object flags to the matching flags that block projectiles.
+pub fn projectile(flags: u32) -> u32 {
+    flags << 9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn projectile_flags() {
+        assert_eq!(projectile(WALL_NORTH_WEST), PROJECTILE_WALL_NORTH_WEST);
+        assert_eq!(projectile(WALL_WEST), PROJECTILE_WALL_WEST);
+        assert_eq!(projectile(OBJECT), PROJECTILE_OBJECT);
+    }
+}
@@ -1,76 +1,432 @@
+use std::sync::{Arc, OnceLock};
+
 use ahash::AHashMap;
-use mithril_core::fs::{defs, CacheFileSystem};
-use mithril_core::pos::Position;
-use pathfinding::grid::Grid;
+use mithril_core::fs::defs::{MapObject, ObjectDefinition};
+use mithril_core::fs::CacheFileSystem;
+use mithril_core::pos::{Direction, Position};
+
+use crate::clipping::*;
+use crate::world_map::{region_key, REGION_SIZE};
+use crate::WorldMap;
+
+const PLANES: usize = 4;
+
+const STRAIGHT_WALL: u16 = 0;
+const DIAGONAL_CORNER_WALL: u16 = 1;
+const CORNER_WALL: u16 = 2;
+const SQUARE_CORNER_WALL: u16 = 3;
+const DIAGONAL_WALL: u16 = 9;
+const ROOF_EDGE_LAST: u16 = 21;
+const FLOOR_DECORATION_TYPE: u16 = 22;
 
+type RegionFlags = [[[u32; REGION_SIZE as usize]; REGION_SIZE as usize]; PLANES];
+
+/// The clipping flags of every tile in the world, built from the map tiles and the objects
+/// placed on them the first time a position within a region is looked up.
 #[derive(Debug)]
 pub struct CollisionDetector {
-    impassable: AHashMap<(u8, i16, i16), Grid>,
+    map: WorldMap,
+    objects: Vec<ObjectDefinition>,
+    regions: AHashMap<(u8, u8), OnceLock<Box<RegionFlags>>>,
 }
 
 impl CollisionDetector {
-    pub fn new(cache: &mut CacheFileSystem) -> anyhow::Result<Self> {
-        let map_indices = defs::MapIndex::load(cache)?;
-        let mut impassable = AHashMap::new();
-        for index in map_indices.values() {
-            let map_file = defs::MapFile::load(cache, index).expect("map file");
-            for plane in 0..4 {
-                let mut grid = Grid::new(64, 64);
-                grid.enable_diagonal_mode();
-                grid.add_borders();
-                grid.fill();
-
-                for x in 0..64 {
-                    for z in 0..64 {
-                        if !map_file.is_walkable(plane, x, z) || map_file.is_bridge(plane, x, z) {
-                            grid.remove_vertex(&(x, z));
+    pub fn new(cache: Arc<CacheFileSystem>) -> anyhow::Result<Self> {
+        let objects = ObjectDefinition::load(&cache)?;
+        let map = WorldMap::new(cache)?;
+        let regions = map
+            .region_keys()
+            .map(|key| (key, OnceLock::new()))
+            .collect();
+        Ok(Self {
+            map,
+            objects,
+            regions,
+        })
+    }
+
+    pub fn map(&self) -> &WorldMap {
+        &self.map
+    }
+
+    /// The clipping flags of the tile at `position`, or `None` if it is not part of the map.
+    pub fn flags(&self, position: Position) -> Option<u32> {
+        let key = region_key(position)?;
+        let flags = self
+            .regions
+            .get(&key)?
+            .get_or_init(|| self.clip_region(key));
+        let x = position.get_x().rem_euclid(REGION_SIZE) as usize;
+        let y = position.get_y().rem_euclid(REGION_SIZE) as usize;
+        Some(flags[position.get_plane() as usize][x][y])
+    }
+
+    pub fn is_traversable(&self, pos: Position) -> bool {
+        self.flags(pos).is_some_and(|flags| flags & IMPASSABLE == 0)
+    }
+
+    /// Whether an entity occupying a single tile can take a step from `from` in `direction`.
+    pub fn can_move(&self, from: Position, direction: Direction) -> bool {
+        self.can_move_with_size(from, direction, 1)
+    }
+
+    /// Whether an entity occupying a `size` by `size` square of tiles, with its south-west
+    /// corner at `from`, can take a step in `direction`.
+    ///
+    /// A diagonal step is only possible when both of the straight steps it is made up of are,
+    /// so entities cannot cut the corners of walls or objects.
+    pub fn can_move_with_size(&self, from: Position, direction: Direction, size: u8) -> bool {
+        let (dx, dy) = direction.delta();
+        if size <= 1 {
+            return self.can_step(from, dx, dy);
+        }
+
+        let size = i16::from(size);
+        self.can_step_with_size(from, dx, dy, size)
+            && (dx == 0
+                || dy == 0
+                || self.can_step_with_size(from, dx, 0, size)
+                    && self.can_step_with_size(from, 0, dy, size))
+    }
+
+    fn can_step_with_size(&self, from: Position, dx: i16, dy: i16, size: i16) -> bool {
+        // Every tile the entity moves onto must be open, and none may be separated from the rest
+        // of the entity by a wall. Tiles are relative to the south-west corner after the step.
+        let occupies = |x: i16, y: i16| (0..size).contains(&x) && (0..size).contains(&y);
+        for x in 0..size {
+            for y in 0..size {
+                if occupies(x + dx, y + dy) {
+                    continue;
+                }
+                let flags = match self.flags(from + (x + dx, y + dy)) {
+                    Some(flags) => flags,
+                    None => return false,
+                };
+                let walls = Direction::ALL
+                    .iter()
+                    .map(|direction| direction.delta())
+                    .filter(|(wall_x, wall_y)| occupies(x + wall_x, y + wall_y))
+                    .fold(IMPASSABLE, |mask, (wall_x, wall_y)| {
+                        mask | wall(wall_x, wall_y)
+                    });
+                if flags & walls != 0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn can_step(&self, from: Position, dx: i16, dy: i16) -> bool {
+        let open = |position: Position, walls: u32| {
+            self.flags(position)
+                .is_some_and(|flags| flags & (IMPASSABLE | walls) == 0)
+        };
+
+        let walls = wall(-dx, -dy) | wall(-dx, 0) | wall(0, -dy);
+        if !open(from + (dx, dy), walls) {
+            return false;
+        }
+        dx == 0
+            || dy == 0
+            || open(from + (dx, 0), wall(-dx, 0)) && open(from + (0, dy), wall(0, -dy))
+    }
+
+    fn clip_region(&self, (region_x, region_y): (u8, u8)) -> Box<RegionFlags> {
+        let origin = Position::new(
+            i16::from(region_x) * REGION_SIZE,
+            i16::from(region_y) * REGION_SIZE,
+        );
+        let mut region = RegionClipping::new(origin);
+
+        if let Some(map_file) = self.map.region(origin) {
+            for plane in 0..PLANES {
+                for x in 0..REGION_SIZE as usize {
+                    for y in 0..REGION_SIZE as usize {
+                        if map_file.is_walkable(plane, x, y) {
+                            continue;
+                        }
+                        let plane = if map_file.is_bridge(1, x, y) {
+                            plane.checked_sub(1)
+                        } else {
+                            Some(plane)
+                        };
+                        if let Some(plane) = plane {
+                            region.flags[plane][x][y] |= BLOCKED;
                         }
                     }
                 }
+            }
+        }
+
+        // Walls and large objects near the edge of a neighbouring region can clip tiles in this
+        // one, so the objects of every neighbour are clipped as well.
+        for offset_x in -1..=1 {
+            for offset_y in -1..=1 {
+                let neighbour = origin + (offset_x * REGION_SIZE, offset_y * REGION_SIZE);
+                for object in self.map.objects(neighbour).unwrap_or_default() {
+                    self.clip_object(&mut region, neighbour, object);
+                }
+            }
+        }
+        region.flags
+    }
+
+    fn clip_object(&self, region: &mut RegionClipping, origin: Position, object: &MapObject) {
+        let definition = match self.objects.get(object.id() as usize) {
+            Some(definition) => definition,
+            None => return,
+        };
 
-                let key = (
-                    plane as u8,
-                    index.get_x() as i16 / 64i16,
-                    index.get_y() as i16 / 64i16,
-                );
-                impassable.insert(key, grid);
+        // Objects placed beneath a bridge are clipped on the plane below, like the tiles.
+        let bridge = self.map.region(origin).is_some_and(|map_file| {
+            map_file.is_bridge(1, object.get_x() as usize, object.get_y() as usize)
+        });
+        let plane = object.get_plane() as usize;
+        let plane = match bridge {
+            true if plane == 0 => return,
+            true => plane - 1,
+            false => plane,
+        };
+
+        let x = origin.get_x() + object.get_x();
+        let y = origin.get_y() + object.get_y();
+        let impenetrable = definition.is_impenetrable();
+        match object.variant() {
+            STRAIGHT_WALL..=SQUARE_CORNER_WALL if definition.is_solid() => {
+                let walls = wall_flags(object.variant(), object.orientation());
+                region.clip_wall(plane, x, y, walls, impenetrable);
+            }
+            DIAGONAL_WALL..=ROOF_EDGE_LAST if definition.is_solid() => {
+                let (width, length) = match object.orientation() & 1 {
+                    1 => (definition.length(), definition.width()),
+                    _ => (definition.width(), definition.length()),
+                };
+                region.clip_occupant(plane, x, y, width, length, impenetrable);
             }
+            FLOOR_DECORATION_TYPE if definition.is_solid() && definition.is_interactive() => {
+                region.add(plane, x, y, FLOOR_DECORATION);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+impl CollisionDetector {
+    /// A detector for the region with its south-west corner at (3200, 3200), where every tile is
+    /// open apart from the tiles on plane 0 given with their flags.
+    pub(crate) fn with_tiles(tiles: &[(i16, i16, u32)]) -> Self {
+        let mut region = RegionClipping::new(Position::new(3200, 3200));
+        for &(x, y, flags) in tiles {
+            region.add(0, x, y, flags);
         }
+        Self::with_region(region)
+    }
 
-        Ok(Self { impassable })
+    fn with_region(region: RegionClipping) -> Self {
+        let key = region_key(Position::new(region.x, region.y)).expect("region");
+        let regions = std::iter::once((key, OnceLock::from(region.flags))).collect();
+        Self {
+            map: WorldMap::default(),
+            objects: Vec::new(),
+            regions,
+        }
     }
+}
 
-    pub fn is_traversable(&self, pos: Position) -> bool {
-        let search_x = pos.get_x() / 64;
-        let search_y = pos.get_y() / 64;
-        match self.impassable.get(&(pos.get_plane(), search_x, search_y)) {
-            Some(grid) => {
-                let (local_x, local_y) = pos.get_relative(pos);
-                grid.has_vertex(&(local_x as _, local_y as _))
+/// The flags of a region being clipped, ignoring anything outside of the region.
+struct RegionClipping {
+    x: i16,
+    y: i16,
+    flags: Box<RegionFlags>,
+}
+
+impl RegionClipping {
+    fn new(origin: Position) -> Self {
+        Self {
+            x: origin.get_x(),
+            y: origin.get_y(),
+            flags: Box::new([[[0; REGION_SIZE as usize]; REGION_SIZE as usize]; PLANES]),
+        }
+    }
+
+    fn add(&mut self, plane: usize, x: i16, y: i16, flags: u32) {
+        let (x, y) = (x - self.x, y - self.y);
+        if (0..REGION_SIZE).contains(&x) && (0..REGION_SIZE).contains(&y) {
+            self.flags[plane][x as usize][y as usize] |= flags;
+        }
+    }
+
+    fn clip_wall(&mut self, plane: usize, x: i16, y: i16, walls: &[Wall], impenetrable: bool) {
+        for &(offset_x, offset_y, flags) in walls {
+            self.add(plane, x + offset_x, y + offset_y, flags);
+            if impenetrable {
+                self.add(plane, x + offset_x, y + offset_y, projectile(flags));
             }
-            None => false,
         }
     }
+
+    fn clip_occupant(
+        &mut self,
+        plane: usize,
+        x: i16,
+        y: i16,
+        width: u8,
+        length: u8,
+        impenetrable: bool,
+    ) {
+        let flags = if impenetrable {
+            OBJECT | PROJECTILE_OBJECT
+        } else {
+            OBJECT
+        };
+        for offset_x in 0..i16::from(width) {
+            for offset_y in 0..i16::from(length) {
+                self.add(plane, x + offset_x, y + offset_y, flags);
+            }
+        }
+    }
+}
+
+/// The offset of a tile from the wall's position and the flags set on it.
+type Wall = (i16, i16, u32);
+
+/// The tiles clipped by a wall, which always include the tile the wall is placed on and the
+/// tiles on the other side of each of its edges.
+fn wall_flags(variant: u16, orientation: u8) -> &'static [Wall] {
+    match (variant, orientation & 3) {
+        (STRAIGHT_WALL, 0) => &[(0, 0, WALL_WEST), (-1, 0, WALL_EAST)],
+        (STRAIGHT_WALL, 1) => &[(0, 0, WALL_NORTH), (0, 1, WALL_SOUTH)],
+        (STRAIGHT_WALL, 2) => &[(0, 0, WALL_EAST), (1, 0, WALL_WEST)],
+        (STRAIGHT_WALL, _) => &[(0, 0, WALL_SOUTH), (0, -1, WALL_NORTH)],
+        (CORNER_WALL, 0) => &[
+            (0, 0, WALL_WEST | WALL_NORTH),
+            (-1, 0, WALL_EAST),
+            (0, 1, WALL_SOUTH),
+        ],
+        (CORNER_WALL, 1) => &[
+            (0, 0, WALL_NORTH | WALL_EAST),
+            (0, 1, WALL_SOUTH),
+            (1, 0, WALL_WEST),
+        ],
+        (CORNER_WALL, 2) => &[
+            (0, 0, WALL_EAST | WALL_SOUTH),
+            (1, 0, WALL_WEST),
+            (0, -1, WALL_NORTH),
+        ],
+        (CORNER_WALL, _) => &[
+            (0, 0, WALL_SOUTH | WALL_WEST),
+            (0, -1, WALL_NORTH),
+            (-1, 0, WALL_EAST),
+        ],
+        (DIAGONAL_CORNER_WALL | SQUARE_CORNER_WALL, 0) => {
+            &[(0, 0, WALL_NORTH_WEST), (-1, 1, WALL_SOUTH_EAST)]
+        }
+        (DIAGONAL_CORNER_WALL | SQUARE_CORNER_WALL, 1) => {
+            &[(0, 0, WALL_NORTH_EAST), (1, 1, WALL_SOUTH_WEST)]
+        }
+        (DIAGONAL_CORNER_WALL | SQUARE_CORNER_WALL, 2) => {
+            &[(0, 0, WALL_SOUTH_EAST), (1, -1, WALL_NORTH_WEST)]
+        }
+        (DIAGONAL_CORNER_WALL | SQUARE_CORNER_WALL, _) => {
+            &[(0, 0, WALL_SOUTH_WEST), (-1, -1, WALL_NORTH_EAST)]
+        }
+        _ => &[],
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::collision_detection::CollisionDetector;
-    use mithril_core::fs::CacheFileSystem;
-    use mithril_core::pos::Position;
+    use super::*;
+    use crate::{Destination, RouteFinder};
+
+    fn region() -> RegionClipping {
+        RegionClipping::new(Position::new(3200, 3200))
+    }
 
     #[test]
-    pub fn test_collisions() {
-        use pathfinding::prelude::{absdiff, astar};
+    pub fn walls_clip_both_sides() {
+        let mut region = region();
+        let walls = wall_flags(STRAIGHT_WALL, 2);
+        region.clip_wall(0, 3205, 3205, walls, true);
+        assert_eq!(region.flags[0][5][5], WALL_EAST | PROJECTILE_WALL_EAST);
+        assert_eq!(region.flags[0][6][5], WALL_WEST | PROJECTILE_WALL_WEST);
+
+        let walls = wall_flags(CORNER_WALL, 0);
+        region.clip_wall(1, 3200, 3205, walls, false);
+        assert_eq!(region.flags[1][0][5], WALL_WEST | WALL_NORTH);
+        assert_eq!(region.flags[1][0][6], WALL_SOUTH);
+
+        let walls = wall_flags(DIAGONAL_CORNER_WALL, 1);
+        region.clip_wall(2, 3210, 3210, walls, false);
+        assert_eq!(region.flags[2][11][11], WALL_SOUTH_WEST);
+    }
 
+    fn detector(clip: impl FnOnce(&mut RegionClipping)) -> CollisionDetector {
+        let mut region = region();
+        clip(&mut region);
+        CollisionDetector::with_region(region)
+    }
+
+    #[test]
+    pub fn walls_block_movement() {
+        let detector = detector(|region| {
+            region.clip_wall(0, 3210, 3210, wall_flags(STRAIGHT_WALL, 2), false);
+        });
+
+        let from = Position::new(3210, 3210);
+        assert!(detector.can_move(from, Direction::North));
+        assert!(detector.can_move(from, Direction::West));
+        assert!(!detector.can_move(from, Direction::East));
+        assert!(
+            !detector.can_move(from, Direction::NorthEast),
+            "cut the corner"
+        );
+        assert!(!detector.can_move(from + (1, 0), Direction::West));
+        assert!(!detector.can_move(from + (1, 1), Direction::SouthWest));
+        assert!(detector.can_move(from + (1, 1), Direction::West));
+        assert!(!detector.can_move(Position::new(3263, 3210), Direction::East));
+    }
+
+    #[test]
+    pub fn large_entities() {
+        let detector = detector(|region| {
+            region.clip_occupant(0, 3214, 3212, 1, 1, false);
+            region.clip_wall(0, 3212, 3210, wall_flags(STRAIGHT_WALL, 1), false);
+        });
+
+        let from = Position::new(3210, 3210);
+        assert!(detector.can_move_with_size(from, Direction::North, 2));
+        assert!(!detector.can_move_with_size(from, Direction::East, 2));
+        assert!(!detector.can_move_with_size(from + (2, 1), Direction::East, 2));
+        assert!(detector.can_move_with_size(from + (2, 1), Direction::North, 2));
+        assert!(detector.can_move_with_size(from + (1, 2), Direction::South, 2));
+        assert!(!detector.can_move_with_size(from + (1, 1), Direction::South, 2));
+        assert!(
+            !detector.can_move_with_size(from + (2, 2), Direction::NorthEast, 2),
+            "cut the corner"
+        );
+        assert!(detector.can_move_with_size(from + (2, 3), Direction::NorthEast, 2));
+    }
+
+    #[test]
+    pub fn occupants_outside_region_are_ignored() {
+        let mut region = region();
+        region.clip_occupant(0, 3198, 3263, 3, 2, false);
+        assert_eq!(region.flags[0][0][63], OBJECT);
+        assert_eq!(region.flags[0][1][63], 0);
+        assert_eq!(region.flags[0][0][62], 0);
+    }
+
+    #[test]
+    pub fn test_collisions() {
         if ci_info::is_ci() {
             return;
         }
 
-        let mut cache = CacheFileSystem::open("../../cache").expect("cache");
-        let detector = CollisionDetector::new(&mut cache).expect("detector");
-
-        println!("Map coordinates loaded, beginning pathfinding");
+        let cache = CacheFileSystem::open("../../cache").expect("cache");
+        let detector = CollisionDetector::new(Arc::new(cache)).expect("detector");
 
         let start = Position::default();
         let goal = Position::new(start.get_x() + 2, start.get_y() + 4);
@@ -80,36 +436,9 @@ mod tests {
         );
         assert!(detector.is_traversable(goal), "goal is non-traversable");
 
-        let result = astar(
-            &start,
-            |&pos| {
-                let mut successors = Vec::with_capacity(8);
-                for y in -1..=1 {
-                    for x in -1..=1 {
-                        if x == 0 && y == 0 {
-                            continue;
-                        }
-                        let successor = pos + (x, y);
-                        if detector.is_traversable(successor) {
-                            successors.push((successor, 1));
-                        }
-                    }
-                }
-                println!("Found {} successors to {:?}", successors.len(), pos);
-                successors.into_iter()
-            },
-            |&pos| absdiff(pos.get_x(), goal.get_x()) + absdiff(pos.get_y(), goal.get_y()),
-            |&pos| pos.get_x() == goal.get_x() && pos.get_y() == goal.get_y(),
-        );
-
-        match result {
-            Some((path, cost)) => {
-                dbg!(cost);
-                dbg!(path);
-            }
-            None => {
-                println!("No path found between {:?} and {:?}", start, goal);
-            }
-        }
+        let route = RouteFinder::new(&detector)
+            .find(start, Destination::Tile(goal))
+            .expect("route");
+        assert_eq!(route.last(), Some(&goal));
     }
 }
@@ -2,8 +2,7 @@ use mithril_core::pos::Position;
 use specs::{Component, VecStorage};
 use std::collections::VecDeque;
 
-use crate::CollisionDetector;
-use pathfinding::prelude::{absdiff, astar};
+use crate::{CollisionDetector, Destination, RouteFinder, DEFAULT_MAX_STEPS};
 
 #[derive(Debug, Default)]
 pub struct PreviousPosition(pub Position, pub Option<Position>);
@@ -22,6 +21,7 @@ impl Component for PreviousPosition {
 pub struct Pathfinder {
     points: VecDeque<Position>,
     running: bool,
+    max_steps: u16,
 }
 
 impl Pathfinder {
@@ -33,6 +33,16 @@ impl Pathfinder {
         self.running = running
     }
 
+    /// Limits the number of steps queued by [`walk_path`](Self::walk_path).
+    pub fn set_max_steps(&mut self, max_steps: u16) {
+        self.max_steps = max_steps
+    }
+
+    /// Queues a route from `from` through each waypoint of `path` in turn.
+    ///
+    /// Like the client, a waypoint that cannot be reached is replaced by the closest tile that
+    /// can, after which the rest of the path is ignored. The route is cut short once it is
+    /// `max_steps` long, however far the waypoints are from each other.
     pub fn walk_path(&mut self, detector: &CollisionDetector, from: Position, path: Vec<Position>) {
         if path.is_empty() {
             return;
@@ -40,40 +50,30 @@ impl Pathfinder {
 
         self.clear();
 
-        let mut full_route = VecDeque::new();
-        let mut starting_point = from;
+        let mut start = from;
         for point in path {
-            match astar(
-                &starting_point,
-                |&pos| {
-                    let mut successors = Vec::with_capacity(8);
-                    for y in -1..=1 {
-                        for x in -1..=1 {
-                            if x == 0 && y == 0 {
-                                continue;
-                            }
-                            let successor = pos + (x, y);
-                            if detector.is_traversable(successor) {
-                                successors.push((successor, 1));
-                            }
-                        }
-                    }
-                    println!("Found {} successors to {:?}", successors.len(), pos);
-                    successors.into_iter()
-                },
-                |&pos| absdiff(pos.get_x(), point.get_x()) + absdiff(pos.get_y(), point.get_y()),
-                |&pos| pos == point,
-            ) {
-                Some((route, _steps)) => {
-                    full_route.extend(route);
-                }
-                None => break,
+            let remaining = self.max_steps.saturating_sub(self.points.len() as u16);
+            if remaining == 0 {
+                break;
             }
 
-            starting_point = point;
-        }
+            let point = Position::new_with_height(point.get_x(), point.get_y(), from.get_plane())
+                .expect("plane of a valid position");
+            let route = match RouteFinder::new(detector)
+                .with_max_steps(remaining)
+                .find(start, Destination::Tile(point))
+            {
+                Some(route) => route,
+                None => break,
+            };
 
-        self.points.append(&mut full_route);
+            let reached = route.last().map_or(start == point, |end| *end == point);
+            self.points.extend(route);
+            if !reached {
+                break;
+            }
+            start = point;
+        }
     }
 
     pub fn next_step(&mut self) -> Option<Position> {
@@ -91,6 +91,7 @@ impl Default for Pathfinder {
         Self {
             points: VecDeque::with_capacity(16),
             running: false,
+            max_steps: DEFAULT_MAX_STEPS,
         }
     }
 }
@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 
+use mithril_core::net::PacketDecoder;
 use specs::{Component, VecStorage};
 
 use rand::SeedableRng;
@@ -11,6 +12,11 @@ impl Component for NetworkAddress {
     type Storage = VecStorage<Self>;
 }
 
+/// Bytes received from the connection that are yet to be decoded into packets.
+#[derive(Component, Debug, Default)]
+#[storage(VecStorage)]
+pub struct ConnectionDecoder(pub PacketDecoder);
+
 #[derive(Component)]
 #[storage(VecStorage)]
 pub struct ConnectionIsaac {
@@ -1,8 +1,13 @@
 pub mod auth;
+pub mod clipping;
 mod collision_detection;
 pub mod components;
 mod id_allocator;
+mod route_finder;
+mod world_map;
 
 pub use collision_detection::CollisionDetector;
 pub use components::*;
 pub use id_allocator::IdAllocator;
+pub use route_finder::{Destination, RouteFinder, DEFAULT_MAX_STEPS, SEARCH_SIZE};
+pub use world_map::WorldMap;
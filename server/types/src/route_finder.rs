@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+
+use mithril_core::pos::{Direction, Position};
+
+use crate::clipping::wall;
+use crate::CollisionDetector;
+
+/// The width and length of the area the client searches for a route, which is the area of the
+/// map it has loaded around the player.
+pub const SEARCH_SIZE: i16 = 104;
+
+/// The default length of the longest route that will be searched for, in steps.
+pub const DEFAULT_MAX_STEPS: u16 = 100;
+
+/// How far from the destination a tile can be and still be walked to when the destination
+/// itself cannot be reached.
+const ALTERNATIVE_RADIUS: i16 = 10;
+
+/// The order the client considers each direction in, which decides between routes of the same
+/// length.
+const SEARCH_ORDER: [Direction; 8] = [
+    Direction::West,
+    Direction::East,
+    Direction::South,
+    Direction::North,
+    Direction::SouthWest,
+    Direction::SouthEast,
+    Direction::NorthWest,
+    Direction::NorthEast,
+];
+
+/// Where a route should end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    /// Ends on the tile.
+    Tile(Position),
+    /// Ends beside an entity occupying a `size` by `size` square, with its south-west corner at
+    /// the position.
+    Entity { position: Position, size: u8 },
+    /// Ends beside an object occupying `width` by `length` tiles, with its south-west corner at
+    /// the position.
+    Object {
+        position: Position,
+        width: u8,
+        length: u8,
+    },
+}
+
+impl Destination {
+    /// The south-west corner and size of the area the destination occupies.
+    fn bounds(self) -> (Position, i16, i16) {
+        match self {
+            Destination::Tile(position) => (position, 1, 1),
+            Destination::Entity { position, size } => (position, i16::from(size), i16::from(size)),
+            Destination::Object {
+                position,
+                width,
+                length,
+            } => (position, i16::from(width), i16::from(length)),
+        }
+    }
+}
+
+/// Finds routes the same way as the client, with a breadth-first search of the area around the
+/// start of the route.
+#[derive(Debug)]
+pub struct RouteFinder<'a> {
+    detector: &'a CollisionDetector,
+    max_steps: u16,
+}
+
+impl<'a> RouteFinder<'a> {
+    pub fn new(detector: &'a CollisionDetector) -> Self {
+        Self {
+            detector,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// Limits the length of the routes that are searched for.
+    pub fn with_max_steps(mut self, max_steps: u16) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Finds the shortest route from `start` to `destination`, returning every tile stepped on
+    /// after the start.
+    ///
+    /// When the destination cannot be reached, the route ends on the reachable tile closest to
+    /// it instead. `None` is returned when no tile near the destination can be reached.
+    pub fn find(&self, start: Position, destination: Destination) -> Option<Vec<Position>> {
+        let search = Search::new(start);
+        let (target, width, length) = destination.bounds();
+        if target.get_plane() != start.get_plane() {
+            return None;
+        }
+
+        let mut via = vec![None; (SEARCH_SIZE * SEARCH_SIZE) as usize];
+        let mut cost = vec![u16::MAX; (SEARCH_SIZE * SEARCH_SIZE) as usize];
+        let mut queue = VecDeque::new();
+        let index = search.index(start)?;
+        cost[index] = 0;
+        queue.push_back(start);
+
+        let mut end = None;
+        while let Some(current) = queue.pop_front() {
+            if self.reached(current, destination) {
+                end = Some(current);
+                break;
+            }
+
+            let steps = cost[search.index(current)?];
+            if steps >= self.max_steps {
+                continue;
+            }
+            for direction in SEARCH_ORDER.iter() {
+                let next = current + direction.delta();
+                let index = match search.index(next) {
+                    Some(index) if cost[index] == u16::MAX => index,
+                    _ => continue,
+                };
+                if self.detector.can_move(current, *direction) {
+                    via[index] = Some(*direction);
+                    cost[index] = steps + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let end = match end {
+            Some(end) => end,
+            None => self.closest(&search, &cost, target, width, length)?,
+        };
+
+        let mut route = Vec::new();
+        let mut current = end;
+        while let Some(direction) = via[search.index(current)?] {
+            route.push(current);
+            let (dx, dy) = direction.delta();
+            current = current + (-dx, -dy);
+        }
+        route.reverse();
+        Some(route)
+    }
+
+    /// Whether an entity standing at `position` has arrived at the destination. Entities and
+    /// objects are reached from any tile beside one of their edges that is not behind a wall.
+    fn reached(&self, position: Position, destination: Destination) -> bool {
+        let (target, width, length) = destination.bounds();
+        if let Destination::Tile(_) = destination {
+            return position == target;
+        }
+
+        let x = position.get_x() - target.get_x();
+        let y = position.get_y() - target.get_y();
+        let (dx, dy) = if (0..width).contains(&x) && y == -1 {
+            (0, 1)
+        } else if (0..width).contains(&x) && y == length {
+            (0, -1)
+        } else if (0..length).contains(&y) && x == -1 {
+            (1, 0)
+        } else if (0..length).contains(&y) && x == width {
+            (-1, 0)
+        } else {
+            return false;
+        };
+        self.detector
+            .flags(position)
+            .is_some_and(|flags| flags & wall(dx, dy) == 0)
+    }
+
+    /// The reached tile closest to the destination, preferring the shortest route when several
+    /// are as close, as the client does when a destination cannot be reached.
+    fn closest(
+        &self,
+        search: &Search,
+        cost: &[u16],
+        target: Position,
+        width: i16,
+        length: i16,
+    ) -> Option<Position> {
+        let distance = |offset: i16, size: i16| {
+            if offset < 0 {
+                -offset
+            } else if offset >= size {
+                offset - size + 1
+            } else {
+                0
+            }
+        };
+
+        let mut closest = None;
+        for x in -ALTERNATIVE_RADIUS..width + ALTERNATIVE_RADIUS {
+            for y in -ALTERNATIVE_RADIUS..length + ALTERNATIVE_RADIUS {
+                let position = target + (x, y);
+                let steps = match search.index(position) {
+                    Some(index) if cost[index] != u16::MAX => cost[index],
+                    _ => continue,
+                };
+                let (dx, dy) = (distance(x, width), distance(y, length));
+                let key = (dx * dx + dy * dy, steps);
+                if closest.is_none_or(|(closest, _)| key < closest) {
+                    closest = Some((key, position));
+                }
+            }
+        }
+        closest.map(|(_, position)| position)
+    }
+}
+
+/// The area searched for a route, matching the area of the map loaded by the client.
+struct Search {
+    x: i16,
+    y: i16,
+    plane: u8,
+}
+
+impl Search {
+    fn new(start: Position) -> Self {
+        Self {
+            x: start.get_region_x() * 8,
+            y: start.get_region_y() * 8,
+            plane: start.get_plane(),
+        }
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        let x = position.get_x() - self.x;
+        let y = position.get_y() - self.y;
+        let bounds = 0..SEARCH_SIZE;
+        if position.get_plane() != self.plane || !bounds.contains(&x) || !bounds.contains(&y) {
+            return None;
+        }
+        Some((x * SEARCH_SIZE + y) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipping::*;
+
+    #[test]
+    pub fn route_around_walls() {
+        let detector = CollisionDetector::with_tiles(&[
+            (3220, 3220, WALL_EAST),
+            (3221, 3220, WALL_WEST),
+            (3220, 3221, OBJECT),
+        ]);
+        let finder = RouteFinder::new(&detector);
+
+        let start = Position::new(3220, 3220);
+        let route = finder
+            .find(start, Destination::Tile(start + (1, 0)))
+            .expect("route");
+        assert_eq!(
+            route,
+            vec![start + (0, -1), start + (1, -1), start + (1, 0)],
+            "should step around the wall without cutting its corners"
+        );
+    }
+
+    #[test]
+    pub fn route_to_closest_tile() {
+        let detector = CollisionDetector::with_tiles(&[(3225, 3220, BLOCKED)]);
+        let finder = RouteFinder::new(&detector);
+
+        let start = Position::new(3220, 3220);
+        let route = finder
+            .find(start, Destination::Tile(start + (5, 0)))
+            .expect("route");
+        assert_eq!(route.last(), Some(&(start + (4, 0))));
+        assert_eq!(route.len(), 4);
+    }
+
+    #[test]
+    pub fn route_beside_entities() {
+        let detector = CollisionDetector::with_tiles(&[(3224, 3222, WALL_EAST)]);
+        let finder = RouteFinder::new(&detector);
+
+        let start = Position::new(3220, 3222);
+        let destination = Destination::Entity {
+            position: start + (5, 0),
+            size: 2,
+        };
+        let route = finder.find(start, destination).expect("route");
+        assert_eq!(
+            route.last(),
+            Some(&(start + (4, 1))),
+            "the entity cannot be reached through a wall"
+        );
+        assert!(finder.reached(start + (5, 2), destination));
+        assert!(!finder.reached(start + (6, 0), destination));
+        assert!(!finder.reached(start + (4, 2), destination));
+    }
+
+    #[test]
+    pub fn step_limit() {
+        let detector = CollisionDetector::with_tiles(&[]);
+        let finder = RouteFinder::new(&detector).with_max_steps(3);
+
+        let start = Position::new(3220, 3220);
+        let route = finder
+            .find(start, Destination::Tile(start + (8, 0)))
+            .expect("route");
+        assert_eq!(route.last(), Some(&(start + (3, 0))));
+        assert!(finder
+            .find(start, Destination::Tile(start + (20, 0)))
+            .is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use std::convert::TryFrom;
+use std::sync::{Arc, OnceLock};
+
+use ahash::AHashMap;
+use mithril_core::fs::defs::{MapFile, MapIndex, MapObject, Tile};
+use mithril_core::fs::CacheFileSystem;
+use mithril_core::pos::Position;
+
+pub(crate) const REGION_SIZE: i16 = 64;
+
+/// The tiles and objects of every region in the world, looked up by world position.
+///
+/// Only the map index is read up front; a region's map and object files are read from the cache
+/// and decoded the first time a position within it is looked up, after which they are kept for
+/// the lifetime of the map.
+#[derive(Debug, Default)]
+pub struct WorldMap {
+    regions: AHashMap<(u8, u8), MapRegion>,
+}
+
+#[derive(Debug)]
+struct MapRegion {
+    cache: Arc<CacheFileSystem>,
+    index: MapIndex,
+    tiles: OnceLock<Option<MapFile>>,
+    objects: OnceLock<Option<Vec<MapObject>>>,
+}
+
+impl WorldMap {
+    /// Reads the map index, keeping a handle to `cache` to load each region from later.
+    pub fn new(cache: Arc<CacheFileSystem>) -> anyhow::Result<Self> {
+        let regions = MapIndex::load(&cache)?
+            .into_values()
+            .map(|index| {
+                let key = (
+                    (index.get_x() / REGION_SIZE as u16) as u8,
+                    (index.get_y() / REGION_SIZE as u16) as u8,
+                );
+                let region = MapRegion {
+                    cache: Arc::clone(&cache),
+                    index,
+                    tiles: OnceLock::new(),
+                    objects: OnceLock::new(),
+                };
+                (key, region)
+            })
+            .collect();
+        Ok(Self { regions })
+    }
+
+    /// The decoded map file of the region containing `position`, loading it if this is the
+    /// first lookup within the region.
+    ///
+    /// Returns `None` if the region's map file is missing from the cache or fails to decode.
+    pub fn region(&self, position: Position) -> Option<&MapFile> {
+        self.regions.get(&region_key(position)?)?.tiles()
+    }
+
+    /// The objects placed in the region containing `position`, loading them if this is the
+    /// first lookup of the region's objects.
+    pub fn objects(&self, position: Position) -> Option<&[MapObject]> {
+        self.regions.get(&region_key(position)?)?.objects()
+    }
+
+    /// The keys of every region on the map, as returned by [`region_key`].
+    pub(crate) fn region_keys(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.regions.keys().copied()
+    }
+
+    pub fn tile(&self, position: Position) -> Option<&Tile> {
+        let x = position.get_x().rem_euclid(REGION_SIZE) as usize;
+        let y = position.get_y().rem_euclid(REGION_SIZE) as usize;
+        self.region(position)?
+            .get_tile(position.get_plane() as usize, x, y)
+    }
+
+    pub fn height(&self, position: Position) -> Option<u16> {
+        self.tile(position).map(|tile| tile.height())
+    }
+
+    /// The ID of the `FloorDefinition` painted beneath the tile at `position`.
+    pub fn underlay_id(&self, position: Position) -> Option<u8> {
+        self.tile(position)?.underlay_id()
+    }
+
+    /// The ID of the `FloorDefinition` painted over the tile at `position`.
+    pub fn overlay_id(&self, position: Position) -> Option<u8> {
+        self.tile(position)?.overlay_id()
+    }
+
+    /// Whether the tile at `position` can be walked on, which is never the case for tiles that
+    /// are not part of the map.
+    pub fn is_walkable(&self, position: Position) -> bool {
+        self.tile(position).is_some_and(|tile| tile.is_walkable())
+    }
+
+    pub fn is_bridge(&self, position: Position) -> bool {
+        self.tile(position).is_some_and(|tile| tile.is_bridge())
+    }
+}
+
+impl MapRegion {
+    fn tiles(&self) -> Option<&MapFile> {
+        let load = || match MapFile::load(&self.cache, &self.index) {
+            Ok(map_file) => Some(map_file),
+            Err(cause) => {
+                log::warn!("Failed to load map file of {:?}; {}", self.index, cause);
+                None
+            }
+        };
+        self.tiles.get_or_init(load).as_ref()
+    }
+
+    fn objects(&self) -> Option<&[MapObject]> {
+        let load = || match MapObject::load(&self.cache, &self.index) {
+            Ok(objects) => Some(objects),
+            Err(cause) => {
+                log::warn!("Failed to load object file of {:?}; {}", self.index, cause);
+                None
+            }
+        };
+        self.objects.get_or_init(load).as_deref()
+    }
+}
+
+/// The coordinates of the 64x64 region containing `position`.
+pub(crate) fn region_key(position: Position) -> Option<(u8, u8)> {
+    let x = u8::try_from(position.get_x().checked_div_euclid(REGION_SIZE)?).ok()?;
+    let y = u8::try_from(position.get_y().checked_div_euclid(REGION_SIZE)?).ok()?;
+    Some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn region_keys() {
+        assert_eq!(region_key(Position::new(3093, 3104)), Some((48, 48)));
+        assert_eq!(region_key(Position::new(3199, 3200)), Some((49, 50)));
+        assert_eq!(region_key(Position::new(-1, 3200)), None);
+        assert!(WorldMap::default().tile(Position::default()).is_none());
+    }
+
+    #[test]
+    pub fn lookup_across_regions() {
+        if ci_info::is_ci() {
+            return;
+        }
+
+        let cache = CacheFileSystem::open("../../cache").expect("cache");
+        let map = WorldMap::new(Arc::new(cache)).expect("map");
+
+        let position = Position::new(3199, 3200);
+        assert!(map.tile(position).is_some());
+        assert!(map.tile(position + (1, 0)).is_some());
+        assert!(map.height(position + (1, 0)).is_some());
+        assert!(map.region(position).is_some());
+    }
+}